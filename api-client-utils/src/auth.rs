@@ -0,0 +1,145 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use crate::unauthorized::{reissue_on_unauthorized, UnauthorizedHook};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::{Request, Response};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+/// Produces the `Authorization` header value for outgoing requests
+pub trait AuthProvider: Send + Sync {
+    fn auth_header(&self) -> impl Future<Output = anyhow::Result<HeaderValue>> + Send;
+    /// called when the API rejected the current credentials with a 401, drop any cached token here
+    fn invalidate(&self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+impl<P: AuthProvider> AuthProvider for Arc<P> {
+    fn auth_header(&self) -> impl Future<Output = anyhow::Result<HeaderValue>> + Send {
+        self.as_ref().auth_header()
+    }
+    fn invalidate(&self) -> impl Future<Output = ()> + Send {
+        self.as_ref().invalidate()
+    }
+}
+
+pub fn bearer_header(token: &str) -> anyhow::Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// A token that never changes, e.g. an API key
+#[derive(Clone)]
+pub struct StaticToken(HeaderValue);
+impl StaticToken {
+    pub fn bearer(token: &str) -> anyhow::Result<Self> {
+        Ok(Self(bearer_header(token)?))
+    }
+    pub fn header_value(value: HeaderValue) -> Self {
+        Self(value)
+    }
+}
+impl AuthProvider for StaticToken {
+    async fn auth_header(&self) -> anyhow::Result<HeaderValue> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A token that can be swapped at runtime (e.g. by a background refresh task) through any of its clones
+#[derive(Clone)]
+pub struct SharedToken(Arc<RwLock<HeaderValue>>);
+impl SharedToken {
+    pub fn bearer(token: &str) -> anyhow::Result<Self> {
+        Ok(Self(Arc::new(RwLock::new(bearer_header(token)?))))
+    }
+    pub fn rotate_bearer(&self, token: &str) -> anyhow::Result<()> {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = bearer_header(token)?;
+        Ok(())
+    }
+}
+impl AuthProvider for SharedToken {
+    async fn auth_header(&self) -> anyhow::Result<HeaderValue> {
+        Ok(self.0.read().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+}
+
+/// Middleware setting the `Authorization` header from an AuthProvider,
+/// unless the request already carries one (e.g. set explicitly with `.bearer_auth()`).
+///
+/// On a 401 the provider is invalidated and the request re-issued once with fresh credentials.
+pub struct Auth<P: AuthProvider>(pub P);
+impl<P: AuthProvider> Auth<P> {
+    async fn authorize(&self, request: &mut Request) -> Result<(), ExecuteErr> {
+        let value = self.0.auth_header().await.map_err(ExecuteErr::Middleware)?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+}
+impl<P: AuthProvider> UnauthorizedHook for Auth<P> {
+    async fn on_unauthorized(&self, retry_request: &mut Request) -> anyhow::Result<bool> {
+        self.0.invalidate().await;
+        self.authorize(retry_request).await?;
+        Ok(true)
+    }
+}
+impl<P: AuthProvider> ApiMiddleware for Auth<P> {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            if request.headers().contains_key(AUTHORIZATION) {
+                return next.run(request).await;
+            }
+            self.authorize(&mut request).await?;
+            reissue_on_unauthorized(self, request, next).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::stub_response;
+    use crate::prelude::*;
+    use crate::ApiRequestBuilder;
+
+    /// answers 200 only when the expected Authorization header is present
+    struct ExpectAuth(&'static str);
+    impl ApiMiddleware for ExpectAuth {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let status = match request.headers().get(AUTHORIZATION) {
+                Some(value) if value == self.0 => 200,
+                _ => 401,
+            };
+            Box::pin(async move { stub_response(status, "null") })
+        }
+    }
+
+    async fn call(auth: impl ApiMiddleware + 'static, expect: &'static str) -> bool {
+        ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/auth"), vec![])
+            .with_middleware(auth)
+            .with_middleware(ExpectAuth(expect))
+            .recv_json::<(), ()>()
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_auth_providers() -> anyhow::Result<()> {
+        assert!(call(Auth(StaticToken::bearer("abc")?), "Bearer abc").await);
+
+        let shared = SharedToken::bearer("first")?;
+        assert!(call(Auth(shared.clone()), "Bearer first").await);
+        shared.rotate_bearer("second")?;
+        assert!(call(Auth(shared.clone()), "Bearer second").await);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::ClientErr;
+use crate::serialization_formats::{ApiFormat, SerialFormat};
+use crate::status_errors::ErrorBody;
+use crate::{ReceiveResp, RequestClient, ToRequestClient};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::future::Future;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedFormat {
+    Json,
+    Xml,
+    FormUrlEncoded,
+}
+impl DetectedFormat {
+    /// from the Content-Type, or sniffed from the body when it's missing or unknown
+    pub fn detect(content_type: Option<&str>, body: &str) -> Self {
+        let essence = content_type
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_ascii_lowercase());
+        match essence.as_deref() {
+            // application/json, application/problem+json...
+            Some(ct) if ct.ends_with("json") => DetectedFormat::Json,
+            // application/xml, text/xml, application/soap+xml...
+            Some(ct) if ct.ends_with("xml") => DetectedFormat::Xml,
+            Some("application/x-www-form-urlencoded") => DetectedFormat::FormUrlEncoded,
+            _ if body.trim_start().starts_with('<') => DetectedFormat::Xml,
+            _ => DetectedFormat::Json,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AutoFormatError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Xml(#[from] serde_xml_rs::Error),
+    #[error(transparent)]
+    FormUrlEncoded(#[from] serde_urlencoded::de::Error),
+}
+
+/// Picks the format per response from its Content-Type, for gateways answering
+/// JSON on success but XML on errors, or the other way round.
+#[derive(Debug)]
+pub struct AutoFormat;
+impl SerialFormat for AutoFormat {
+    type Error = AutoFormatError;
+    /// without a Content-Type, the format is sniffed from the body
+    fn from_str<T: for<'a> Deserialize<'a>>(input: &str) -> Result<T, Self::Error> {
+        Self::from_str_with_content_type(input, None)
+    }
+    fn from_str_with_content_type<T: for<'a> Deserialize<'a>>(
+        input: &str,
+        content_type: Option<&str>,
+    ) -> Result<T, Self::Error> {
+        Ok(match DetectedFormat::detect(content_type, input) {
+            DetectedFormat::Json => serde_json::from_str(input)?,
+            DetectedFormat::Xml => serde_xml_rs::from_str(input)?,
+            DetectedFormat::FormUrlEncoded => serde_urlencoded::from_str(input)?,
+        })
+    }
+}
+impl ApiFormat for AutoFormat {
+    fn with_accept_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Accept", "application/json, application/xml;q=0.9")
+    }
+    /// requests are sent as JSON
+    fn with_content_type_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Content-Type", "application/json")
+    }
+}
+
+pub trait ReceiveAuto {
+    /// like recv_json, with the Ok and error bodies each read in the format their Content-Type announces
+    fn recv_auto<Ok: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+    ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, AutoFormat>>>;
+}
+// wrapper so that ReceiveResp<AutoFormat> doesn't make JSON calls like `.expect_ok()` ambiguous
+struct AutoFormatted<T>(T);
+impl<T: ToRequestClient> ToRequestClient for AutoFormatted<T> {
+    fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+        self.0.try_into()
+    }
+}
+impl<T: ToRequestClient> ReceiveResp<AutoFormat> for AutoFormatted<T> {}
+impl<T: ToRequestClient> ReceiveAuto for T {
+    fn recv_auto<Ok: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+    ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, AutoFormat>>> {
+        ReceiveResp::<AutoFormat>::expect_ok(AutoFormatted(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::response_from_parts;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+    use reqwest::{Request, Response, StatusCode};
+
+    #[derive(Deserialize, Debug)]
+    struct Pet {
+        name: String,
+    }
+    #[derive(Deserialize, Debug)]
+    struct GatewayError {
+        code: u16,
+        message: String,
+    }
+
+    /// JSON pets, but the gateway in front answers errors in XML
+    struct Gateway;
+    impl ApiMiddleware for Gateway {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let (status, content_type, body) = match request.url().path() {
+                "/pets/1" => (StatusCode::OK, "application/json", r#"{"name":"rex"}"#),
+                _ => (
+                    StatusCode::BAD_GATEWAY,
+                    "text/xml; charset=utf-8",
+                    "<error><code>502</code><message>upstream down</message></error>",
+                ),
+            };
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            Box::pin(async move { Ok(response_from_parts(status, headers, body)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_format_by_content_type() -> anyhow::Result<()> {
+        let request = |path: &str| {
+            let url = format!("http://localhost{path}");
+            ApiRequestBuilder::new(reqwest::Client::new().get(url), vec![]).with_middleware(Gateway)
+        };
+
+        let pet = request("/pets/1")
+            .recv_auto::<Pet, GatewayError>()
+            .await
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        assert_eq!(pet.name, "rex");
+
+        let err = request("/pets/2").recv_auto::<Pet, GatewayError>().await;
+        let Err(ClientErr::ErrorResponse { err_body, .. }) = err else {
+            anyhow::bail!("expected an error response, got {err:?}");
+        };
+        assert_eq!(err_body.code, 502);
+        assert_eq!(err_body.message, "upstream down");
+        Ok(())
+    }
+}
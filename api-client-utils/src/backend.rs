@@ -0,0 +1,188 @@
+use crate::error::{ErrorKind, ExecuteErr};
+use crate::transport::HttpTransport;
+use futures::future::BoxFuture;
+use reqwest::{Body, Request, Response};
+use std::time::Duration;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Failure of an HttpBackend, `kind` tells timeouts and connection failures apart for retries
+#[derive(thiserror::Error, Debug)]
+#[error("{source}")]
+pub struct BackendErr {
+    pub kind: ErrorKind,
+    pub source: BoxError,
+}
+impl BackendErr {
+    pub fn new(kind: ErrorKind, source: impl Into<BoxError>) -> Self {
+        Self {
+            kind,
+            source: source.into(),
+        }
+    }
+}
+
+/// An HTTP library sending requests in place of reqwest, speaking the plain `http` types.
+/// Bodies stay reqwest's `Body`, which implements `http_body::Body`: a response body
+/// can be built from bytes with `.into()` or from a stream with `Body::wrap_stream`.
+///
+/// Wrap it in `Backend` to use it as an ApiClient's transport, the middlewares and
+/// receive methods run the same on top of it.
+pub trait HttpBackend: Send + Sync {
+    fn send(
+        &self,
+        request: http::Request<Body>,
+    ) -> BoxFuture<'_, Result<http::Response<Body>, BackendErr>>;
+}
+
+/// HttpTransport sending through an HttpBackend. The request timeout covers receiving the response head.
+pub struct Backend<B: HttpBackend>(pub B);
+impl<B: HttpBackend> HttpTransport for Backend<B> {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            let timeout = request.timeout().copied();
+            let request = http::Request::try_from(request).map_err(ExecuteErr::Request)?;
+            let response = with_timeout(timeout, self.0.send(request)).await?;
+            Ok(Response::from(response))
+        })
+    }
+}
+
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    send: BoxFuture<'_, Result<T, BackendErr>>,
+) -> Result<T, ExecuteErr> {
+    let result = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, send).await {
+            Ok(result) => result,
+            Err(elapsed) => Err(BackendErr::new(ErrorKind::Timeout, elapsed)),
+        },
+        None => send.await,
+    };
+    result.map_err(ExecuteErr::Backend)
+}
+
+#[cfg(feature = "hyper")]
+pub use self::hyper_backend::HyperBackend;
+#[cfg(feature = "hyper")]
+mod hyper_backend {
+    use super::*;
+    use http_body_util::BodyExt;
+    use hyper_util::client::legacy::connect::HttpConnector;
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+
+    /// Plain-http hyper client, e.g. for talking to a sidecar without pulling TLS in.
+    /// For https, build a `Client` with a TLS connector and implement HttpBackend the same way.
+    #[derive(Clone)]
+    pub struct HyperBackend(pub Client<HttpConnector, Body>);
+    impl Default for HyperBackend {
+        fn default() -> Self {
+            Self(Client::builder(TokioExecutor::new()).build_http())
+        }
+    }
+    impl HttpBackend for HyperBackend {
+        fn send(
+            &self,
+            request: http::Request<Body>,
+        ) -> BoxFuture<'_, Result<http::Response<Body>, BackendErr>> {
+            Box::pin(async move {
+                let response = self.0.request(request).await.map_err(|e| {
+                    let kind = match e.is_connect() {
+                        true => ErrorKind::Connect,
+                        false => ErrorKind::Request,
+                    };
+                    BackendErr::new(kind, e)
+                })?;
+                Ok(response.map(|body| Body::wrap_stream(body.into_data_stream())))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use reqwest::StatusCode;
+
+    /// answers every request with its own method and path, after `delay`
+    struct EchoBackend {
+        delay: Duration,
+    }
+    impl HttpBackend for EchoBackend {
+        fn send(
+            &self,
+            request: http::Request<Body>,
+        ) -> BoxFuture<'_, Result<http::Response<Body>, BackendErr>> {
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                let echo = format!("{} {}", request.method(), request.uri().path());
+                http::Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Body::from(serde_json::json!(echo).to_string()))
+                    .map_err(|e| BackendErr::new(ErrorKind::Request, e))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backend_transport() -> anyhow::Result<()> {
+        let pets = JsonClient::builder("http://pets.internal/v1")
+            .transport(Backend(EchoBackend {
+                delay: Duration::ZERO,
+            }))
+            .build()?;
+        let echoed: String = pets
+            .get("/pets/1")
+            .recv_json::<_, serde_json::Value>()
+            .await?;
+        assert_eq!(echoed, "GET /v1/pets/1");
+
+        let slow = JsonClient::builder("http://pets.internal/v1")
+            .timeout(Duration::from_millis(10))
+            .transport(Backend(EchoBackend {
+                delay: Duration::from_secs(5),
+            }))
+            .build()?;
+        let err = slow
+            .get("/pets/1")
+            .recv_json::<String, serde_json::Value>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientErr::Backend { .. }), "{err:?}");
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert!(err.is_retryable());
+        Ok(())
+    }
+
+    #[cfg(feature = "hyper")]
+    #[tokio::test]
+    async fn test_hyper_backend() -> anyhow::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await?;
+            let body = r#"{"name":"rex"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await?;
+            anyhow::Ok(())
+        });
+
+        let pets = JsonClient::builder(&format!("http://{addr}"))
+            .transport(Backend(HyperBackend::default()))
+            .build()?;
+        let pet: serde_json::Value = pets
+            .get("/pets/1")
+            .recv_json::<_, serde_json::Value>()
+            .await?;
+        assert_eq!(pet["name"], "rex");
+        Ok(())
+    }
+}
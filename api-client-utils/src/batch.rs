@@ -0,0 +1,147 @@
+use crate::error::aliases::ApiResult;
+use crate::error::ClientErr;
+use crate::serialization_formats::SerialFormat;
+use crate::status_errors::ErrorBody;
+use crate::ReceiveResp;
+use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
+
+/// Send prepared requests concurrently, at most `parallelism` at a time.
+/// Results come back in the order of `requests`, one failing doesn't stop the others.
+pub async fn batch<R, Ok, ErrResp, F>(
+    requests: impl IntoIterator<Item = R>,
+    parallelism: usize,
+) -> Vec<ApiResult<Ok, ErrResp, F>>
+where
+    R: ReceiveResp<F>,
+    Ok: DeserializeOwned,
+    ErrResp: ErrorBody,
+    F: SerialFormat,
+{
+    stream::iter(requests)
+        .map(|request| request.expect_ok())
+        .buffered(parallelism.max(1))
+        .collect()
+        .await
+}
+
+/// Results of fetch_all_limited, each paired with the item it was requested for, in the items' order
+#[derive(Debug)]
+pub struct FetchAll<Item, Ok, E> {
+    pub ok: Vec<(Item, Ok)>,
+    pub failed: Vec<(Item, E)>,
+}
+impl<Item, Ok, E> FetchAll<Item, Ok, E> {
+    /// all the responses, or the error of the first item that failed
+    pub fn into_result(self) -> Result<Vec<Ok>, E> {
+        match self.failed.into_iter().next() {
+            Some((_, err)) => Err(err),
+            None => Ok(self.ok.into_iter().map(|(_, ok)| ok).collect()),
+        }
+    }
+}
+
+/// Build a request per item and send them at most `limit` at a time, like batch()
+pub async fn fetch_all_limited<Item, R, Ok, ErrResp, F>(
+    items: impl IntoIterator<Item = Item>,
+    limit: usize,
+    to_request: impl Fn(&Item) -> R,
+) -> FetchAll<Item, Ok, ClientErr<ErrResp, F>>
+where
+    R: ReceiveResp<F>,
+    Ok: DeserializeOwned,
+    ErrResp: ErrorBody,
+    F: SerialFormat,
+{
+    let fetches = items.into_iter().map(|item| {
+        let request = to_request(&item);
+        async move { (item, request.expect_ok().await) }
+    });
+    let mut fetched = FetchAll {
+        ok: vec![],
+        failed: vec![],
+    };
+    let mut results = stream::iter(fetches).buffered(limit.max(1));
+    while let Some((item, result)) = results.next().await {
+        match result {
+            Ok(ok) => fetched.ok.push((item, ok)),
+            Err(err) => fetched.failed.push((item, err)),
+        }
+    }
+    fetched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::aliases::JsonApiErr;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::Next;
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// echoes the id in `/users/{id}`, later ids answer faster, id 0 doesn't exist
+    #[derive(Default)]
+    struct UsersApi {
+        running: AtomicU32,
+        max_running: AtomicU32,
+    }
+    impl ApiMiddleware for UsersApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let id: u64 = request.url().path()[7..].parse().unwrap_or_default();
+            Box::pin(async move {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_running.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(40 - id * 5)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                match id {
+                    0 => stub_response(404, r#"{"message":"no such user"}"#),
+                    _ => stub_response(200, &id.to_string()),
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_keeps_order_and_limit() -> anyhow::Result<()> {
+        let api = Arc::new(UsersApi::default());
+        let client = reqwest::Client::new();
+        let requests = (0..6).map(|id| {
+            ApiRequestBuilder::new(client.get(format!("http://localhost/users/{id}")), vec![])
+                .with_middleware(api.clone())
+        });
+
+        let results: Vec<JsonClientResult<u64, serde_json::Value>> = batch(requests, 2).await;
+        assert!(matches!(results[0], Err(ClientErr::ErrorResponse { .. })));
+        let found: Vec<u64> = results.into_iter().skip(1).collect::<Result<_, _>>()?;
+        assert_eq!(found, vec![1, 2, 3, 4, 5]);
+        assert_eq!(api.max_running.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_limited() -> anyhow::Result<()> {
+        let api = Arc::new(UsersApi::default());
+        let client = reqwest::Client::new();
+        let fetched = fetch_all_limited(vec![3, 0, 1], 3, |id| {
+            ApiRequestBuilder::new(client.get(format!("http://localhost/users/{id}")), vec![])
+                .with_middleware(api.clone())
+        })
+        .await;
+
+        let ok: Vec<(u64, u64)> = fetched.ok;
+        assert_eq!(ok, vec![(3, 3), (1, 1)]);
+        let failed: Vec<(u64, JsonApiErr<serde_json::Value>)> = fetched.failed;
+        assert_eq!(failed[0].0, 0);
+        Ok(())
+    }
+}
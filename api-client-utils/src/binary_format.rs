@@ -0,0 +1,87 @@
+use crate::context::ResponseHead;
+use crate::error::ClientErr;
+use crate::serialization_formats::SerialFormat;
+use crate::ToRequestClient;
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+
+/// Formats whose bodies aren't text (protobuf, msgpack...), parallel to SerialFormat::from_str.
+/// The SerialFormat supertrait only names the decode error carried by ClientErr::DeserializeError.
+pub trait BinaryFormat<T>: SerialFormat {
+    /// accepted response content types, the first one is sent in Accept / Content-Type
+    const CONTENT_TYPES: &'static [&'static str];
+    fn decode(bytes: &[u8]) -> Result<T, Self::Error>;
+    fn encode(message: &T) -> Vec<u8>;
+    /// error used when the response comes back in another format than negotiated
+    fn unexpected_content_type(content_type: &str) -> Self::Error;
+}
+
+pub trait ReceiveBinary: Sized + ToRequestClient {
+    /// Like ReceiveResp::expect_ok, for binary formats: negotiates the content type,
+    /// then decodes the Ok body or the error body from bytes.
+    async fn recv_binary<F, Ok, ErrResp>(self) -> Result<Ok, ClientErr<ErrResp, F>>
+    where
+        F: BinaryFormat<Ok> + BinaryFormat<ErrResp>,
+    {
+        let mut request = self.try_into().map_err(ClientErr::BuildRequest)?;
+        let accept = <F as BinaryFormat<Ok>>::CONTENT_TYPES[0];
+        request
+            .request
+            .headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static(accept));
+        let sent = request.sent();
+
+        let response = request.send().await?;
+        let head = ResponseHead::of(&response);
+        let got_status = head.status;
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            });
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(ClientErr::ReadRespBodyText)?;
+        let context =
+            Box::new(sent.response_context(head, String::from_utf8_lossy(&bytes).into_owned()));
+
+        let expected = <F as BinaryFormat<Ok>>::CONTENT_TYPES;
+        let negotiated = content_type
+            .as_deref()
+            .is_none_or(|content_type| expected.contains(&content_type));
+
+        // error bodies often come from a gateway in its own format, e.g. html
+        if !got_status.is_success() {
+            let err_body = negotiated
+                .then(|| <F as BinaryFormat<ErrResp>>::decode(&bytes).ok())
+                .flatten();
+            return Err(match err_body {
+                Some(err_body) => ClientErr::ErrorResponse { context, err_body },
+                None => ClientErr::UnparsedErrorResponse {
+                    status: got_status,
+                    context,
+                },
+            });
+        }
+        if let Some(content_type) = content_type.filter(|_| !negotiated) {
+            return Err(ClientErr::DeserializeError {
+                context,
+                deserialize_error: <F as BinaryFormat<Ok>>::unexpected_content_type(&content_type),
+            });
+        }
+        <F as BinaryFormat<Ok>>::decode(&bytes).map_err(|deserialize_error| {
+            ClientErr::DeserializeError {
+                context,
+                deserialize_error,
+            }
+        })
+    }
+}
+impl<T: ToRequestClient> ReceiveBinary for T {}
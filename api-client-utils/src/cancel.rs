@@ -0,0 +1,109 @@
+use crate::error::ClientErr;
+use crate::serialization_formats::SerialFormat;
+use std::future::Future;
+use tokio::time::Instant;
+
+pub use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    Token,
+    Deadline,
+}
+impl std::fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CancelReason::Token => write!(f, "cancellation token triggered"),
+            CancelReason::Deadline => write!(f, "deadline passed"),
+        }
+    }
+}
+
+/// Give up on a call, e.g. `.recv_json::<Pet, ApiError>().cancel_on(&shutdown).await`.
+/// The request is dropped, aborting the connection mid-body if need be, and the call
+/// fails with `ClientErr::Cancelled`. Retries and backoff waits are cut short too.
+pub trait CancelExt<Ok, ErrResp, F: SerialFormat>:
+    Future<Output = Result<Ok, ClientErr<ErrResp, F>>> + Sized
+{
+    async fn cancel_on(self, token: &CancellationToken) -> Result<Ok, ClientErr<ErrResp, F>> {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(ClientErr::Cancelled { reason: CancelReason::Token }),
+            result = self => result,
+        }
+    }
+    /// unlike the request timeout, the deadline covers every attempt and reading the body
+    async fn deadline(self, deadline: Instant) -> Result<Ok, ClientErr<ErrResp, F>> {
+        match tokio::time::timeout_at(deadline, self).await {
+            Ok(result) => result,
+            Err(_) => Err(ClientErr::Cancelled {
+                reason: CancelReason::Deadline,
+            }),
+        }
+    }
+}
+impl<Ok, ErrResp, F: SerialFormat, Fut> CancelExt<Ok, ErrResp, F> for Fut where
+    Fut: Future<Output = Result<Ok, ClientErr<ErrResp, F>>>
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::{response_from_parts, ApiMiddleware, Next};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response, StatusCode};
+    use std::time::Duration;
+
+    /// sends the headers right away, then a body that never ends
+    struct Hanging;
+    impl ApiMiddleware for Hanging {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let body = futures::stream::pending::<Result<Vec<u8>, std::io::Error>>();
+            let body = reqwest::Body::wrap_stream(body);
+            let response = response_from_parts(StatusCode::OK, Default::default(), body);
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let request = || {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get("http://localhost/export"),
+                vec![],
+            )
+            .with_middleware(Hanging)
+            .recv_json::<serde_json::Value, serde_json::Value>()
+        };
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            cancel.cancel();
+        });
+        let cancelled = request().cancel_on(&token).await;
+        assert!(matches!(
+            cancelled,
+            Err(ClientErr::Cancelled {
+                reason: CancelReason::Token
+            })
+        ));
+
+        let late = request()
+            .deadline(Instant::now() + Duration::from_millis(20))
+            .await;
+        let Err(err) = late else {
+            panic!("expected the deadline to pass");
+        };
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        assert!(!err.is_retryable());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next, ResponseParts};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{Request, Response};
+use serde_json::{Map, Value};
+
+/// Naming conventions of object keys, as serde's `rename_all`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `pet_name`
+    Snake,
+    /// `petName`
+    Camel,
+    /// `PetName`
+    Pascal,
+    /// `pet-name`
+    Kebab,
+    /// `PET_NAME`
+    ScreamingSnake,
+}
+impl Case {
+    /// `name` in this case, whatever case it was in: `HTTPStatus`, `http_status`, `httpStatus` all give `http_status` in Snake
+    pub fn convert(self, name: &str) -> String {
+        let words = words(name);
+        let capitalized = |word: &str| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        };
+        match self {
+            Case::Snake => words.join("_"),
+            Case::Kebab => words.join("-"),
+            Case::ScreamingSnake => words.join("_").to_ascii_uppercase(),
+            Case::Pascal => words.iter().map(|w| capitalized(w)).collect(),
+            Case::Camel => {
+                let mut words = words.iter();
+                let first = words.next().cloned().unwrap_or_default();
+                first + &words.map(|w| capitalized(w)).collect::<String>()
+            }
+        }
+    }
+}
+
+/// lowercase words of a name, split on `_`, `-` and case changes
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        // the plural of an acronym, the `Ds` of `OwnerIDs`
+        let plural = next == Some(&'s') && !chars.get(i + 2).is_some_and(|n| n.is_lowercase());
+        let starts_word = c.is_uppercase()
+            && (prev.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit())
+                // the `S` of `HTTPStatus`
+                || (prev.is_some_and(char::is_uppercase)
+                    && next.is_some_and(|n| n.is_lowercase())
+                    && !plural));
+        if starts_word && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// `value` with the keys of all its objects, nested ones included, converted to `case`
+pub fn rename_keys(value: Value, case: Case) -> Value {
+    match value {
+        Value::Object(object) => {
+            let renamed: Map<String, Value> = object
+                .into_iter()
+                .map(|(key, value)| (case.convert(&key), rename_keys(value, case)))
+                .collect();
+            Value::Object(renamed)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| rename_keys(item, case))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Middleware renaming JSON keys on the fly, for APIs whose convention differs from the
+/// Rust structs' without `#[serde(rename_all)]` on each of them. Outgoing JSON bodies are
+/// converted to `request` and JSON responses to `response`, other bodies pass through.
+/// Every key is renamed, including those of maps keyed by data (ids, emails...).
+#[derive(Debug, Clone, Copy)]
+pub struct CaseTranslation {
+    pub request: Option<Case>,
+    pub response: Option<Case>,
+}
+impl CaseTranslation {
+    /// sends camelCase, reads snake_case: for the usual JS-flavored API
+    pub fn camel_case_api() -> Self {
+        Self {
+            request: Some(Case::Camel),
+            response: Some(Case::Snake),
+        }
+    }
+    /// any key naming in responses, e.g. a mix of `PetName` and `pet-name`, read as snake_case
+    pub fn snake_case_responses() -> Self {
+        Self {
+            request: None,
+            response: Some(Case::Snake),
+        }
+    }
+
+    fn rename_request(&self, request: &mut Request) {
+        let Some(case) = self.request else { return };
+        if !is_json(request.headers()) {
+            return;
+        }
+        let body = request.body().and_then(|body| body.as_bytes());
+        let Some(Ok(value)) = body.map(serde_json::from_slice::<Value>) else {
+            return;
+        };
+        let renamed = rename_keys(value, case).to_string();
+        request.headers_mut().remove(CONTENT_LENGTH);
+        *request.body_mut() = Some(renamed.into());
+    }
+
+    async fn rename_response(&self, mut response: Response) -> Result<Response, ExecuteErr> {
+        let Some(case) = self.response else {
+            return Ok(response);
+        };
+        if !is_json(response.headers()) {
+            return Ok(response);
+        }
+        let mut parts = ResponseParts::take(&mut response);
+        let body = response.bytes().await.map_err(ExecuteErr::Request)?;
+        let body = match serde_json::from_slice::<Value>(&body) {
+            Ok(value) => {
+                parts.headers.remove(CONTENT_LENGTH);
+                rename_keys(value, case).to_string().into()
+            }
+            // left for the receive method to report
+            Err(_) => body,
+        };
+        Ok(parts.with_body(body))
+    }
+}
+impl ApiMiddleware for CaseTranslation {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        self.rename_request(&mut request);
+        Box::pin(async move {
+            let response = next.run(request).await?;
+            self.rename_response(response).await
+        })
+    }
+}
+
+/// `application/json`, or a `+json` type like `application/problem+json`
+fn is_json(headers: &HeaderMap) -> bool {
+    let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    let essence = content_type
+        .and_then(|v| v.split(';').next())
+        .map(str::trim);
+    essence.is_some_and(|t| t == "application/json" || t.ends_with("+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::response_from_parts;
+    use crate::prelude::*;
+    use reqwest::header::HeaderValue;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_convert_case() {
+        for name in ["HTTPStatus", "http_status", "httpStatus", "Http-Status"] {
+            assert_eq!(Case::Snake.convert(name), "http_status", "{name}");
+        }
+        assert_eq!(Case::Snake.convert("OwnerIDs"), "owner_ids");
+        assert_eq!(Case::Camel.convert("pet_id_v2"), "petIdV2");
+        assert_eq!(Case::Pascal.convert("pet_name"), "PetName");
+        assert_eq!(Case::Kebab.convert("petName"), "pet-name");
+        assert_eq!(Case::ScreamingSnake.convert("petName"), "PET_NAME");
+    }
+
+    /// answers with the keys of the JSON body it got, and a camelCase pet
+    struct EchoKeys;
+    impl ApiMiddleware for EchoKeys {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            let sent: Value = serde_json::from_slice(body).unwrap_or_default();
+            let sent_keys: Vec<&String> = sent
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(k, _)| k)
+                .collect();
+            let body = serde_json::json!({ "sentKeys": sent_keys, "pet": { "petName": "rex", "OwnerIDs": [1] } });
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            Box::pin(async move {
+                Ok(response_from_parts(
+                    StatusCode::OK,
+                    headers,
+                    body.to_string(),
+                ))
+            })
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Pet {
+        pet_name: String,
+        owner_ids: Vec<u32>,
+    }
+    #[derive(serde::Deserialize)]
+    struct Echo {
+        sent_keys: Vec<String>,
+        pet: Pet,
+    }
+
+    #[tokio::test]
+    async fn test_case_translation() -> anyhow::Result<()> {
+        let echo: Echo =
+            ApiRequestBuilder::new(reqwest::Client::new().post("http://localhost/pets"), vec![])
+                .json(&Pet {
+                    pet_name: "rex".into(),
+                    owner_ids: vec![1],
+                })
+                .with_middleware(CaseTranslation::camel_case_api())
+                .with_middleware(EchoKeys)
+                .recv_json::<_, Value>()
+                .await?;
+        assert_eq!(echo.sent_keys, ["ownerIds", "petName"]);
+        assert_eq!(
+            echo.pet,
+            Pet {
+                pet_name: "rex".into(),
+                owner_ids: vec![1]
+            }
+        );
+        Ok(())
+    }
+}
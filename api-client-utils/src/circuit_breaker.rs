@@ -0,0 +1,290 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use crate::retry::RetryCause;
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed {
+        consecutive_failures: u32,
+    },
+    Open {
+        since: Instant,
+    },
+    /// cooldown elapsed, a single probe request is in flight
+    HalfOpen,
+}
+
+/// Stops calling a flapping upstream after `failure_threshold` consecutive failures,
+/// failing fast with `ClientErr::CircuitOpen` until `cooldown` has elapsed.
+///
+/// Register it as a middleware and keep it in the client (e.g. in an `Arc`) so its state is shared by all requests.
+pub struct CircuitBreaker {
+    pub failure_threshold: u32,
+    pub cooldown: Duration,
+    pub is_failure: Arc<dyn Fn(&RetryCause) -> bool + Send + Sync>,
+    state: Mutex<CircuitState>,
+}
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30))
+    }
+}
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            is_failure: Arc::new(|cause: &RetryCause| cause.is_transient()),
+            state: Mutex::new(CircuitState::Closed {
+                consecutive_failures: 0,
+            }),
+        }
+    }
+    pub fn is_failure(
+        self,
+        is_failure: impl Fn(&RetryCause) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            is_failure: Arc::new(is_failure),
+            ..self
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// decide whether a request may go through, moving Open -> HalfOpen once the cooldown elapsed
+    fn acquire(&self) -> Result<Permit<'_>, ExecuteErr> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match *state {
+            CircuitState::Closed { .. } => Ok(Permit {
+                breaker: self,
+                probe: false,
+            }),
+            CircuitState::Open { since } => {
+                let elapsed = since.elapsed();
+                if elapsed < self.cooldown {
+                    return Err(ExecuteErr::CircuitOpen {
+                        retry_in: self.cooldown - elapsed,
+                    });
+                }
+                *state = CircuitState::HalfOpen;
+                Ok(Permit {
+                    breaker: self,
+                    probe: true,
+                })
+            }
+            CircuitState::HalfOpen => Err(ExecuteErr::CircuitOpen {
+                retry_in: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// count the outcome of a request. Only the probe moves the breaker out of Open: requests let
+    /// through before it tripped and finishing late leave it as is
+    fn record(&self, failed: bool, probe: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = match (*state, probe, failed) {
+            (CircuitState::Closed { .. }, false, false) | (CircuitState::HalfOpen, true, false) => {
+                CircuitState::Closed {
+                    consecutive_failures: 0,
+                }
+            }
+            (
+                CircuitState::Closed {
+                    consecutive_failures,
+                },
+                false,
+                true,
+            ) => match consecutive_failures + 1 >= self.failure_threshold {
+                true => CircuitState::Open {
+                    since: Instant::now(),
+                },
+                false => CircuitState::Closed {
+                    consecutive_failures: consecutive_failures + 1,
+                },
+            },
+            (CircuitState::HalfOpen, true, true) => CircuitState::Open {
+                since: Instant::now(),
+            },
+            (unchanged, _, _) => unchanged,
+        };
+    }
+}
+
+/// A request let through by `acquire`. Dropped without `record`, e.g. when the request future is
+/// cancelled, a probe puts the breaker back to Open so the next one can go through after the cooldown.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+impl Permit<'_> {
+    fn record(mut self, failed: bool) {
+        let probe = std::mem::take(&mut self.probe);
+        self.breaker.record(failed, probe);
+    }
+}
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            let mut state = self.breaker.state.lock().unwrap_or_else(|e| e.into_inner());
+            *state = CircuitState::Open {
+                since: Instant::now(),
+            };
+        }
+    }
+}
+
+impl ApiMiddleware for CircuitBreaker {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            let permit = self.acquire()?;
+            let result = next.run(request).await;
+            let failed = match &result {
+                Ok(response) => (self.is_failure)(&RetryCause::Status(response.status())),
+                Err(err) => (self.is_failure)(&RetryCause::Err(err)),
+            };
+            permit.record(failed);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::StubResponse;
+    use crate::prelude::*;
+    use crate::ApiRequestBuilder;
+
+    async fn call(
+        breaker: &Arc<CircuitBreaker>,
+        status: u16,
+    ) -> JsonClientResult<(), serde_json::Value> {
+        ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/breaker"),
+            vec![breaker.clone(), Arc::new(StubResponse::new(status, "null"))],
+        )
+        .recv_json::<(), serde_json::Value>()
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold_then_recovers() -> anyhow::Result<()> {
+        let breaker = Arc::new(CircuitBreaker::new(2, Duration::from_millis(300)));
+
+        call(&breaker, 500).await.ok();
+        assert!(matches!(breaker.state(), CircuitState::Closed { .. }));
+        call(&breaker, 500).await.ok();
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+
+        let err = call(&breaker, 200)
+            .await
+            .expect_err("circuit should be open");
+        assert!(matches!(err, ClientErr::CircuitOpen { .. }));
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        call(&breaker, 200).await?;
+        assert_eq!(
+            breaker.state(),
+            CircuitState::Closed {
+                consecutive_failures: 0
+            }
+        );
+        Ok(())
+    }
+
+    /// Never answers, standing for a request that gets cancelled
+    struct Hang;
+    impl ApiMiddleware for Hang {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            Box::pin(futures::future::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_probe_reopens() -> anyhow::Result<()> {
+        let breaker = Arc::new(CircuitBreaker::new(1, Duration::from_millis(100)));
+        call(&breaker, 500).await.ok();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let probe = ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/breaker"),
+            vec![breaker.clone(), Arc::new(Hang)],
+        )
+        .recv_json::<(), serde_json::Value>();
+        let timed_out = tokio::time::timeout(Duration::from_millis(50), probe).await;
+        assert!(timed_out.is_err());
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        call(&breaker, 200).await?;
+        assert_eq!(
+            breaker.state(),
+            CircuitState::Closed {
+                consecutive_failures: 0
+            }
+        );
+        Ok(())
+    }
+
+    /// Holds the request until notified, standing for a slow one
+    struct Held(Arc<tokio::sync::Notify>);
+    impl ApiMiddleware for Held {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            Box::pin(async move {
+                self.0.notified().await;
+                next.run(request).await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_late_results_keep_it_open() -> anyhow::Result<()> {
+        let breaker = Arc::new(CircuitBreaker::new(1, Duration::from_secs(60)));
+        let late = |status: u16, release: Arc<tokio::sync::Notify>| {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get("http://localhost/breaker"),
+                vec![
+                    breaker.clone(),
+                    Arc::new(Held(release)),
+                    Arc::new(StubResponse::new(status, "null")),
+                ],
+            )
+            .recv_json::<(), serde_json::Value>()
+        };
+        let (success, failure) = (Arc::default(), Arc::default());
+        let late_success = tokio::spawn(late(200, Arc::clone(&success)));
+        let late_failure = tokio::spawn(late(500, Arc::clone(&failure)));
+        tokio::task::yield_now().await;
+
+        call(&breaker, 500).await.ok();
+        let tripped = breaker.state();
+        assert!(matches!(tripped, CircuitState::Open { .. }));
+
+        success.notify_one();
+        late_success.await??;
+        assert_eq!(breaker.state(), tripped);
+        failure.notify_one();
+        late_failure.await?.ok();
+        assert_eq!(breaker.state(), tripped);
+        Ok(())
+    }
+}
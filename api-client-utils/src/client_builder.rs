@@ -0,0 +1,268 @@
+use crate::auth::{Auth, AuthProvider};
+use crate::config::ClientConfig;
+use crate::middleware::ApiMiddleware;
+use crate::redirect::RedirectPolicy;
+use crate::retry::RetryPolicy;
+use crate::transport::HttpTransport;
+use crate::url_join::{self, UrlJoinErr};
+use crate::JsonApiClient;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A ready-made JsonApiClient, for when a struct of your own implementing the trait isn't worth it.
+/// Cheap to clone, clones share the connection pool and middlewares.
+///
+/// ```text
+/// let pets = JsonClient::builder("https://api.pets.com/v2")
+///     .timeout(Duration::from_secs(10))
+///     .auth(StaticToken::bearer(&api_key)?)
+///     .retry(RetryPolicy::new(3))
+///     .build()?;
+/// let pet: Pet = pets.get("/pets/1").recv_json::<Pet, ApiError>().await?;
+/// ```
+#[derive(Clone)]
+pub struct JsonClient {
+    base_url: String,
+    http_client: reqwest::Client,
+    middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    retry: Option<RetryPolicy>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    config: ClientConfig,
+}
+impl JsonClient {
+    pub fn builder(base_url: &str) -> ApiClientBuilder {
+        ApiClientBuilder::new(base_url)
+    }
+}
+impl JsonApiClient for JsonClient {
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+    fn http_client(&self) -> &reqwest::Client {
+        &self.http_client
+    }
+    fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+        &self.middlewares
+    }
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry.clone()
+    }
+    fn transport(&self) -> Option<Arc<dyn HttpTransport>> {
+        self.transport.clone()
+    }
+    fn config(&self) -> ClientConfig {
+        self.config.clone()
+    }
+}
+
+pub struct ApiClientBuilder {
+    base_url: String,
+    http_client: Option<reqwest::Client>,
+    middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    retry: Option<RetryPolicy>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    config: ClientConfig,
+    redirects: Option<RedirectPolicy>,
+}
+impl ApiClientBuilder {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            http_client: None,
+            middlewares: vec![],
+            retry: None,
+            transport: None,
+            config: ClientConfig::default(),
+            redirects: None,
+        }
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            config: self.config.timeout(timeout),
+            ..self
+        }
+    }
+    /// sent with every request that doesn't set it itself
+    pub fn header(self, name: HeaderName, value: HeaderValue) -> Self {
+        Self {
+            config: self.config.header(name, value),
+            ..self
+        }
+    }
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        Self {
+            config: self.config.user_agent(user_agent),
+            ..self
+        }
+    }
+    /// replaces the timeout, headers and user agent set so far
+    pub fn config(self, config: ClientConfig) -> Self {
+        Self { config, ..self }
+    }
+
+    /// runs before the middlewares added after it
+    pub fn auth(self, provider: impl AuthProvider + 'static) -> Self {
+        self.middleware(Auth(provider))
+    }
+    pub fn retry(self, retry: RetryPolicy) -> Self {
+        Self {
+            retry: Some(retry),
+            ..self
+        }
+    }
+    /// middlewares run in the order they were added
+    pub fn middleware(mut self, middleware: impl ApiMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Follow redirects with `policy`, run after every other middleware. The default http client
+    /// then leaves redirects alone: one passed to `http_client()` mustn't follow them either.
+    pub fn redirects(self, policy: RedirectPolicy) -> Self {
+        Self {
+            redirects: Some(policy),
+            ..self
+        }
+    }
+
+    /// e.g. one built from an HttpClientConfig, defaults to `reqwest::Client::new()`
+    pub fn http_client(self, http_client: reqwest::Client) -> Self {
+        Self {
+            http_client: Some(http_client),
+            ..self
+        }
+    }
+    pub fn transport(self, transport: impl HttpTransport + 'static) -> Self {
+        Self {
+            transport: Some(Arc::new(transport)),
+            ..self
+        }
+    }
+
+    /// fails if the base url isn't an absolute http(s) url
+    pub fn build(mut self) -> Result<JsonClient, UrlJoinErr> {
+        url_join::join(&self.base_url, "")?;
+        let http_client = match (self.http_client, &self.redirects) {
+            (Some(http_client), _) => http_client,
+            // only fails where reqwest::Client::new() would panic too
+            (None, Some(_)) => RedirectPolicy::http_client().expect("reqwest client"),
+            (None, None) => reqwest::Client::new(),
+        };
+        if let Some(policy) = self.redirects {
+            self.middlewares.push(Arc::new(policy));
+        }
+        Ok(JsonClient {
+            base_url: self.base_url,
+            http_client,
+            middlewares: self.middlewares,
+            retry: self.retry,
+            transport: self.transport,
+            config: self.config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::StaticToken;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::Next;
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::header::{AUTHORIZATION, USER_AGENT};
+    use reqwest::{Request, Response};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// echoes what reached the wire
+    struct Echo;
+    impl ApiMiddleware for Echo {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let header = |name| {
+                let value = request.headers().get(name);
+                value.and_then(|v| v.to_str().ok()).unwrap_or_default()
+            };
+            let body = serde_json::json!({
+                "url": request.url().as_str(),
+                "authorization": header(AUTHORIZATION),
+                "user_agent": header(USER_AGENT),
+                "api_version": header(HeaderName::from_static("x-api-version")),
+            });
+            let response = stub_response(200, &body.to_string());
+            Box::pin(async move { response })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_built_client() -> anyhow::Result<()> {
+        let pets = JsonClient::builder("https://api.pets.com/v2")
+            .timeout(Duration::from_secs(1))
+            .header(
+                HeaderName::from_static("x-api-version"),
+                HeaderValue::from_static("2024-01"),
+            )
+            .user_agent("pets-cli")
+            .auth(StaticToken::bearer("secret")?)
+            .retry(RetryPolicy::new(2))
+            .middleware(Echo)
+            .build()?;
+
+        let echoed: serde_json::Value = pets
+            .get("/pets/1")
+            .recv_json::<_, serde_json::Value>()
+            .await?;
+        assert_eq!(echoed["url"], "https://api.pets.com/v2/pets/1");
+        assert_eq!(echoed["authorization"], "Bearer secret");
+        assert_eq!(echoed["user_agent"], "pets-cli");
+        assert_eq!(echoed["api_version"], "2024-01");
+
+        assert!(matches!(
+            JsonClient::builder("api.pets.com").build(),
+            Err(UrlJoinErr::InvalidBase { .. })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redirects() -> anyhow::Result<()> {
+        // `/old` moved to `/new`
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let _server = tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let read = socket.read(&mut buf).await.unwrap_or_default();
+                let response = match String::from_utf8_lossy(&buf[..read]).starts_with("GET /old ") {
+                    true => "HTTP/1.1 301 Moved Permanently\r\nlocation: /new\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    false => "HTTP/1.1 200 OK\r\ncontent-length: 4\r\nconnection: close\r\n\r\ntrue",
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let client = |policy: RedirectPolicy| {
+            let builder = JsonClient::builder(&base_url).redirects(policy);
+            builder.build()
+        };
+
+        let moved = ReceiveResp::<JsonFormat>::partial_expect::<bool, serde_json::Value>(
+            client(RedirectPolicy::default())?.get("/old"),
+        )
+        .await?;
+        assert!(moved.ok_body);
+        assert_eq!(moved.context.redirects.len(), 1, "followed by the policy");
+        let refused = client(RedirectPolicy::new(0))?
+            .get("/old")
+            .recv_json::<bool, serde_json::Value>()
+            .await;
+        assert!(matches!(refused, Err(ClientErr::Middleware { .. })));
+        Ok(())
+    }
+}
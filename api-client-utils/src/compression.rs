@@ -0,0 +1,253 @@
+use crate::context::BodySize;
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next, ResponseParts};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use reqwest::{Request, Response};
+use std::io::Read;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Zstd,
+    Brotli,
+    Gzip,
+    Deflate,
+}
+impl Encoding {
+    pub fn token(self) -> &'static str {
+        match self {
+            Encoding::Zstd => "zstd",
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+    /// stops one byte past `limit`, so that a small compression bomb can't fill the memory
+    fn decode(self, wire: &[u8], limit: Option<u64>) -> std::io::Result<Vec<u8>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Encoding::Zstd => Box::new(zstd::stream::read::Decoder::new(wire)?),
+            Encoding::Brotli => Box::new(brotli::Decompressor::new(wire, 4096)),
+            Encoding::Gzip => Box::new(flate2::read::MultiGzDecoder::new(wire)),
+            Encoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(wire)),
+        };
+        let mut decoded = vec![];
+        let cap = limit.map_or(u64::MAX, |limit| limit + 1);
+        decoder.take(cap).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+}
+
+/// Middleware asking for compressed responses and decoding them, recording both sizes in `RespContext::body_size`.
+/// Compressed bodies are buffered to be decoded, so keep it off clients streaming large downloads or SSE.
+/// The request's `max_response_size` caps both the compressed and the decoded body.
+/// The reqwest::Client must not decompress on its own (reqwest's `gzip`, `brotli`... features off, or `.no_gzip()` & co).
+#[derive(Debug, Clone)]
+pub struct Decompression {
+    /// in order of preference
+    pub accept: Vec<Encoding>,
+}
+impl Default for Decompression {
+    fn default() -> Self {
+        Self::only(&[
+            Encoding::Zstd,
+            Encoding::Brotli,
+            Encoding::Gzip,
+            Encoding::Deflate,
+        ])
+    }
+}
+impl Decompression {
+    pub fn only(encodings: &[Encoding]) -> Self {
+        Self {
+            accept: encodings.to_vec(),
+        }
+    }
+
+    fn accept_encoding(&self) -> Option<HeaderValue> {
+        let tokens: Vec<&str> = self.accept.iter().map(|e| e.token()).collect();
+        HeaderValue::try_from(tokens.join(", ")).ok()
+    }
+
+    async fn decode(
+        &self,
+        mut response: Response,
+        limit: Option<u64>,
+    ) -> Result<Response, ExecuteErr> {
+        let content_encoding = response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_ascii_lowercase());
+        let encoding = self
+            .accept
+            .iter()
+            .find(|e| content_encoding.as_deref() == Some(e.token()));
+        let Some(&encoding) = encoding else {
+            // identity, or an encoding we didn't ask for and leave to the caller
+            if content_encoding.is_none() {
+                if let Some(len) = response.content_length() {
+                    let size = BodySize {
+                        wire: len,
+                        decoded: len,
+                    };
+                    response.extensions_mut().insert(size);
+                }
+            }
+            return Ok(response);
+        };
+
+        let mut parts = ResponseParts::take(&mut response);
+        let wire = match limit {
+            Some(limit) => read_limited(response, limit).await?,
+            None => response
+                .bytes()
+                .await
+                .map_err(ExecuteErr::Request)?
+                .to_vec(),
+        };
+        let decoded = encoding.decode(&wire, limit).map_err(|e| {
+            ExecuteErr::Middleware(anyhow::anyhow!("{} body: {e}", encoding.token()))
+        })?;
+        if let Some(limit) = limit.filter(|&limit| decoded.len() as u64 > limit) {
+            return Err(ExecuteErr::ResponseTooLarge {
+                limit,
+                read: decoded.len() as u64,
+                announced: None,
+            });
+        }
+
+        parts.headers.remove(CONTENT_ENCODING);
+        parts
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(decoded.len()));
+        parts.extensions.insert(BodySize {
+            wire: wire.len() as u64,
+            decoded: decoded.len() as u64,
+        });
+        Ok(parts.with_body(decoded))
+    }
+}
+
+impl ApiMiddleware for Decompression {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            if let Some(accept) = self.accept_encoding() {
+                request
+                    .headers_mut()
+                    .entry(ACCEPT_ENCODING)
+                    .or_insert(accept);
+            }
+            let limit = next.max_response_size();
+            let response = next.run(request).await?;
+            self.decode(response, limit).await
+        })
+    }
+}
+
+/// the compressed body, stopping once it goes over `limit`
+async fn read_limited(mut response: Response, limit: u64) -> Result<Vec<u8>, ExecuteErr> {
+    if let Some(announced) = response.content_length().filter(|&len| len > limit) {
+        return Err(ExecuteErr::ResponseTooLarge {
+            limit,
+            read: 0,
+            announced: Some(announced),
+        });
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(ExecuteErr::Request)? {
+        body.extend_from_slice(&chunk);
+        if body.len() as u64 > limit {
+            return Err(ExecuteErr::ResponseTooLarge {
+                limit,
+                read: body.len() as u64,
+                announced: None,
+            });
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::response_from_parts;
+    use crate::prelude::*;
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use std::io::Write;
+
+    /// gzips a large JSON body when asked to
+    struct GzipApi;
+    impl ApiMiddleware for GzipApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let body = serde_json::json!({ "names": vec!["rex"; 500] }).to_string();
+            let asked = request.headers().get(ACCEPT_ENCODING).cloned();
+            Box::pin(async move {
+                let mut headers = HeaderMap::new();
+                let body = match asked.as_ref().and_then(|v| v.to_str().ok()) {
+                    Some(accept) if accept.contains("gzip") => {
+                        let mut encoder =
+                            flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                        encoder
+                            .write_all(body.as_bytes())
+                            .map_err(|e| ExecuteErr::Middleware(e.into()))?;
+                        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+                        encoder
+                            .finish()
+                            .map_err(|e| ExecuteErr::Middleware(e.into()))?
+                    }
+                    _ => body.into_bytes(),
+                };
+                Ok(response_from_parts(StatusCode::OK, headers, body))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gzip_response_decoded() -> anyhow::Result<()> {
+        let request =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/names"), vec![])
+                .with_middleware(Decompression::only(&[Encoding::Gzip]))
+                .with_middleware(GzipApi);
+        let resp =
+            ReceiveResp::<JsonFormat>::partial_expect::<serde_json::Value, serde_json::Value>(
+                request,
+            )
+            .await?;
+
+        assert_eq!(resp.ok_body["names"].as_array().map(Vec::len), Some(500));
+        let size = resp
+            .context
+            .body_size
+            .expect("decompressed body has a size");
+        assert_eq!(size.decoded, resp.context.response_text.len() as u64);
+        assert!(size.wire < size.decoded / 10, "{size:?}");
+        assert_eq!(resp.context.header("content-encoding"), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decoded_size_capped() -> anyhow::Result<()> {
+        let result =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/names"), vec![])
+                .max_response_size(1000)
+                .with_middleware(Decompression::only(&[Encoding::Gzip]))
+                .with_middleware(GzipApi)
+                .recv_json::<serde_json::Value, serde_json::Value>()
+                .await;
+        let Err(ClientErr::ResponseTooLarge { limit, read, .. }) = result else {
+            anyhow::bail!("expected ResponseTooLarge, got {result:?}");
+        };
+        // decoding stopped right past the limit, not at the 3.5KB of the whole body
+        assert_eq!((limit, read), (1000, 1001));
+        Ok(())
+    }
+}
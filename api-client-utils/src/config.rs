@@ -0,0 +1,215 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Method, Request};
+use std::time::Duration;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Defaults applied to every request of an ApiClient, each can be overridden per request
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// None to wait as long as the http client allows
+    pub timeout: Option<Duration>,
+    pub default_headers: HeaderMap,
+    /// None to keep the http client's own User-Agent
+    pub user_agent: Option<String>,
+    /// attach a random `Idempotency-Key` to POST and PATCH requests that don't set one
+    pub idempotency_keys: bool,
+    /// bodies longer than this are cut off with ClientErr::ResponseTooLarge, None for no limit
+    pub max_response_size: Option<u64>,
+    /// header carrying an id per request, for correlating logs across services. None to send none
+    pub request_id_header: Option<HeaderName>,
+}
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(5)),
+            default_headers: HeaderMap::new(),
+            user_agent: None,
+            idempotency_keys: false,
+            max_response_size: None,
+            request_id_header: None,
+        }
+    }
+}
+impl ClientConfig {
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+    pub fn no_timeout(self) -> Self {
+        Self {
+            timeout: None,
+            ..self
+        }
+    }
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        Self {
+            user_agent: Some(user_agent.to_string()),
+            ..self
+        }
+    }
+
+    /// The key is generated once when the request is built, so retries of it send the same key
+    pub fn idempotency_keys(self) -> Self {
+        Self {
+            idempotency_keys: true,
+            ..self
+        }
+    }
+
+    /// Send an `X-Request-Id`: the one set with `with_request_id()` around the call, or a random UUID.
+    /// It is kept in the RespContext and the same across retries.
+    pub fn request_ids(self) -> Self {
+        self.request_id_header(crate::request_id::X_REQUEST_ID)
+    }
+    /// like `request_ids()`, under another header name, e.g. `X-Correlation-Id`
+    pub fn request_id_header(self, name: HeaderName) -> Self {
+        Self {
+            request_id_header: Some(name),
+            ..self
+        }
+    }
+
+    /// applies to buffered bodies, streams and downloads are read unbounded
+    pub fn max_response_size(self, bytes: u64) -> Self {
+        Self {
+            max_response_size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// set the timeout and headers the request doesn't already have
+    pub fn apply_defaults(self, request: &mut Request) {
+        if request.timeout().is_none() {
+            *request.timeout_mut() = self.timeout;
+        }
+        let needs_key = matches!(*request.method(), Method::POST | Method::PATCH);
+        let headers = request.headers_mut();
+        if let Some(key) = (self.idempotency_keys && needs_key)
+            .then(|| HeaderValue::try_from(uuid::Uuid::new_v4().to_string()).ok())
+            .flatten()
+        {
+            headers.entry(IDEMPOTENCY_KEY).or_insert(key);
+        }
+        if let Some(name) = self.request_id_header {
+            if let Ok(id) = HeaderValue::try_from(crate::request_id::request_id()) {
+                headers.entry(name).or_insert(id);
+            }
+        }
+        if let Some(user_agent) = self
+            .user_agent
+            .and_then(|ua| HeaderValue::try_from(ua).ok())
+        {
+            headers.entry(USER_AGENT).or_insert(user_agent);
+        }
+        for (name, value) in self.default_headers {
+            if let Some(name) = name {
+                headers.entry(name).or_insert(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::stub_response;
+    use crate::prelude::*;
+    use crate::ToRequestClient;
+    use futures::future::BoxFuture;
+    use reqwest::Response;
+    use std::sync::{Arc, Mutex};
+
+    struct VersionedApi {
+        http_client: reqwest::Client,
+    }
+    impl JsonApiClient for VersionedApi {
+        fn base_url(&self) -> &str {
+            "http://localhost/api"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn config(&self) -> ClientConfig {
+            ClientConfig::default()
+                .timeout(Duration::from_secs(30))
+                .header(
+                    HeaderName::from_static("x-api-version"),
+                    HeaderValue::from_static("2"),
+                )
+                .user_agent("versioned-api-client/1.0")
+        }
+    }
+
+    #[test]
+    fn test_config_defaults_and_overrides() -> anyhow::Result<()> {
+        let api = VersionedApi {
+            http_client: reqwest::Client::new(),
+        };
+
+        let default = ToRequestClient::try_into(api.get("/reports"))?.request;
+        assert_eq!(default.timeout(), Some(&Duration::from_secs(30)));
+        assert_eq!(default.headers()["x-api-version"], "2");
+        assert_eq!(default.headers()["user-agent"], "versioned-api-client/1.0");
+
+        let slow = api
+            .get("/reports/yearly")
+            .timeout(Duration::from_secs(120))
+            .header("x-api-version", "3");
+        let slow = ToRequestClient::try_into(slow)?.request;
+        assert_eq!(slow.timeout(), Some(&Duration::from_secs(120)));
+        let versions: Vec<_> = slow.headers().get_all("x-api-version").iter().collect();
+        assert_eq!(versions, vec!["3"]);
+        Ok(())
+    }
+
+    /// answers 503 to the first attempt, recording the key of each
+    struct FlakyPayments {
+        keys: Arc<Mutex<Vec<String>>>,
+    }
+    impl ApiMiddleware for FlakyPayments {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let mut keys = self.keys.lock().unwrap();
+            keys.push(
+                request.headers()[IDEMPOTENCY_KEY]
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            );
+            let status = match keys.len() {
+                1 => 503,
+                _ => 201,
+            };
+            Box::pin(async move { stub_response(status, "{}") })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_reused_across_retries() -> anyhow::Result<()> {
+        let keys = Arc::new(Mutex::new(vec![]));
+        let request = ApiRequestBuilder::new(
+            reqwest::Client::new().post("http://localhost/payments"),
+            vec![],
+        )
+        .with_config(ClientConfig::default().idempotency_keys())
+        .retry(RetryPolicy::new(2).backoff(Duration::ZERO, Duration::ZERO))
+        .with_middleware(FlakyPayments { keys: keys.clone() });
+
+        let context = request.expect_success::<serde_json::Value>().await?;
+        let keys = keys.lock().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], keys[1]);
+        assert_eq!(context.idempotency_key.as_ref(), Some(&keys[0]));
+        Ok(())
+    }
+}
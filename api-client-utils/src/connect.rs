@@ -0,0 +1,404 @@
+use crate::client_builder::JsonClient;
+use crate::context::{RespContext, ResponseHead};
+use crate::error::ClientErr;
+use crate::serialization_formats::{JsonFormat, SerialFormat};
+use crate::{ApiClient, ToRequestClient};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+
+/// Status codes shared by gRPC and Connect, `"not_found"` in Connect error bodies, `5` in `grpc-status`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcCode {
+    Canceled,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+    /// also what codes this client doesn't know deserialize to
+    #[serde(other)]
+    Unknown,
+}
+impl RpcCode {
+    /// the code of a `grpc-status`, None for 0 (OK)
+    pub fn from_grpc(status: u32) -> Option<Self> {
+        Some(match status {
+            0 => return None,
+            1 => RpcCode::Canceled,
+            3 => RpcCode::InvalidArgument,
+            4 => RpcCode::DeadlineExceeded,
+            5 => RpcCode::NotFound,
+            6 => RpcCode::AlreadyExists,
+            7 => RpcCode::PermissionDenied,
+            8 => RpcCode::ResourceExhausted,
+            9 => RpcCode::FailedPrecondition,
+            10 => RpcCode::Aborted,
+            11 => RpcCode::OutOfRange,
+            12 => RpcCode::Unimplemented,
+            13 => RpcCode::Internal,
+            14 => RpcCode::Unavailable,
+            15 => RpcCode::DataLoss,
+            16 => RpcCode::Unauthenticated,
+            _ => RpcCode::Unknown,
+        })
+    }
+}
+
+/// Error of an RPC, returned as ClientErr::ErrorResponse.
+/// Connect sends it as a JSON body whatever the codec, gRPC-web in its trailers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConnectError {
+    pub code: RpcCode,
+    #[serde(default)]
+    pub message: String,
+    /// Connect only, `{"type": ..., "value": <base64 protobuf>}` objects
+    #[serde(default)]
+    pub details: Vec<Value>,
+}
+impl ConnectError {
+    fn new(code: RpcCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: vec![],
+        }
+    }
+}
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+/// How messages are encoded, named as in content types: `application/proto`, `application/grpc-web+json`
+pub trait RpcCodec<T>: SerialFormat {
+    const NAME: &'static str;
+    fn encode(message: &T) -> Result<Vec<u8>, Self::Error>;
+    fn decode(bytes: &[u8]) -> Result<T, Self::Error>;
+}
+impl<T: Serialize + DeserializeOwned> RpcCodec<T> for JsonFormat {
+    const NAME: &'static str = "json";
+    fn encode(message: &T) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(message)
+    }
+    fn decode(bytes: &[u8]) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+}
+#[cfg(feature = "protobuf")]
+impl<T: prost::Message + Default> RpcCodec<T> for crate::protobuf::ProtobufFormat {
+    const NAME: &'static str = "proto";
+    fn encode(message: &T) -> Result<Vec<u8>, crate::protobuf::ProtobufErr> {
+        Ok(message.encode_to_vec())
+    }
+    fn decode(bytes: &[u8]) -> Result<T, crate::protobuf::ProtobufErr> {
+        Ok(T::decode(bytes)?)
+    }
+}
+
+/// The wire protocol of unary calls, both POST to `/{package.Service}/{Method}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RpcProtocol {
+    /// plain bodies, errors as JSON with a non-200 status
+    #[default]
+    Connect,
+    /// length-prefixed frames, the status in a trailers frame, as Envoy and gRPC-web proxies expect
+    GrpcWeb,
+}
+impl RpcProtocol {
+    fn content_type(self, codec: &str) -> String {
+        match self {
+            RpcProtocol::Connect => format!("application/{codec}"),
+            RpcProtocol::GrpcWeb => format!("application/grpc-web+{codec}"),
+        }
+    }
+}
+
+/// Connect or gRPC-web unary calls on top of an ApiClient, without tonic: `impl ConnectClient for MyService {}`.
+/// Failed calls come back as ClientErr::ErrorResponse with a ConnectError, malformed gRPC-web
+/// responses too, with code Internal.
+pub trait ConnectClient: ApiClient<JsonFormat> {
+    fn rpc_protocol(&self) -> RpcProtocol {
+        RpcProtocol::Connect
+    }
+
+    /// `procedure` is the path under base_url: `/acme.pets.v1.PetService/GetPet`.
+    /// C is the codec, JsonFormat or ProtobufFormat.
+    fn unary<C, Req, Resp>(
+        &self,
+        procedure: &str,
+        message: &Req,
+    ) -> impl Future<Output = Result<Resp, ClientErr<ConnectError, C>>>
+    where
+        C: RpcCodec<Req> + RpcCodec<Resp>,
+    {
+        let protocol = self.rpc_protocol();
+        let content_type = protocol.content_type(<C as RpcCodec<Req>>::NAME);
+        let encoded = <C as RpcCodec<Req>>::encode(message);
+        let request = self
+            .post(procedure)
+            .accept(&content_type)
+            .content_type(&content_type);
+        async move {
+            let encoded = encoded.map_err(|err| {
+                ClientErr::middleware(anyhow::anyhow!("can't encode the request: {err:?}"))
+            })?;
+            let request = match protocol {
+                RpcProtocol::Connect => request
+                    .header("connect-protocol-version", "1")
+                    .body(encoded),
+                RpcProtocol::GrpcWeb => request
+                    .header("x-grpc-web", "1")
+                    .body(frame(DATA_FRAME, &encoded)),
+            };
+            let request = ToRequestClient::try_into(request).map_err(ClientErr::BuildRequest)?;
+            let sent = request.sent();
+            let response = request.send().await?;
+            let head = ResponseHead::of(&response);
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(ClientErr::ReadRespBodyText)?;
+            let context =
+                Box::new(sent.response_context(head, String::from_utf8_lossy(&bytes).into_owned()));
+            let message = match protocol {
+                RpcProtocol::Connect => match connect_message(&bytes, &context) {
+                    Some(message) => message,
+                    // e.g. an HTML page from a proxy
+                    None => {
+                        return Err(ClientErr::UnparsedErrorResponse {
+                            status: context.got_status,
+                            context,
+                        })
+                    }
+                },
+                RpcProtocol::GrpcWeb => grpc_web_message(&bytes, &context),
+            };
+            let decoded = message.map(|message| <C as RpcCodec<Resp>>::decode(&message));
+            match decoded {
+                Ok(Ok(message)) => Ok(message),
+                Ok(Err(deserialize_error)) => Err(ClientErr::DeserializeError {
+                    context,
+                    deserialize_error,
+                }),
+                Err(err_body) => Err(ClientErr::ErrorResponse { context, err_body }),
+            }
+        }
+    }
+}
+impl ConnectClient for JsonClient {}
+
+/// the body of a 200, the ConnectError of other statuses, None if their body isn't one
+fn connect_message(bytes: &[u8], context: &RespContext) -> Option<Result<Vec<u8>, ConnectError>> {
+    if context.got_status.is_success() {
+        return Some(Ok(bytes.to_vec()));
+    }
+    serde_json::from_slice::<ConnectError>(bytes).ok().map(Err)
+}
+
+const DATA_FRAME: u8 = 0x00;
+const TRAILERS_FRAME: u8 = 0x80;
+
+/// `flag, u32 big-endian length, payload`
+fn frame(flag: u8, payload: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(payload.len() + 5);
+    framed.push(flag);
+    framed.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    framed.extend_from_slice(payload);
+    framed
+}
+
+/// The message of the data frame once the trailers say OK. A trailers-only response
+/// (errors before any message) has its status in the headers instead.
+fn grpc_web_message(bytes: &[u8], context: &RespContext) -> Result<Vec<u8>, ConnectError> {
+    let malformed = |what: &str| ConnectError::new(RpcCode::Internal, format!("gRPC-web: {what}"));
+    if !context.got_status.is_success() {
+        return Err(malformed(&format!("HTTP status {}", context.got_status)));
+    }
+    let mut message = None;
+    let mut trailers = vec![];
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (flag, len) = match rest {
+            [flag, a, b, c, d, ..] => (*flag, u32::from_be_bytes([*a, *b, *c, *d]) as usize),
+            _ => return Err(malformed("truncated frame header")),
+        };
+        let payload = rest.get(5..5 + len).ok_or(malformed("truncated frame"))?;
+        match flag {
+            DATA_FRAME => message = Some(payload.to_vec()),
+            TRAILERS_FRAME => trailers = parse_trailers(payload),
+            _ => return Err(malformed(&format!("unexpected frame flag {flag:#x}"))),
+        }
+        rest = &rest[5 + len..];
+    }
+
+    let from_headers = |name: &str| context.header(name).map(str::to_string);
+    let from_trailers = |name: &str| {
+        let trailer = trailers.iter().find(|(key, _)| key == name);
+        trailer.map(|(_, value)| value.clone())
+    };
+    let status = from_trailers("grpc-status").or_else(|| from_headers("grpc-status"));
+    let message_trailer = from_trailers("grpc-message").or_else(|| from_headers("grpc-message"));
+    let status = status.ok_or(malformed("no grpc-status"))?;
+    let status = status.trim().parse::<u32>();
+    let status = status.map_err(|_| malformed("grpc-status isn't a number"))?;
+    if let Some(code) = RpcCode::from_grpc(status) {
+        let message = percent_decode(&message_trailer.unwrap_or_default());
+        return Err(ConnectError::new(code, message));
+    }
+    message.ok_or(malformed("no message"))
+}
+
+/// `grpc-status: 0\r\ngrpc-message: ...`, keys lowercased
+fn parse_trailers(payload: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(payload);
+    let lines = text.split("\r\n").filter_map(|line| line.split_once(':'));
+    let trailers =
+        lines.map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim().to_string()));
+    trailers.collect()
+}
+
+/// grpc-message is percent-encoded outside of printable ASCII
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        match (
+            bytes[i],
+            hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+        ) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::response_from_parts;
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::header::{HeaderMap, CONTENT_TYPE};
+    use reqwest::{Request, Response, StatusCode};
+    use std::sync::Arc;
+
+    /// `GetPet` finding pet 7 only, answering Connect or gRPC-web like the request
+    struct PetService;
+    impl ApiMiddleware for PetService {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let grpc_web = request.headers().contains_key("x-grpc-web");
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            let body = if grpc_web { &body[5..] } else { body };
+            let asked: Value = serde_json::from_slice(body).unwrap_or_default();
+            let found = asked["id"] == 7 && request.url().path() == "/pets.v1.PetService/GetPet";
+
+            let mut headers = HeaderMap::new();
+            let content_type = request.headers()[CONTENT_TYPE].clone();
+            let (status, body) = match (grpc_web, found) {
+                (false, true) => (StatusCode::OK, br#"{"id":7,"name":"rex"}"#.to_vec()),
+                (false, false) => (
+                    StatusCode::NOT_FOUND,
+                    br#"{"code":"not_found","message":"no pet 8"}"#.to_vec(),
+                ),
+                (true, true) => {
+                    let mut body = frame(DATA_FRAME, br#"{"id":7,"name":"rex"}"#);
+                    body.extend(frame(TRAILERS_FRAME, b"grpc-status: 0\r\n"));
+                    (StatusCode::OK, body)
+                }
+                (true, false) => {
+                    let trailers = b"grpc-status: 5\r\ngrpc-message: no pet 8 %E2%9C%97\r\n";
+                    (StatusCode::OK, frame(TRAILERS_FRAME, trailers))
+                }
+            };
+            headers.insert(CONTENT_TYPE, content_type);
+            Box::pin(async move { Ok(response_from_parts(status, headers, body)) })
+        }
+    }
+
+    struct Pets {
+        protocol: RpcProtocol,
+        http_client: reqwest::Client,
+        middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    }
+    impl JsonApiClient for Pets {
+        fn base_url(&self) -> &str {
+            "http://localhost"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+            &self.middlewares
+        }
+    }
+    impl ConnectClient for Pets {
+        fn rpc_protocol(&self) -> RpcProtocol {
+            self.protocol
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Pet {
+        id: u64,
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn test_unary_calls() -> anyhow::Result<()> {
+        for protocol in [RpcProtocol::Connect, RpcProtocol::GrpcWeb] {
+            let pets = Pets {
+                protocol,
+                http_client: reqwest::Client::new(),
+                middlewares: vec![Arc::new(PetService)],
+            };
+            let get_pet = "/pets.v1.PetService/GetPet";
+            let pet: Pet = pets
+                .unary::<JsonFormat, _, _>(get_pet, &serde_json::json!({ "id": 7 }))
+                .await?;
+            assert_eq!(pet.name, "rex", "{protocol:?}");
+
+            let missing = pets
+                .unary::<JsonFormat, _, Pet>(get_pet, &serde_json::json!({ "id": 8 }))
+                .await;
+            let Err(ClientErr::ErrorResponse { err_body, .. }) = missing else {
+                anyhow::bail!("{protocol:?}: expected an error response, got {missing:?}");
+            };
+            assert_eq!(err_body.code, RpcCode::NotFound, "{protocol:?}");
+            assert!(err_body.message.starts_with("no pet 8"), "{protocol:?}");
+        }
+        assert_eq!(percent_decode("no pet 8 %E2%9C%97"), "no pet 8 ✗");
+        Ok(())
+    }
+}
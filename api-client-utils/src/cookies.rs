@@ -0,0 +1,173 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use cookie_store::CookieStore;
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderValue, COOKIE, SET_COOKIE};
+use reqwest::{Request, Response, Url};
+use std::sync::Mutex;
+
+/// Session cookies shared by every request of a client: `Set-Cookie` from responses is stored
+/// and the matching cookies are sent back, respecting domain, path, expiry and `Secure`.
+/// Register an `Arc<CookieJar>` in the client's middlewares to keep a handle for inspecting or clearing it.
+///
+/// Middlewares only see the last response of a redirect chain, to also keep cookies set by redirects
+/// pass the jar to `reqwest::ClientBuilder::cookie_provider` instead.
+#[derive(Default)]
+pub struct CookieJar {
+    store: Mutex<CookieStore>,
+}
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CookieStore> {
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// store a `Set-Cookie` value as if `url` had answered it
+    pub fn insert(&self, set_cookie: &str, url: &Url) -> anyhow::Result<()> {
+        self.lock().parse(set_cookie, url)?;
+        Ok(())
+    }
+    /// value of a cookie that hasn't expired
+    pub fn get(&self, domain: &str, path: &str, name: &str) -> Option<String> {
+        let store = self.lock();
+        Some(store.get(domain, path, name)?.value().to_string())
+    }
+    /// (name, value) of the cookies a request to `url` would carry
+    pub fn cookies_for(&self, url: &Url) -> Vec<(String, String)> {
+        let store = self.lock();
+        store
+            .get_request_values(url)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+    /// (domain, name) of every cookie that hasn't expired
+    pub fn names(&self) -> Vec<(String, String)> {
+        let store = self.lock();
+        store
+            .iter_unexpired()
+            .map(|cookie| {
+                let domain = cookie.domain().unwrap_or_default().to_string();
+                (domain, cookie.name().to_string())
+            })
+            .collect()
+    }
+    pub fn remove(&self, domain: &str, path: &str, name: &str) {
+        self.lock().remove(domain, path, name);
+    }
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn cookie_header(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .cookies_for(url)
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        match header.is_empty() {
+            true => None,
+            false => HeaderValue::from_str(&header).ok(),
+        }
+    }
+    fn store_set_cookies<'h>(&self, headers: impl Iterator<Item = &'h HeaderValue>, url: &Url) {
+        let mut store = self.lock();
+        for set_cookie in headers.filter_map(|value| value.to_str().ok()) {
+            // cookies the server isn't allowed to set for this url are dropped
+            let _ = store.parse(set_cookie, url);
+        }
+    }
+}
+
+impl ApiMiddleware for CookieJar {
+    // cookies are scoped by the request url, responses served by stubs or caches don't carry one
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            let url = request.url().clone();
+            if !request.headers().contains_key(COOKIE) {
+                if let Some(cookie) = self.cookie_header(&url) {
+                    request.headers_mut().insert(COOKIE, cookie);
+                }
+            }
+            let response = next.run(request).await?;
+            self.store_set_cookies(response.headers().get_all(SET_COOKIE).iter(), &url);
+            Ok(response)
+        })
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        self.store_set_cookies(cookie_headers, url);
+    }
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.cookie_header(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::response_from_parts;
+    use crate::prelude::*;
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use std::sync::Arc;
+
+    /// `/login` opens a session, `/me` requires it
+    struct SessionApi;
+    impl ApiMiddleware for SessionApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let mut headers = HeaderMap::new();
+            let status = match request.url().path() {
+                "/login" => {
+                    let session = "session=abc123; Path=/; HttpOnly";
+                    headers.insert(SET_COOKIE, HeaderValue::from_static(session));
+                    StatusCode::OK
+                }
+                _ => match request.headers().get(COOKIE) {
+                    Some(cookie) if cookie == "session=abc123" => StatusCode::OK,
+                    _ => StatusCode::UNAUTHORIZED,
+                },
+            };
+            Box::pin(async move { Ok(response_from_parts(status, headers, "{}")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_cookie_reused() -> anyhow::Result<()> {
+        let jar = Arc::new(CookieJar::new());
+        let call = |path: &str| {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get(format!("http://localhost{path}")),
+                vec![],
+            )
+            .with_middleware(jar.clone())
+            .with_middleware(SessionApi)
+            .recv_json::<serde_json::Value, serde_json::Value>()
+        };
+
+        assert!(call("/me").await.is_err());
+        call("/login").await?;
+        call("/me").await?;
+        assert_eq!(
+            jar.get("localhost", "/", "session").as_deref(),
+            Some("abc123")
+        );
+
+        jar.clear();
+        assert!(call("/me").await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Calendar fields of a SystemTime in UTC, enough to format dates without a date crate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+}
+impl UtcDateTime {
+    /// None before the unix epoch
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
+        let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let (days, secs_of_day) = (secs / 86400, secs % 86400);
+        let (year, month, day) = civil_from_days(days as i64);
+        Some(Self {
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3600,
+            minute: secs_of_day % 3600 / 60,
+            second: secs_of_day % 60,
+        })
+    }
+    /// IMF-fixdate, the format of HTTP date headers: `Sun, 06 Nov 1994 08:49:37 GMT`
+    pub fn parse_http_date(date: &str) -> Option<Self> {
+        let mut parts = date.split_whitespace().skip(1);
+        let day = parts.next()?.parse().ok()?;
+        let month_name = parts.next()?;
+        let month = MONTHS.iter().position(|&m| m == month_name)? as u32 + 1;
+        let year = parts.next()?.parse().ok()?;
+        let mut time = parts.next()?.split(':').map(|n| n.parse::<u64>().ok());
+        let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+        (parts.next()? == "GMT").then_some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let days = u64::try_from(days_from_civil(self.year, self.month, self.day)).ok()?;
+        let secs = days * 86400 + self.hour * 3600 + self.minute * 60 + self.second;
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+    /// `2015-08-30T12:36:00Z`
+    pub fn rfc3339(&self) -> String {
+        let Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        } = self;
+        format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// (year, month, day) to days since 1970-01-01, inverse of civil_from_days
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// days since 1970-01-01 to (year, month, day), from Howard Hinnant's date algorithms
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
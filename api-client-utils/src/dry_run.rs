@@ -0,0 +1,133 @@
+use crate::error::ExecuteErr;
+use crate::middleware::response_from_parts;
+use crate::transport::HttpTransport;
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Request, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use std::sync::Mutex;
+
+/// A request as it would have gone on the wire, after the middlewares
+#[derive(Debug, Clone)]
+pub struct CapturedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// None without a body, or with a streaming one
+    pub body: Option<bytes::Bytes>,
+}
+impl CapturedRequest {
+    pub fn body_json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(self.body.as_deref().unwrap_or_default())
+    }
+}
+
+/// Transport that never touches the network, for contract tests and CI without credentials.
+/// Requests still go through path joining, body serialization and the middlewares, so those
+/// failures come back as usual, then are captured and answered with a canned `200 {}`.
+/// Middlewares that make calls of their own (e.g. OAuth2 token fetches) still do.
+///
+/// ```text
+/// let dry_run = Arc::new(DryRun::new());
+/// let pets = JsonClient::builder("https://api.pets.com/v2").transport(dry_run.clone()).build()?;
+/// pets.post("/pets").json(&pet).recv_json::<Value, Value>().await?;
+/// assert_eq!(dry_run.captured()[0].body_json::<Pet>()?, pet);
+/// ```
+pub struct DryRun {
+    status: StatusCode,
+    body: String,
+    captured: Mutex<Vec<CapturedRequest>>,
+}
+impl Default for DryRun {
+    fn default() -> Self {
+        Self {
+            status: StatusCode::OK,
+            body: "{}".to_string(),
+            captured: Mutex::default(),
+        }
+    }
+}
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// answer every request with this instead, e.g. for calls deserializing into a struct
+    pub fn respond(self, status: StatusCode, body: &str) -> Self {
+        Self {
+            status,
+            body: body.to_string(),
+            ..self
+        }
+    }
+    /// every request received so far, oldest first
+    pub fn captured(&self) -> Vec<CapturedRequest> {
+        self.captured
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+    /// empty the captured list, returning it
+    pub fn take(&self) -> Vec<CapturedRequest> {
+        std::mem::take(&mut *self.captured.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+impl HttpTransport for DryRun {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>> {
+        let body = request.body().and_then(|body| body.as_bytes());
+        let captured = CapturedRequest {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: request.headers().clone(),
+            body: body.map(bytes::Bytes::copy_from_slice),
+        };
+        let mut received = self.captured.lock().unwrap_or_else(|e| e.into_inner());
+        received.push(captured);
+        let response = response_from_parts(self.status, HeaderMap::new(), self.body.clone());
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_dry_run() -> anyhow::Result<()> {
+        let dry_run = Arc::new(DryRun::new());
+        let pets = JsonClient::builder("https://api.pets.com/v2")
+            .header(reqwest::header::AUTHORIZATION, "Bearer test".parse()?)
+            .transport(dry_run.clone())
+            .build()?;
+
+        let pet = serde_json::json!({ "name": "rex" });
+        pets.post("/pets")
+            .json(&pet)
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await?;
+        let [created] = &dry_run.take()[..] else {
+            anyhow::bail!("expected one request");
+        };
+        assert_eq!(created.method, Method::POST);
+        assert_eq!(created.url.as_str(), "https://api.pets.com/v2/pets");
+        assert_eq!(created.headers["authorization"], "Bearer test");
+        assert_eq!(created.body_json::<serde_json::Value>()?, pet);
+
+        let outside = pets.get("/../admin").recv_text::<serde_json::Value>().await;
+        assert!(matches!(outside, Err(ClientErr::Middleware { .. })));
+        let unserializable = HashMap::from([((1, 2), "tuple keys aren't JSON")]);
+        let unserializable = pets
+            .post("/pets")
+            .json(&unserializable)
+            .recv_text::<serde_json::Value>()
+            .await;
+        assert!(matches!(unserializable, Err(ClientErr::BuildRequest(_))));
+        assert!(
+            dry_run.captured().is_empty(),
+            "invalid requests aren't sent"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+/// Generate the async methods of a JSON client from one line per endpoint.
+/// `{param}` placeholders in the path are filled from the arguments of the same name,
+/// `body(T)` adds a `body: &T` argument sent as JSON.
+///
+/// Endpoints can carry their own policies, as attributes before the method:
+/// - `#[timeout(duration)]`, in place of the client's
+/// - `#[retry(policy)]` or `#[no_retry]`, in place of the client's `retry_policy()`
+/// - `#[cache(field, ttl)]`, GETs served from the client's `field: Arc<TtlCache>` for `ttl`
+/// - `#[expect_status(status)]`, any other status is ClientErr::ExpectedStatus, see `ReceiveResp::expect_status`
+///
+/// ```text
+/// endpoints! {
+///     PetApi {
+///         #[cache(cache, Duration::from_secs(60))]
+///         GET "/pets/{id}" get_pet(id: u64) -> Pet, err PetError;
+///         #[timeout(Duration::from_secs(30))]
+///         GET "/pets" list_pets() -> Vec<Pet>, err PetError;
+///         #[no_retry]
+///         #[expect_status(StatusCode::CREATED)]
+///         POST "/pets" create_pet() body(NewPet) -> Pet, err PetError;
+///         DELETE "/pets/{id}" delete_pet(id: u64) -> (), err PetError;
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! endpoints {
+    (@option $api:ident, $request:ident, timeout($timeout:expr)) => {
+        $request.timeout($timeout)
+    };
+    (@option $api:ident, $request:ident, retry($policy:expr)) => {
+        $request.retry($policy)
+    };
+    (@option $api:ident, $request:ident, no_retry) => {
+        $request.no_retry()
+    };
+    (@option $api:ident, $request:ident, cache($field:ident, $ttl:expr)) => {
+        $request.with_middleware($api.$field.for_request($ttl))
+    };
+    // applied when receiving, see @recv
+    (@option $api:ident, $request:ident, expect_status($status:expr)) => {
+        $request
+    };
+    (@option $api:ident, $request:ident, $($other:tt)*) => {
+        compile_error!(concat!("unknown endpoint attribute: ", stringify!($($other)*)))
+    };
+
+    (@recv $request:ident;) => {
+        $crate::ReceiveJson::recv_json($request).await
+    };
+    (@recv $request:ident; {expect_status($status:expr)} $($rest:tt)*) => {
+        $crate::ReceiveResp::<$crate::serialization_formats::JsonFormat>::expect_status($request, $status).await
+    };
+    (@recv $request:ident; {$($other:tt)*} $($rest:tt)*) => {
+        $crate::endpoints!(@recv $request; $($rest)*)
+    };
+
+    ($client:ty {
+        $(
+            $(#[$opt:ident $( ( $($opt_args:tt)* ) )?])*
+            $method:ident $path:literal $name:ident ( $($arg:ident : $arg_ty:ty),* $(,)? )
+                $(body($body_ty:ty))? -> $ok:ty, err $err:ty;
+        )*
+    }) => {
+        impl $client {
+            $(
+                pub async fn $name(
+                    &self,
+                    $($arg: $arg_ty,)*
+                    $(body: &$body_ty,)?
+                ) -> $crate::error::aliases::JsonClientResult<$ok, $err> {
+                    let request = <Self as $crate::ApiClient<$crate::serialization_formats::JsonFormat>>::request(
+                        self,
+                        $crate::re_exports::reqwest::Method::$method,
+                        &format!($path),
+                    );
+                    $(let request = request.json::<$body_ty>(body);)?
+                    $(let request = $crate::endpoints!(@option self, request, $opt $( ($($opt_args)*) )?);)*
+                    $crate::endpoints!(@recv request; $({$opt $( ($($opt_args)*) )?})*)
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{RespContext, SentRequest};
+    use crate::prelude::*;
+    use reqwest::{Method, StatusCode};
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Deserialize, Debug)]
+    struct Pet {
+        name: String,
+    }
+    #[derive(Serialize)]
+    struct NewPet {
+        name: String,
+    }
+    #[derive(thiserror::Error, Deserialize, Debug)]
+    #[error("{message}")]
+    struct PetError {
+        message: String,
+    }
+
+    struct PetApi {
+        http_client: reqwest::Client,
+        transport: Arc<MockTransport>,
+        cache: Arc<TtlCache>,
+    }
+    impl JsonApiClient for PetApi {
+        fn base_url(&self) -> &str {
+            "http://localhost/v1"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn transport(&self) -> Option<Arc<dyn HttpTransport>> {
+            Some(self.transport.clone())
+        }
+    }
+    crate::endpoints! {
+        PetApi {
+            #[cache(cache, Duration::from_secs(60))]
+            #[timeout(Duration::from_secs(2))]
+            GET "/pets/{id}" get_pet(id: u64) -> Pet, err PetError;
+            POST "/owners/{owner}/pets" create_pet(owner: &str) body(NewPet) -> Pet, err PetError;
+            #[no_retry]
+            #[expect_status(StatusCode::CREATED)]
+            POST "/pets" adopt_pet() body(NewPet) -> Pet, err PetError;
+        }
+    }
+
+    fn canned(
+        method: Method,
+        url: &str,
+        status: StatusCode,
+        body: &str,
+    ) -> anyhow::Result<RespContext> {
+        let sent = SentRequest::new(method, url.parse()?);
+        Ok(sent.context(status, body.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_generated_endpoints() -> anyhow::Result<()> {
+        let transport = MockTransport::new()
+            .respond(canned(
+                Method::GET,
+                "http://localhost/v1/pets/7",
+                StatusCode::OK,
+                r#"{"name":"rex"}"#,
+            )?)
+            .respond(canned(
+                Method::POST,
+                "http://localhost/v1/owners/ann/pets",
+                StatusCode::CONFLICT,
+                r#"{"message":"already exists"}"#,
+            )?)
+            .respond(canned(
+                Method::POST,
+                "http://localhost/v1/pets",
+                StatusCode::OK,
+                r#"{"name":"rex"}"#,
+            )?);
+        let transport = Arc::new(transport);
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+            transport: transport.clone(),
+            cache: Arc::new(TtlCache::new()),
+        };
+
+        assert_eq!(api.get_pet(7).await?.name, "rex");
+        assert_eq!(api.get_pet(7).await?.name, "rex");
+        assert_eq!(transport.received().len(), 1, "second get_pet is cached");
+        let new_pet = NewPet {
+            name: "rex".to_string(),
+        };
+        let err = api
+            .create_pet("ann", &new_pet)
+            .await
+            .try_into_err_resp(StatusCode::CONFLICT)?;
+        assert_eq!(err.message, "already exists");
+        let adopted = api.adopt_pet(&new_pet).await;
+        assert!(matches!(adopted, Err(ClientErr::ExpectedStatus { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::context::OkRespWithContext;
+use crate::error::aliases::JsonClientResult;
+use crate::error::ClientErr;
+use crate::serialization_formats::JsonFormat;
+use crate::ReceiveResp;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Wrapper an API puts around every response body, holding either the payload or an error
+pub trait Envelope: DeserializeOwned {
+    type Payload;
+    type Error;
+    fn into_result(self) -> Result<Self::Payload, Self::Error>;
+}
+
+/// `{"data": ..., "error": ...}`, one of the two set
+#[derive(Debug, Deserialize)]
+#[serde(
+    try_from = "RawDataEnvelope<T, E>",
+    bound(deserialize = "T: DeserializeOwned, E: DeserializeOwned")
+)]
+pub enum DataEnvelope<T, E> {
+    Data(T),
+    Error(E),
+}
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: DeserializeOwned, E: DeserializeOwned"))]
+struct RawDataEnvelope<T, E> {
+    data: Option<T>,
+    error: Option<E>,
+}
+impl<T, E> TryFrom<RawDataEnvelope<T, E>> for DataEnvelope<T, E> {
+    type Error = &'static str;
+    fn try_from(raw: RawDataEnvelope<T, E>) -> Result<Self, &'static str> {
+        match (raw.data, raw.error) {
+            (_, Some(error)) => Ok(DataEnvelope::Error(error)),
+            (Some(data), None) => Ok(DataEnvelope::Data(data)),
+            (None, None) => Err("envelope has neither data nor error"),
+        }
+    }
+}
+impl<T: DeserializeOwned, E: DeserializeOwned> Envelope for DataEnvelope<T, E> {
+    type Payload = T;
+    type Error = E;
+    fn into_result(self) -> Result<T, E> {
+        match self {
+            DataEnvelope::Data(data) => Ok(data),
+            DataEnvelope::Error(error) => Err(error),
+        }
+    }
+}
+
+/// `{"success": bool, ...}`, the whole object is read as T on success and as E otherwise
+#[derive(Debug, Deserialize)]
+#[serde(
+    try_from = "Value",
+    bound(deserialize = "T: DeserializeOwned, E: DeserializeOwned")
+)]
+pub enum SuccessEnvelope<T, E> {
+    Success(T),
+    Failure(E),
+}
+impl<T: DeserializeOwned, E: DeserializeOwned> TryFrom<Value> for SuccessEnvelope<T, E> {
+    type Error = serde_json::Error;
+    fn try_from(body: Value) -> Result<Self, Self::Error> {
+        match body.get("success").and_then(Value::as_bool) {
+            Some(true) => serde_json::from_value(body).map(SuccessEnvelope::Success),
+            Some(false) => serde_json::from_value(body).map(SuccessEnvelope::Failure),
+            None => Err(serde::de::Error::missing_field("success")),
+        }
+    }
+}
+impl<T: DeserializeOwned, E: DeserializeOwned> Envelope for SuccessEnvelope<T, E> {
+    type Payload = T;
+    type Error = E;
+    fn into_result(self) -> Result<T, E> {
+        match self {
+            SuccessEnvelope::Success(payload) => Ok(payload),
+            SuccessEnvelope::Failure(error) => Err(error),
+        }
+    }
+}
+
+pub trait ReceiveEnvelope: Sized + ReceiveResp<JsonFormat> {
+    /// Unwrap the payload of an enveloped JSON response. An embedded error becomes
+    /// ClientErr::ErrorResponse whatever the status, even 200.
+    async fn recv_enveloped<Env: Envelope>(self) -> JsonClientResult<Env::Payload, Env::Error> {
+        match self.partial_expect::<Env, Env>().await {
+            Ok(OkRespWithContext { ok_body, context }) => {
+                ok_body
+                    .into_result()
+                    .map_err(|err_body| ClientErr::ErrorResponse {
+                        context: Box::new(context),
+                        err_body,
+                    })
+            }
+            Err(err) => Err(err.map_err_body(|context, envelope| {
+                match envelope.into_result() {
+                    Err(err_body) => ClientErr::ErrorResponse { context, err_body },
+                    // an error status with a payload, nothing to return as ErrResp
+                    Ok(_) => ClientErr::UnparsedErrorResponse {
+                        status: context.got_status,
+                        context,
+                    },
+                }
+            })),
+        }
+    }
+}
+impl<T: ReceiveResp<JsonFormat>> ReceiveEnvelope for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ResultExt;
+    use crate::middleware::tests::StubResponse;
+    use crate::ApiRequestBuilder;
+    use reqwest::StatusCode;
+
+    #[derive(Deserialize, Debug)]
+    struct Pet {
+        name: String,
+    }
+    /// error bodies stay Values, Display is needed to `?` the results into anyhow
+    type ApiError = serde_json::Value;
+
+    fn request(status: u16, body: &'static str) -> ApiRequestBuilder {
+        ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/pets/1"),
+            vec![],
+        )
+        .with_middleware(StubResponse::new(status, body))
+    }
+
+    #[tokio::test]
+    async fn test_data_envelope() -> anyhow::Result<()> {
+        let pet = request(200, r#"{"data":{"name":"rex"},"error":null}"#)
+            .recv_enveloped::<DataEnvelope<Pet, ApiError>>()
+            .await?;
+        assert_eq!(pet.name, "rex");
+
+        let embedded = request(200, r#"{"data":null,"error":{"code":"not_found"}}"#)
+            .recv_enveloped::<DataEnvelope<Pet, ApiError>>()
+            .await;
+        let Err(ClientErr::ErrorResponse { err_body, context }) = embedded else {
+            anyhow::bail!("expected an error response, got {embedded:?}");
+        };
+        assert_eq!(err_body["code"], "not_found");
+        assert_eq!(context.got_status, StatusCode::OK);
+
+        let rejected = request(400, r#"{"error":{"code":"invalid"}}"#)
+            .recv_enveloped::<DataEnvelope<Pet, ApiError>>()
+            .await
+            .try_into_err_resp(StatusCode::BAD_REQUEST)?;
+        assert_eq!(rejected["code"], "invalid");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_success_envelope() -> anyhow::Result<()> {
+        let pet = request(200, r#"{"success":true,"name":"rex"}"#)
+            .recv_enveloped::<SuccessEnvelope<Pet, ApiError>>()
+            .await?;
+        assert_eq!(pet.name, "rex");
+
+        let failed = request(200, r#"{"success":false,"code":"quota"}"#)
+            .recv_enveloped::<SuccessEnvelope<Pet, ApiError>>()
+            .await;
+        assert!(
+            matches!(failed, Err(ClientErr::ErrorResponse { err_body, .. }) if err_body["code"] == "quota")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Environment {
+    #[default]
+    Prod,
+    Staging,
+    Local,
+}
+impl FromStr for Environment {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "prod" | "production" => Ok(Environment::Prod),
+            "staging" | "stage" => Ok(Environment::Staging),
+            "local" | "dev" => Ok(Environment::Local),
+            other => {
+                anyhow::bail!("unknown environment {other:?}, expected prod, staging or local")
+            }
+        }
+    }
+}
+
+/// The base url of an API in each environment, along with the one requests go to.
+/// Return `active()` from `ApiClient::base_url` so `path()` joins against it.
+#[derive(Debug, Clone)]
+pub struct BaseUrls {
+    pub prod: String,
+    pub staging: String,
+    pub local: String,
+    pub env: Environment,
+}
+impl BaseUrls {
+    /// targets prod until switched with `env()` or `env_var()`
+    pub fn new(prod: &str, staging: &str, local: &str) -> Self {
+        Self {
+            prod: prod.to_string(),
+            staging: staging.to_string(),
+            local: local.to_string(),
+            env: Environment::Prod,
+        }
+    }
+    pub fn env(self, env: Environment) -> Self {
+        Self { env, ..self }
+    }
+    /// switch to the environment named in `var` (e.g. `PETS_API_ENV=staging`), stays as is when unset
+    pub fn env_var(self, var: &str) -> anyhow::Result<Self> {
+        match std::env::var(var) {
+            Ok(name) => Ok(self.env(name.parse()?)),
+            Err(std::env::VarError::NotPresent) => Ok(self),
+            Err(e) => Err(anyhow::anyhow!("{var}: {e}")),
+        }
+    }
+
+    pub fn get(&self, env: Environment) -> &str {
+        match env {
+            Environment::Prod => &self.prod,
+            Environment::Staging => &self.staging,
+            Environment::Local => &self.local,
+        }
+    }
+    pub fn active(&self) -> &str {
+        self.get(self.env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::ToRequestClient;
+
+    struct PetApi {
+        http_client: reqwest::Client,
+        base_urls: BaseUrls,
+    }
+    impl JsonApiClient for PetApi {
+        fn base_url(&self) -> &str {
+            self.base_urls.active()
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+    }
+
+    #[test]
+    fn test_path_joins_active_base_url() -> anyhow::Result<()> {
+        let base_urls = BaseUrls::new(
+            "https://api.pets.com/v1",
+            "https://staging.pets.com/v1",
+            "http://localhost:8080/v1",
+        )
+        .env_var("PETS_API_ENV_UNSET_IN_TESTS")?;
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+            base_urls: base_urls.env("Staging".parse()?),
+        };
+
+        let request = ToRequestClient::try_into(api.get("/pets/1"))?.request;
+        assert_eq!(request.url().as_str(), "https://staging.pets.com/v1/pets/1");
+        assert!("qa".parse::<Environment>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+use crate::context::RespContext;
+use crate::datetime::UtcDateTime;
+use crate::error::{ClientErr, ErrorKind};
+use crate::logging::{REDACTED, SECRET_HEADERS};
+use crate::serialization_formats::SerialFormat;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Method, Request, Url};
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static MAX_ERROR_BODY: AtomicUsize = AtomicUsize::new(2048);
+
+/// How many chars of the response text errors show, in Display and `to_json()`, for the whole process.
+/// 2048 by default, `usize::MAX` to keep bodies whole.
+pub fn set_max_error_body(max_chars: usize) {
+    MAX_ERROR_BODY.store(max_chars, Ordering::Relaxed);
+}
+pub fn max_error_body() -> usize {
+    MAX_ERROR_BODY.load(Ordering::Relaxed)
+}
+
+/// `text` cut after `max_chars`, saying how much was left out
+pub fn truncate_body(text: &str, max_chars: usize) -> Cow<'_, str> {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => {
+            let truncated = text.len() - cut;
+            Cow::Owned(format!("{}… {truncated} bytes truncated", &text[..cut]))
+        }
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// What was sent, taken before the request goes through the middlewares, kept by the errors that
+/// come without a response. Secret headers are redacted and the body cut after `max_error_body()` chars
+/// right away, so it can be logged as is.
+#[derive(Debug, Clone)]
+pub struct RequestSnapshot {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// None without a body or with a streaming one
+    pub body: Option<String>,
+}
+impl RequestSnapshot {
+    pub fn of(request: &Request) -> Self {
+        let mut headers = request.headers().clone();
+        for (name, value) in headers.iter_mut() {
+            if SECRET_HEADERS.contains(&name.as_str()) || value.is_sensitive() {
+                *value = HeaderValue::from_static(REDACTED);
+            }
+        }
+        let body = request.body().and_then(|body| body.as_bytes());
+        let body = body.map(|bytes| {
+            let text = String::from_utf8_lossy(bytes);
+            truncate_body(&text, max_error_body()).into_owned()
+        });
+        Self {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers,
+            body,
+        }
+    }
+    pub fn to_json(&self) -> Value {
+        let headers: Map<String, Value> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.to_string(), Value::String(value))
+            })
+            .collect();
+        json!({
+            "method": self.method.as_str(),
+            "url": self.url.as_str(),
+            "headers": headers,
+            "body": self.body,
+        })
+    }
+}
+
+impl RespContext {
+    /// the response text cut after `max_error_body()` chars
+    pub fn truncated_text(&self) -> Cow<'_, str> {
+        truncate_body(&self.response_text, max_error_body())
+    }
+
+    /// For structured logs, with the body cut after `max_body` chars and secret headers redacted
+    pub fn to_json(&self, max_body: usize) -> Value {
+        let headers: Map<String, Value> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = match SECRET_HEADERS.contains(&name.as_str()) || value.is_sensitive() {
+                    true => REDACTED.into(),
+                    false => String::from_utf8_lossy(value.as_bytes()),
+                };
+                (name.to_string(), Value::String(value.into_owned()))
+            })
+            .collect();
+        let mut json = json!({
+            "method": self.method.as_str(),
+            "url": self.url.as_str(),
+            "status": self.got_status.as_u16(),
+            "version": format!("{:?}", self.version),
+            "headers": headers,
+            "body": truncate_body(&self.response_text, max_body),
+        });
+        if !self.redirects.is_empty() {
+            let redirects: Vec<&str> = self.redirects.iter().map(|url| url.as_str()).collect();
+            json["redirects"] = json!(redirects);
+        }
+        if let Some(size) = self.body_size {
+            json["body_size"] = json!({ "wire": size.wire, "decoded": size.decoded });
+        }
+        if let Some(key) = &self.idempotency_key {
+            json["idempotency_key"] = json!(key);
+        }
+        if let Some(id) = &self.request_id {
+            json["request_id"] = json!(id);
+        }
+        let started_at = UtcDateTime::from_system_time(self.timing.started_at);
+        json["timing"] = json!({
+            "started_at": started_at.map(|date| date.rfc3339()),
+            "duration_ms": self.timing.duration.as_millis() as u64,
+            "attempts": self.timing.attempts,
+        });
+        json
+    }
+}
+
+impl<ErrResp, F: SerialFormat> ClientErr<ErrResp, F> {
+    /// Stable snake_case name of the variant, e.g. for an `error.type` log field
+    pub fn tag(&self) -> &'static str {
+        match self {
+            ClientErr::BuildRequest(_) => "build_request",
+            ClientErr::ExecuteRequest { source, .. } if source.is_timeout() => "timeout",
+            ClientErr::ExecuteRequest { source, .. } if source.is_connect() => "connect",
+            ClientErr::ExecuteRequest { .. } => "execute_request",
+            ClientErr::Backend { source, .. } if source.kind == ErrorKind::Timeout => "timeout",
+            ClientErr::Backend { source, .. } if source.kind == ErrorKind::Connect => "connect",
+            ClientErr::Backend { .. } => "backend",
+            ClientErr::Middleware { .. } => "middleware",
+            ClientErr::CircuitOpen { .. } => "circuit_open",
+            ClientErr::Cancelled { .. } => "cancelled",
+            ClientErr::ReadRespBodyText(_) => "read_body",
+            ClientErr::IncompleteBody { .. } => "incomplete_body",
+            ClientErr::WriteFile { .. } => "write_file",
+            ClientErr::ResponseTooLarge { .. } => "response_too_large",
+            ClientErr::ExpectedErrorResponse { .. } => "expected_error_response",
+            ClientErr::ExpectedStatus { .. } => "unexpected_status",
+            ClientErr::DeserializeError { .. } => "deserialize",
+            ClientErr::ErrorResponse { .. } => "error_response",
+            ClientErr::UnparsedErrorResponse { .. } => "unparsed_error_response",
+            ClientErr::GraphQlErrors { .. } => "graphql_errors",
+        }
+    }
+
+    /// The error as one JSON object to emit as a structured log event or store:
+    /// `kind`, a `message`, the response context if there was one and the variant's own fields.
+    /// The ErrResp body is only there as the (truncated) response text, so it needn't be Serialize.
+    pub fn to_json(&self) -> Value {
+        let (message, details) = match self {
+            ClientErr::BuildRequest(source) | ClientErr::ExecuteRequest { source, .. } => {
+                (source.to_string(), json!({}))
+            }
+            ClientErr::Backend { source, .. } => (source.to_string(), json!({})),
+            ClientErr::Middleware { source, .. } => (format!("{source:#}"), json!({})),
+            ClientErr::CircuitOpen { retry_in, .. } => (
+                "circuit open".to_string(),
+                json!({ "retry_in_ms": retry_in.as_millis() as u64 }),
+            ),
+            ClientErr::Cancelled { reason } => (
+                "cancelled".to_string(),
+                json!({ "reason": reason.to_string() }),
+            ),
+            ClientErr::ReadRespBodyText(e) => (e.to_string(), json!({})),
+            ClientErr::IncompleteBody { expected, received } => (
+                "body ended early".to_string(),
+                json!({ "expected": expected, "received": received }),
+            ),
+            ClientErr::WriteFile { path, source } => (
+                source.to_string(),
+                json!({ "path": path.display().to_string() }),
+            ),
+            ClientErr::ResponseTooLarge {
+                limit,
+                read,
+                announced,
+            } => (
+                "response too large".to_string(),
+                json!({ "limit": limit, "read": read, "announced": announced }),
+            ),
+            ClientErr::ExpectedErrorResponse { .. } => (
+                "expected error response, got success".to_string(),
+                json!({}),
+            ),
+            ClientErr::ExpectedStatus {
+                context,
+                expected_status,
+            } => (
+                format!(
+                    "expected status {expected_status}, got {}",
+                    context.got_status
+                ),
+                json!({ "expected_status": expected_status.as_u16() }),
+            ),
+            ClientErr::DeserializeError {
+                deserialize_error, ..
+            } => (format!("{deserialize_error:?}"), json!({})),
+            ClientErr::ErrorResponse { context, .. } => {
+                (format!("error response {}", context.got_status), json!({}))
+            }
+            ClientErr::UnparsedErrorResponse { status, .. } => (
+                format!("error response {status} not matching the error type"),
+                json!({}),
+            ),
+            ClientErr::GraphQlErrors { errors, .. } => {
+                let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+                (messages.join("; "), json!({ "errors": messages }))
+            }
+        };
+        let mut json = json!({ "kind": self.tag(), "message": message });
+        if let Some(context) = self.context() {
+            json["context"] = context.to_json(max_error_body());
+        }
+        if let Some(request) = self.request() {
+            json["request"] = request.to_json();
+        }
+        if let (Value::Object(json), Value::Object(details)) = (&mut json, details) {
+            json.extend(details);
+        }
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::SentRequest;
+    use crate::prelude::*;
+    use reqwest::header::SET_COOKIE;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_to_json() {
+        let request = reqwest::Client::new()
+            .get("https://api.pets.com/pets/1")
+            .build()
+            .unwrap();
+        let mut context =
+            SentRequest::of(&request).context(StatusCode::NOT_FOUND, "x".repeat(5000));
+        context
+            .headers
+            .insert(SET_COOKIE, HeaderValue::from_static("session=abc"));
+        let err: JsonApiErr<serde_json::Value> = ClientErr::ExpectedStatus {
+            context: Box::new(context),
+            expected_status: StatusCode::OK,
+        };
+
+        let json = err.to_json();
+        assert_eq!(json["kind"], "unexpected_status");
+        assert_eq!(json["expected_status"], 200);
+        assert_eq!(json["context"]["status"], 404);
+        assert_eq!(json["context"]["url"], "https://api.pets.com/pets/1");
+        assert_eq!(json["context"]["headers"]["set-cookie"], REDACTED);
+        assert_eq!(json["context"]["timing"]["attempts"], 1);
+        let body = json["context"]["body"].as_str().unwrap_or_default();
+        assert!(body.ends_with("x… 2952 bytes truncated"), "{body}");
+        let displayed = err.to_string();
+        assert!(
+            displayed.ends_with("x… 2952 bytes truncated\n"),
+            "{displayed}"
+        );
+
+        let request = reqwest::Client::new()
+            .post("http://localhost/pets")
+            .bearer_auth("secret")
+            .body(r#"{"name":"rex"}"#)
+            .build()
+            .unwrap();
+        let err: JsonApiErr<serde_json::Value> = ClientErr::CircuitOpen {
+            retry_in: std::time::Duration::from_secs(2),
+            request: Box::new(RequestSnapshot::of(&request)),
+        };
+        assert_eq!(
+            err.to_json(),
+            json!({
+                "kind": "circuit_open",
+                "message": "circuit open",
+                "retry_in_ms": 2000,
+                "request": {
+                    "method": "POST",
+                    "url": "http://localhost/pets",
+                    "headers": { "authorization": REDACTED },
+                    "body": r#"{"name":"rex"}"#,
+                },
+            })
+        );
+    }
+}
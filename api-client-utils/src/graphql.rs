@@ -0,0 +1,178 @@
+use crate::error::aliases::JsonClientResult;
+use crate::error::ClientErr;
+use crate::serialization_formats::JsonFormat;
+use crate::status_errors::ErrorBody;
+use crate::{ApiClient, ReceiveResp};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GraphQlError {
+    pub message: String,
+    #[serde(default)]
+    pub path: Vec<Value>,
+    #[serde(default)]
+    pub locations: Vec<GraphQlLocation>,
+    pub extensions: Option<Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GraphQlLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Deserialize)]
+struct Envelope<Data> {
+    data: Option<Data>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Serialize)]
+struct GraphQlRequest<'a, V> {
+    query: &'a str,
+    variables: V,
+}
+
+/// GraphQL on top of a JSON ApiClient: `impl GraphQlClient for MyApi {}`.
+/// Responses carrying `errors` come back as ClientErr::GraphQlErrors, even with partial data.
+pub trait GraphQlClient: ApiClient<JsonFormat> {
+    /// endpoint path, relative to base_url
+    fn graphql_path(&self) -> &str {
+        "/graphql"
+    }
+
+    fn query<Data, ErrResp>(
+        &self,
+        document: &str,
+        variables: impl Serialize,
+    ) -> impl Future<Output = JsonClientResult<Data, ErrResp>>
+    where
+        Data: DeserializeOwned,
+        ErrResp: ErrorBody,
+    {
+        let request = self.post(self.graphql_path()).json(&GraphQlRequest {
+            query: document,
+            variables,
+        });
+        async move {
+            // data is only typed once errors are ruled out, partial data often doesn't fit Data
+            let resp =
+                ReceiveResp::<JsonFormat>::partial_expect::<Envelope<Value>, ErrResp>(request)
+                    .await?;
+            let Envelope { data, errors } = resp.ok_body;
+            if !errors.is_empty() || data.is_none() {
+                return Err(ClientErr::GraphQlErrors {
+                    context: Box::new(resp.context),
+                    errors,
+                });
+            }
+            serde_json::from_value(data.unwrap_or_default()).map_err(|deserialize_error| {
+                ClientErr::DeserializeError {
+                    context: Box::new(resp.context),
+                    deserialize_error,
+                }
+            })
+        }
+    }
+    /// same wire format as queries, kept separate so call sites read as reads vs writes
+    fn mutate<Data, ErrResp>(
+        &self,
+        document: &str,
+        variables: impl Serialize,
+    ) -> impl Future<Output = JsonClientResult<Data, ErrResp>>
+    where
+        Data: DeserializeOwned,
+        ErrResp: ErrorBody,
+    {
+        self.query(document, variables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response};
+    use std::sync::Arc;
+
+    /// resolves `pet(id)` for id 1 only
+    struct PetGraph;
+    impl ApiMiddleware for PetGraph {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            let body: Value = serde_json::from_slice(body).unwrap_or_default();
+            let response = match body["variables"]["id"].as_u64() {
+                Some(1) => serde_json::json!({ "data": { "pet": { "name": "rex" } } }),
+                _ => serde_json::json!({
+                    "data": { "pet": null },
+                    "errors": [{ "message": "pet not found", "path": ["pet"], "locations": [{ "line": 1, "column": 9 }] }],
+                }),
+            };
+            Box::pin(async move { stub_response(200, &response.to_string()) })
+        }
+    }
+
+    struct PetApi {
+        http_client: reqwest::Client,
+        middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    }
+    impl JsonApiClient for PetApi {
+        fn base_url(&self) -> &str {
+            "http://localhost"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+            &self.middlewares
+        }
+    }
+    impl GraphQlClient for PetApi {}
+
+    #[derive(Deserialize)]
+    struct PetData {
+        pet: Pet,
+    }
+    #[derive(Deserialize)]
+    struct Pet {
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn test_graphql_envelope() -> anyhow::Result<()> {
+        const QUERY: &str = "query($id: ID!) { pet(id: $id) { name } }";
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+            middlewares: vec![Arc::new(PetGraph)],
+        };
+
+        let found: PetData = api
+            .query::<_, Value>(QUERY, serde_json::json!({ "id": 1 }))
+            .await?;
+        assert_eq!(found.pet.name, "rex");
+
+        let missing = api
+            .query::<PetData, Value>(QUERY, serde_json::json!({ "id": 2 }))
+            .await;
+        let Err(ClientErr::GraphQlErrors { errors, .. }) = missing else {
+            panic!("expected graphql errors");
+        };
+        assert_eq!(errors[0].message, "pet not found");
+        assert_eq!(errors[0].locations[0].column, 9);
+        Ok(())
+    }
+}
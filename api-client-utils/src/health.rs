@@ -0,0 +1,115 @@
+use crate::client_builder::JsonClient;
+use crate::error::aliases::JsonApiErr;
+use crate::serialization_formats::JsonFormat;
+use crate::{ApiClient, ReceiveResp};
+use futures::future::BoxFuture;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct Health {
+    pub status: StatusCode,
+    pub latency: Duration,
+    /// the body, when the endpoint answers JSON (e.g. `{"status":"ok","db":"up"}`)
+    pub details: Option<Value>,
+}
+
+/// An API a supervisor can probe: any 2xx on `health_path()` is healthy,
+/// error statuses and failures to connect come back as the ClientErr.
+pub trait HealthCheck: ApiClient<JsonFormat> + Sync {
+    fn health_path(&self) -> &str {
+        "/health"
+    }
+    fn ping(&self) -> impl Future<Output = Result<Health, JsonApiErr<Value>>> + Send {
+        async move {
+            let start = Instant::now();
+            let context = self.get(self.health_path()).expect_success().await?;
+            Ok(Health {
+                status: context.got_status,
+                latency: start.elapsed(),
+                details: serde_json::from_str(&context.response_text).ok(),
+            })
+        }
+    }
+}
+impl HealthCheck for JsonClient {}
+
+/// HealthCheck as a trait object, to keep clients of different types in one list
+pub trait DynHealthCheck: Send + Sync {
+    fn ping_dyn(&self) -> BoxFuture<'_, Result<Health, JsonApiErr<Value>>>;
+}
+impl<T: HealthCheck + Send> DynHealthCheck for T {
+    fn ping_dyn(&self) -> BoxFuture<'_, Result<Health, JsonApiErr<Value>>> {
+        Box::pin(self.ping())
+    }
+}
+
+/// ping every client concurrently, results in the order given
+pub async fn ping_all<'a>(
+    clients: &[(&'a str, &'a dyn DynHealthCheck)],
+) -> Vec<(&'a str, Result<Health, JsonApiErr<Value>>)> {
+    let pings = clients.iter().map(|(name, client)| async move {
+        let result = client.ping_dyn().await;
+        (*name, result)
+    });
+    futures::future::join_all(pings).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::StubResponse;
+    use crate::prelude::*;
+    use std::sync::Arc;
+
+    struct Payments {
+        http_client: reqwest::Client,
+        middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    }
+    impl JsonApiClient for Payments {
+        fn base_url(&self) -> &str {
+            "http://payments.internal"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+            &self.middlewares
+        }
+    }
+    impl HealthCheck for Payments {
+        fn health_path(&self) -> &str {
+            "/_status"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ping_all() -> anyhow::Result<()> {
+        let pets = JsonClient::builder("http://pets.internal")
+            .middleware(StubResponse::new(200, r#"{"status":"ok"}"#))
+            .build()?;
+        let payments = Payments {
+            http_client: reqwest::Client::new(),
+            middlewares: vec![Arc::new(StubResponse::new(503, "down"))],
+        };
+
+        let results = ping_all(&[("pets", &pets), ("payments", &payments)]).await;
+        let [(pets, Ok(pets_health)), (payments, Err(payments_err))] = &results[..] else {
+            anyhow::bail!("expected pets up and payments down, got {results:?}");
+        };
+        assert_eq!((*pets, *payments), ("pets", "payments"));
+        assert_eq!(pets_health.status, StatusCode::OK);
+        assert_eq!(
+            pets_health.details.as_ref().map(|d| &d["status"]),
+            Some(&"ok".into())
+        );
+        assert_eq!(payments_err.kind(), ErrorKind::ServerError);
+        assert_eq!(
+            payments_err.context().map(|c| c.url.path()),
+            Some("/_status")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{cache_key, response_from_parts, ApiMiddleware, Next, ResponseParts};
+use base64::Engine;
+use file_cache::{FileBytes, GitRepoCacheDir, StaticCacheDir};
+use futures::future::BoxFuture;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED,
+};
+use reqwest::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// A GET response stored along with its validators
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// base64
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+impl CachedResponse {
+    pub(crate) fn from_parts(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        let header_str = |name| Some(headers.get(name)?.to_str().ok()?.to_string());
+        Self {
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: base64::engine::general_purpose::STANDARD.encode(body),
+            etag: header_str(ETAG),
+            last_modified: header_str(LAST_MODIFIED),
+        }
+    }
+    pub(crate) fn to_response(&self) -> Result<Response, ExecuteErr> {
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(&self.body)
+            .map_err(|e| ExecuteErr::Middleware(e.into()))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::try_from(name), HeaderValue::try_from(value))
+            {
+                headers.append(name, value);
+            }
+        }
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        Ok(response_from_parts(status, headers, body))
+    }
+}
+impl FileBytes for CachedResponse {
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Conditional-GET cache: stores responses carrying an ETag or Last-Modified in `dir`,
+/// revalidates them with If-None-Match / If-Modified-Since, and serves the stored body on 304.
+/// Entries are keyed by url, Accept and Authorization so users never see each other's responses.
+/// Responses marked `Cache-Control: no-store` or `private` aren't stored.
+pub struct HttpCache {
+    pub dir: PathBuf,
+}
+impl HttpCache {
+    /// `.cache/http` in the git repo
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self::in_dir(GitRepoCacheDir::file_path("http")?))
+    }
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn entry_path(&self, request: &Request) -> PathBuf {
+        let hash = Sha256::digest(cache_key(request));
+        self.dir.join(hex::encode(hash))
+    }
+
+    fn store(&self, path: &Path, entry: &CachedResponse) -> Result<(), ExecuteErr> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| ExecuteErr::Middleware(e.into()))?;
+        entry.to_file(path).map_err(ExecuteErr::Middleware)
+    }
+}
+
+impl ApiMiddleware for HttpCache {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            if request.method() != Method::GET {
+                return next.run(request).await;
+            }
+            let path = self.entry_path(&request);
+            // unreadable entries are treated as missing
+            let cached = CachedResponse::from_file(&path).ok();
+
+            if let Some(cached) = &cached {
+                let headers = request.headers_mut();
+                let validators = [
+                    (IF_NONE_MATCH, &cached.etag),
+                    (IF_MODIFIED_SINCE, &cached.last_modified),
+                ];
+                for (name, value) in validators {
+                    let value = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok());
+                    if let (false, Some(value)) = (headers.contains_key(&name), value) {
+                        headers.insert(name, value);
+                    }
+                }
+            }
+
+            let mut response = next.run(request).await?;
+            match (response.status(), cached) {
+                (StatusCode::NOT_MODIFIED, Some(cached)) => cached.to_response(),
+                (status, _) if status.is_success() => {
+                    let headers = response.headers();
+                    let validated =
+                        headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED);
+                    if !validated || !may_store(headers) {
+                        return Ok(response);
+                    }
+                    let parts = ResponseParts::take(&mut response);
+                    let body = response.bytes().await.map_err(ExecuteErr::Request)?;
+                    self.store(
+                        &path,
+                        &CachedResponse::from_parts(status, &parts.headers, &body),
+                    )?;
+                    Ok(parts.with_body(body))
+                }
+                _ => Ok(response),
+            }
+        })
+    }
+}
+
+/// false if Cache-Control forbids keeping the response: `no-store`, or `private` to its user
+fn may_store(headers: &HeaderMap) -> bool {
+    let mut directives = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.split('=').next().unwrap_or_default().trim());
+    !directives
+        .any(|name| name.eq_ignore_ascii_case("no-store") || name.eq_ignore_ascii_case("private"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// serves a body with ETag "v1", and 304 when the client already has it. `/me` is private
+    #[derive(Default)]
+    struct VersionedResource {
+        full_responses: AtomicU32,
+    }
+    impl ApiMiddleware for VersionedResource {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let response = match request.headers().get(IF_NONE_MATCH) {
+                Some(etag) if etag == "\"v1\"" => {
+                    response_from_parts(StatusCode::NOT_MODIFIED, HeaderMap::new(), "")
+                }
+                _ => {
+                    self.full_responses.fetch_add(1, Ordering::SeqCst);
+                    let mut headers = HeaderMap::new();
+                    headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+                    if request.url().path() == "/me" {
+                        let private = HeaderValue::from_static("private, max-age=60");
+                        headers.insert(CACHE_CONTROL, private);
+                    }
+                    response_from_parts(StatusCode::OK, headers, r#"{"name":"rex"}"#)
+                }
+            };
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_etag_revalidation() -> anyhow::Result<()> {
+        let temp = file_cache::TempCacheDir::new()?;
+        let cache = Arc::new(HttpCache::in_dir(temp.path()));
+        let server = Arc::new(VersionedResource::default());
+        let call = |path: &str| {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get(format!("http://localhost{path}")),
+                vec![],
+            )
+            .with_middleware(cache.clone())
+            .with_middleware(server.clone())
+            .recv_json::<serde_json::Value, serde_json::Value>()
+        };
+
+        assert_eq!(call("/pets/1").await?["name"], "rex");
+        assert_eq!(call("/pets/1").await?["name"], "rex");
+        assert_eq!(server.full_responses.load(Ordering::SeqCst), 1);
+
+        assert_eq!(call("/me").await?["name"], "rex");
+        assert_eq!(call("/me").await?["name"], "rex");
+        assert_eq!(
+            server.full_responses.load(Ordering::SeqCst),
+            3,
+            "private responses aren't stored"
+        );
+        Ok(())
+    }
+}
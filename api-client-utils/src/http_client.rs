@@ -0,0 +1,408 @@
+use reqwest::tls::{Certificate, Identity};
+use reqwest::{ClientBuilder, NoProxy, Proxy};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+/// How the reqwest::Client behind an ApiClient connects.
+/// Build it once, keep it in the client struct and return it from `http_client()`.
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub proxy: ProxyMode,
+    /// trusted on top of the built-in roots, e.g. the CA of internal services
+    pub root_certificates: Vec<Certificate>,
+    /// false to trust only `root_certificates`
+    pub built_in_roots: bool,
+    /// client certificate presented to servers requiring mutual TLS
+    pub identity: Option<Identity>,
+    pub pool: PoolConfig,
+    /// hostnames answered without DNS, see `resolve()`
+    pub resolve: Vec<(String, Vec<IpAddr>)>,
+}
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            proxy: ProxyMode::default(),
+            root_certificates: vec![],
+            built_in_roots: true,
+            identity: None,
+            pool: PoolConfig::default(),
+            resolve: vec![],
+        }
+    }
+}
+impl HttpClientConfig {
+    /// send matching requests through `proxy`, can be called several times (e.g. one for http, one for https)
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        match &mut self.proxy {
+            ProxyMode::Explicit(proxies) => proxies.push(proxy),
+            _ => self.proxy = ProxyMode::Explicit(vec![proxy]),
+        }
+        self
+    }
+    /// connect directly, even if HTTP_PROXY & co are set
+    pub fn no_proxy(mut self) -> Self {
+        self.proxy = ProxyMode::Direct;
+        self
+    }
+
+    pub fn root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+    /// every certificate of a PEM bundle
+    pub fn root_certificates_file(mut self, pem_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let pem = std::fs::read(pem_path.as_ref())?;
+        self.root_certificates
+            .extend(Certificate::from_pem_bundle(&pem)?);
+        Ok(self)
+    }
+    /// refuse servers whose certificate doesn't chain to `root_certificates`
+    pub fn only_custom_roots(self) -> Self {
+        Self {
+            built_in_roots: false,
+            ..self
+        }
+    }
+    pub fn identity(self, identity: Identity) -> Self {
+        Self {
+            identity: Some(identity),
+            ..self
+        }
+    }
+    /// client certificate (chain) and its PKCS#8 private key, both PEM
+    pub fn identity_files(
+        self,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        let cert = std::fs::read(cert_path.as_ref())?;
+        let key = std::fs::read(key_path.as_ref())?;
+        Ok(self.identity(Identity::from_pkcs8_pem(&cert, &key)?))
+    }
+
+    pub fn pool(self, pool: PoolConfig) -> Self {
+        Self { pool, ..self }
+    }
+    /// Connect to `addrs` for `host` instead of looking it up, like `curl --resolve`:
+    /// urls, the Host header and TLS SNI keep the real hostname, so a stub with the production
+    /// certificate name can stand in. The port still comes from the url.
+    pub fn resolve(mut self, host: &str, addrs: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.resolve
+            .push((host.to_string(), addrs.into_iter().collect()));
+        self
+    }
+
+    /// for settings this doesn't cover, to finish with `.build()`
+    pub fn client_builder(self) -> reqwest::Result<ClientBuilder> {
+        let mut builder = reqwest::Client::builder();
+        match self.proxy {
+            ProxyMode::FromEnv => {}
+            ProxyMode::Direct => builder = builder.no_proxy(),
+            ProxyMode::Explicit(proxies) => {
+                // explicit proxies replace the ones from the environment rather than adding to them
+                builder = builder.no_proxy();
+                for proxy in proxies {
+                    builder = builder.proxy(proxy.into_proxy()?);
+                }
+            }
+        }
+        for certificate in self.root_certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+        builder = builder.tls_built_in_root_certs(self.built_in_roots);
+        if let Some(identity) = self.identity {
+            builder = builder.identity(identity);
+        }
+        for (host, addrs) in &self.resolve {
+            let addrs: Vec<SocketAddr> = addrs.iter().map(|ip| SocketAddr::new(*ip, 0)).collect();
+            builder = builder.resolve_to_addrs(host, &addrs);
+        }
+        Ok(self.pool.apply(builder))
+    }
+    pub fn build(self) -> reqwest::Result<reqwest::Client> {
+        self.client_builder()?.build()
+    }
+}
+
+/// How connections are kept for reuse, None everywhere keeps reqwest's defaults:
+/// unlimited idle connections per host, dropped after 90s idle, no keep-alive probes.
+#[derive(Debug, Clone, Default)]
+pub struct PoolConfig {
+    /// idle connections kept per host, 0 to open a new connection for every request
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout: Option<Duration>,
+    /// TCP keep-alive probes, so that NATs and load balancers don't drop idle connections silently
+    pub tcp_keepalive: Option<Duration>,
+    /// ping HTTP/2 connections every `interval`, and drop them if no answer comes within `timeout`
+    pub http2_keep_alive: Option<Http2KeepAlive>,
+}
+#[derive(Debug, Clone, Copy)]
+pub struct Http2KeepAlive {
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// ping connections without requests in flight too
+    pub while_idle: bool,
+}
+impl PoolConfig {
+    /// For services making many concurrent calls to a few hosts: enough warm connections
+    /// to absorb bursts without keeping hundreds open, and dead connections found by
+    /// keep-alives instead of by the request that would have used them.
+    pub fn high_throughput() -> Self {
+        Self {
+            max_idle_per_host: Some(32),
+            idle_timeout: Some(Duration::from_secs(60)),
+            tcp_keepalive: Some(Duration::from_secs(30)),
+            http2_keep_alive: Some(Http2KeepAlive {
+                interval: Duration::from_secs(30),
+                timeout: Duration::from_secs(10),
+                while_idle: true,
+            }),
+        }
+    }
+    pub fn max_idle_per_host(self, max: usize) -> Self {
+        Self {
+            max_idle_per_host: Some(max),
+            ..self
+        }
+    }
+    pub fn idle_timeout(self, idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout: Some(idle_timeout),
+            ..self
+        }
+    }
+    pub fn tcp_keepalive(self, interval: Duration) -> Self {
+        Self {
+            tcp_keepalive: Some(interval),
+            ..self
+        }
+    }
+    pub fn http2_keep_alive(self, interval: Duration, timeout: Duration) -> Self {
+        Self {
+            http2_keep_alive: Some(Http2KeepAlive {
+                interval,
+                timeout,
+                while_idle: true,
+            }),
+            ..self
+        }
+    }
+
+    fn apply(self, mut builder: ClientBuilder) -> ClientBuilder {
+        if let Some(max) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        if let Some(interval) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if let Some(keep_alive) = self.http2_keep_alive {
+            builder = builder
+                .http2_keep_alive_interval(keep_alive.interval)
+                .http2_keep_alive_timeout(keep_alive.timeout)
+                .http2_keep_alive_while_idle(keep_alive.while_idle);
+        }
+        builder
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum ProxyMode {
+    /// HTTP_PROXY, HTTPS_PROXY, ALL_PROXY and NO_PROXY from the environment, like a bare reqwest::Client
+    #[default]
+    FromEnv,
+    Direct,
+    Explicit(Vec<ProxyConfig>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyScope {
+    All,
+    Http,
+    Https,
+}
+
+/// One proxy, `http://`, `https://` or, with the `socks` feature, `socks5://` / `socks5h://`
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub url: String,
+    pub scope: ProxyScope,
+    /// NO_PROXY syntax: comma separated hosts, `.domain` suffixes, IPs and CIDR ranges
+    pub except: Option<String>,
+    pub basic_auth: Option<(String, String)>,
+}
+impl ProxyConfig {
+    pub fn all(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            scope: ProxyScope::All,
+            except: None,
+            basic_auth: None,
+        }
+    }
+    pub fn http(url: &str) -> Self {
+        Self {
+            scope: ProxyScope::Http,
+            ..Self::all(url)
+        }
+    }
+    pub fn https(url: &str) -> Self {
+        Self {
+            scope: ProxyScope::Https,
+            ..Self::all(url)
+        }
+    }
+    /// e.g. `"localhost,.internal,10.0.0.0/8"`
+    pub fn except(self, no_proxy: &str) -> Self {
+        Self {
+            except: Some(no_proxy.to_string()),
+            ..self
+        }
+    }
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        Self {
+            basic_auth: Some((username.to_string(), password.to_string())),
+            ..self
+        }
+    }
+
+    fn into_proxy(self) -> reqwest::Result<Proxy> {
+        let proxy = match self.scope {
+            ProxyScope::All => Proxy::all(&self.url)?,
+            ProxyScope::Http => Proxy::http(&self.url)?,
+            ProxyScope::Https => Proxy::https(&self.url)?,
+        };
+        let proxy = proxy.no_proxy(self.except.as_deref().and_then(NoProxy::from_string));
+        Ok(match &self.basic_auth {
+            Some((username, password)) => proxy.basic_auth(username, password),
+            None => proxy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// answers one request with its request line as body
+    async fn echo_server() -> anyhow::Result<(String, tokio::task::JoinHandle<()>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = format!("127.0.0.1:{}", listener.local_addr()?.port());
+        let server = tokio::spawn(async move {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buf = vec![0; 4096];
+            let read = socket.read(&mut buf).await.unwrap_or_default();
+            let request = String::from_utf8_lossy(&buf[..read]);
+            let line = request.lines().next().unwrap_or_default().to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{line}",
+                line.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+        Ok((addr, server))
+    }
+
+    #[tokio::test]
+    async fn test_explicit_proxy_and_exceptions() -> anyhow::Result<()> {
+        let (proxy, _proxy_server) = echo_server().await?;
+        let client = HttpClientConfig::default()
+            .proxy(ProxyConfig::http(&format!("http://{proxy}")).except("127.0.0.1"))
+            .build()?;
+
+        let proxied = client
+            .get("http://pets.test/pets")
+            .send()
+            .await?
+            .text()
+            .await?;
+        assert_eq!(proxied, "GET http://pets.test/pets HTTP/1.1");
+
+        let (direct, _direct_server) = echo_server().await?;
+        let url = format!("http://{direct}/pets");
+        let not_proxied = client.get(url).send().await?.text().await?;
+        assert_eq!(not_proxied, "GET /pets HTTP/1.1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_override() -> anyhow::Result<()> {
+        let (stub, _stub_server) = echo_server().await?;
+        let port = stub.rsplit(':').next().unwrap_or_default();
+        let client = HttpClientConfig::default()
+            .no_proxy()
+            .resolve("api.pets.com", [IpAddr::from([127, 0, 0, 1])])
+            .build()?;
+
+        let url = format!("http://api.pets.com:{port}/pets");
+        let response = client.get(&url).send().await?;
+        assert_eq!(response.url().as_str(), url);
+        assert_eq!(response.text().await?, "GET /pets HTTP/1.1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pool_reuse() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // keep-alive server counting the connections it accepts
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/pets", listener.local_addr()?);
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 4096];
+                    while socket.read(&mut buf).await.unwrap_or_default() > 0 {
+                        let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                        let _ = socket.write_all(response.as_bytes()).await;
+                    }
+                });
+            }
+        });
+        let send_twice = |client: reqwest::Client| {
+            let url = url.clone();
+            async move {
+                for _ in 0..2 {
+                    client.get(&url).send().await?.text().await?;
+                }
+                anyhow::Ok(())
+            }
+        };
+
+        let pooled = HttpClientConfig::default().pool(PoolConfig::high_throughput());
+        send_twice(pooled.build()?).await?;
+        assert_eq!(connections.swap(0, Ordering::SeqCst), 1);
+
+        let unpooled = HttpClientConfig::default().pool(PoolConfig::default().max_idle_per_host(0));
+        send_twice(unpooled.build()?).await?;
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_internal_ca_and_client_identity() -> anyhow::Result<()> {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/tls");
+        let config = HttpClientConfig::default()
+            .root_certificates_file(fixtures.join("ca.pem"))?
+            .only_custom_roots()
+            .identity_files(fixtures.join("client.pem"), fixtures.join("client.key"))?;
+        assert_eq!(config.root_certificates.len(), 1);
+        config.build()?;
+
+        let swapped = HttpClientConfig::default()
+            .identity_files(fixtures.join("client.key"), fixtures.join("client.pem"));
+        assert!(swapped.is_err());
+        Ok(())
+    }
+}
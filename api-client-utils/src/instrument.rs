@@ -0,0 +1,44 @@
+use crate::context::OkRespWithContext;
+use crate::error::ClientErr;
+use crate::serialization_formats::SerialFormat;
+use reqwest::Request;
+use std::future::Future;
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+/// span named and shaped after the OpenTelemetry HTTP client conventions
+pub(crate) fn request_span(request: &Request) -> Span {
+    tracing::info_span!(
+        "http.client.request",
+        otel.kind = "client",
+        http.request.method = %request.method(),
+        url.full = %request.url(),
+        http.response.status_code = Empty,
+        duration_ms = Empty,
+        error.type = Empty,
+    )
+}
+
+/// run the exchange inside `span`, recording its outcome on it
+pub(crate) async fn record<Ok, ErrResp, F: SerialFormat>(
+    span: Span,
+    exchange: impl Future<Output = Result<OkRespWithContext<Ok>, ClientErr<ErrResp, F>>>,
+) -> Result<OkRespWithContext<Ok>, ClientErr<ErrResp, F>> {
+    let start = Instant::now();
+    let result = exchange.instrument(span.clone()).await;
+    span.record("duration_ms", start.elapsed().as_millis() as u64);
+
+    let context = match &result {
+        Ok(ok) => Some(&ok.context),
+        Err(err) => err.context(),
+    };
+    if let Some(context) = context {
+        span.record("http.response.status_code", context.got_status.as_u16());
+    }
+    if let Err(err) = &result {
+        span.record("error.type", err.tag());
+        tracing::warn!(parent: &span, "request failed: {}", err.tag());
+    }
+    result
+}
@@ -0,0 +1,295 @@
+use crate::envelope::{Envelope, ReceiveEnvelope};
+use crate::error::aliases::JsonClientResult;
+use crate::error::ClientErr;
+use crate::serialization_formats::JsonFormat;
+use crate::{ApiClient, ReceiveResp};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The `error` member of a JSON-RPC response, returned as ClientErr::ErrorResponse
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+impl std::fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRpcRequest<'a, P> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: P,
+    /// absent for notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+}
+impl<'a, P> JsonRpcRequest<'a, P> {
+    fn new(method: &'a str, params: P, id: Option<u64>) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            method,
+            params,
+            id,
+        }
+    }
+}
+
+/// `{"jsonrpc": "2.0", "id": ..., "result": ...}` or `{..., "error": {...}}`
+#[derive(Deserialize, Debug)]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
+pub struct JsonRpcResponse<T> {
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub outcome: JsonRpcOutcome<T>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonRpcOutcome<T> {
+    Result(T),
+    Error(JsonRpcError),
+}
+impl<T: DeserializeOwned> Envelope for JsonRpcResponse<T> {
+    type Payload = T;
+    type Error = JsonRpcError;
+    fn into_result(self) -> Result<T, JsonRpcError> {
+        match self.outcome {
+            JsonRpcOutcome::Result(result) => Ok(result),
+            JsonRpcOutcome::Error(error) => Err(error),
+        }
+    }
+}
+
+/// JSON-RPC 2.0 on top of a JSON ApiClient: `impl JsonRpcClient for MyNode {}`.
+/// Error objects come back as ClientErr::ErrorResponse, whatever the HTTP status.
+pub trait JsonRpcClient: ApiClient<JsonFormat> {
+    /// endpoint path, relative to base_url
+    fn rpc_path(&self) -> &str {
+        "/"
+    }
+    /// ids only need to be unique among in-flight requests, a process-wide counter by default
+    fn next_id(&self) -> u64 {
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn call<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> impl Future<Output = JsonClientResult<R, JsonRpcError>> {
+        let request = JsonRpcRequest::new(method, params, Some(self.next_id()));
+        let request = self.post(self.rpc_path()).json(&request);
+        request.recv_enveloped::<JsonRpcResponse<R>>()
+    }
+
+    /// a call without id, the server sends no result back
+    fn notify(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> impl Future<Output = JsonClientResult<(), JsonRpcError>> {
+        let request = JsonRpcRequest::new(method, params, None);
+        let request = self.post(self.rpc_path()).json(&request);
+        async move {
+            ReceiveResp::<JsonFormat>::expect_success(request)
+                .await
+                .map(drop)
+        }
+    }
+
+    fn batch(&self) -> RpcBatch<'_, Self>
+    where
+        Self: Sized,
+    {
+        RpcBatch {
+            client: self,
+            requests: vec![],
+            ids: vec![],
+        }
+    }
+}
+
+/// Calls and notifications sent as one JSON array.
+/// Results come back in the order the calls were added, whatever order the server answers in.
+pub struct RpcBatch<'c, C> {
+    client: &'c C,
+    requests: Vec<Value>,
+    ids: Vec<u64>,
+}
+impl<C: JsonRpcClient> RpcBatch<'_, C> {
+    pub fn call(mut self, method: &str, params: impl Serialize) -> Self {
+        let id = self.client.next_id();
+        self.push(JsonRpcRequest::new(method, params, Some(id)));
+        self.ids.push(id);
+        self
+    }
+    pub fn notify(mut self, method: &str, params: impl Serialize) -> Self {
+        self.push(JsonRpcRequest::new(method, params, None));
+        self
+    }
+    fn push<P: Serialize>(&mut self, request: JsonRpcRequest<'_, P>) {
+        // params that don't serialize surface as a null, the server rejects the call
+        self.requests
+            .push(serde_json::to_value(request).unwrap_or_default());
+    }
+
+    /// one result per `call`, an error object only fails its own call
+    pub async fn send(self) -> JsonClientResult<Vec<Result<Value, JsonRpcError>>, JsonRpcError> {
+        let request = self
+            .client
+            .post(self.client.rpc_path())
+            .json(&self.requests);
+        if self.ids.is_empty() {
+            // only notifications, nothing comes back
+            return ReceiveResp::<JsonFormat>::expect_success(request)
+                .await
+                .map(|_| vec![]);
+        }
+        let resp = ReceiveResp::<JsonFormat>::partial_expect::<
+            Vec<JsonRpcResponse<Value>>,
+            JsonRpcResponse<Value>,
+        >(request)
+        .await
+        .map_err(|err| {
+            err.map_err_body(|context, resp| match resp.into_result() {
+                Err(err_body) => ClientErr::ErrorResponse { context, err_body },
+                Ok(_) => ClientErr::UnparsedErrorResponse {
+                    status: context.got_status,
+                    context,
+                },
+            })
+        })?;
+
+        let mut by_id: HashMap<u64, JsonRpcResponse<Value>> = resp
+            .ok_body
+            .into_iter()
+            .filter_map(|r| Some((r.id?, r)))
+            .collect();
+        let mut results = Vec::with_capacity(self.ids.len());
+        for id in self.ids {
+            match by_id.remove(&id) {
+                Some(r) => results.push(r.into_result()),
+                None => {
+                    return Err(ClientErr::DeserializeError {
+                        context: Box::new(resp.context),
+                        deserialize_error: serde::de::Error::custom(format!(
+                            "no response for JSON-RPC call {id}"
+                        )),
+                    })
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response};
+    use std::sync::Arc;
+
+    /// answers `getblockcount` and rejects other methods, batches answered in reverse
+    struct Node;
+    impl Node {
+        fn answer(request: &Value) -> Option<Value> {
+            let id = request.get("id")?;
+            Some(match request["method"].as_str() {
+                Some("getblockcount") => {
+                    serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": 840_000 })
+                }
+                _ => serde_json::json!({
+                    "jsonrpc": "2.0", "id": id,
+                    "error": { "code": -32601, "message": "Method not found" },
+                }),
+            })
+        }
+    }
+    impl ApiMiddleware for Node {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            let body: Value = serde_json::from_slice(body).unwrap_or_default();
+            let response = match &body {
+                Value::Array(calls) => {
+                    let answers: Vec<Value> = calls.iter().rev().filter_map(Node::answer).collect();
+                    Some(Value::Array(answers))
+                }
+                call => Node::answer(call),
+            };
+            Box::pin(async move {
+                match response {
+                    Some(response) => stub_response(200, &response.to_string()),
+                    None => stub_response(204, ""),
+                }
+            })
+        }
+    }
+
+    struct BitcoinNode {
+        http_client: reqwest::Client,
+        middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    }
+    impl JsonApiClient for BitcoinNode {
+        fn base_url(&self) -> &str {
+            "http://localhost:8332"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+            &self.middlewares
+        }
+    }
+    impl JsonRpcClient for BitcoinNode {}
+
+    #[tokio::test]
+    async fn test_json_rpc() -> anyhow::Result<()> {
+        let node = BitcoinNode {
+            http_client: reqwest::Client::new(),
+            middlewares: vec![Arc::new(Node)],
+        };
+
+        let height: u64 = node.call("getblockcount", ()).await?;
+        assert_eq!(height, 840_000);
+        let err = node
+            .call::<Value>("getblock", ["00ab"])
+            .await
+            .try_into_err_resp(reqwest::StatusCode::OK)?;
+        assert_eq!(err.code, -32601);
+        node.notify("ping", ()).await?;
+
+        let results = node
+            .batch()
+            .call("getblockcount", ())
+            .notify("ping", ())
+            .call("getblock", ["00ab"])
+            .send()
+            .await?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], Ok(serde_json::json!(840_000)));
+        assert!(matches!(&results[1], Err(e) if e.message == "Method not found"));
+        Ok(())
+    }
+}
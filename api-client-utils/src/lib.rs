@@ -1,23 +1,164 @@
 #![allow(async_fn_in_trait)]
-use self::context::{OkRespWithContext, RespContext};
+use self::config::ClientConfig;
+use self::context::{OkRespWithContext, RespContext, ResponseHead, SentRequest};
 use self::error::ClientErr;
-use self::serialization_formats::{ApiFormat, JsonFormat, SerialFormat, XmlFormat};
-use reqwest::{RequestBuilder, StatusCode};
+use self::middleware::{ApiMiddleware, Middlewares, Next};
+use self::retry::RetryPolicy;
+use self::serialization_formats::{
+    ApiFormat, FormUrlEncodedFormat, JsonFormat, SerialFormat, XmlFormat,
+};
+use self::transport::HttpTransport;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{Method, RequestBuilder, StatusCode};
 use serde::de::DeserializeOwned;
+use status_errors::ErrorBody;
 use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 
+pub mod auth;
+pub mod auto_format;
+pub mod backend;
+pub mod batch;
+pub mod binary_format;
+pub mod cancel;
+pub mod case_convert;
+pub mod circuit_breaker;
+pub mod client_builder;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod config;
+#[cfg(feature = "connect")]
+pub mod connect;
+#[cfg(feature = "cookies")]
+pub mod cookies;
+mod datetime;
+pub mod dry_run;
+pub mod endpoints;
+pub mod envelope;
+pub mod environment;
+pub mod error_report;
+pub mod graphql;
+pub mod health;
+#[cfg(feature = "file-cache")]
+pub mod http_cache;
+pub mod http_client;
+#[cfg(feature = "tracing")]
+mod instrument;
+pub mod jsonrpc;
+pub mod link_header;
+pub mod logging;
+pub mod metrics;
+pub mod middleware;
+pub mod multipart;
+pub mod ndjson;
+pub mod oauth2;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod pagination;
+pub mod path_params;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod query;
+pub mod redirect;
+pub mod request_id;
+pub mod retry;
+pub mod signing;
+#[cfg(feature = "sigv4")]
+pub mod sigv4;
+pub mod single_flight;
+pub mod soap;
+pub mod sse;
+pub mod status_errors;
+pub mod streaming;
+#[cfg(feature = "opentelemetry")]
+pub mod trace_context;
+pub mod transport;
+pub mod ttl_cache;
+pub mod unauthorized;
+pub mod upload;
+pub mod url_join;
+#[cfg(feature = "file-cache")]
+pub mod vcr;
+pub mod webhook;
+#[cfg(feature = "websocket")]
+pub mod ws;
+pub mod xmlrpc;
+
 pub mod re_exports {
     pub use reqwest;
+    pub use serde;
 }
 
 pub mod prelude {
+    pub use crate::auth::{Auth, AuthProvider};
+    pub use crate::auto_format::{AutoFormat, ReceiveAuto};
+    #[cfg(feature = "hyper")]
+    pub use crate::backend::HyperBackend;
+    pub use crate::backend::{Backend, HttpBackend};
+    pub use crate::batch::{batch, fetch_all_limited, FetchAll};
+    pub use crate::binary_format::{BinaryFormat, ReceiveBinary};
+    pub use crate::cancel::CancelExt;
+    pub use crate::case_convert::{Case, CaseTranslation};
+    pub use crate::circuit_breaker::CircuitBreaker;
+    pub use crate::client_builder::{ApiClientBuilder, JsonClient};
+    #[cfg(feature = "compression")]
+    pub use crate::compression::{Decompression, Encoding};
+    pub use crate::config::ClientConfig;
+    #[cfg(feature = "connect")]
+    pub use crate::connect::{ConnectClient, ConnectError, RpcProtocol};
+    #[cfg(feature = "cookies")]
+    pub use crate::cookies::CookieJar;
+    pub use crate::dry_run::DryRun;
+    pub use crate::envelope::{DataEnvelope, Envelope, ReceiveEnvelope, SuccessEnvelope};
+    pub use crate::environment::{BaseUrls, Environment};
     pub use crate::error::aliases::{
         ApiResult, JsonApiErr, JsonClientResult, XmlApiErr, XmlApiResult,
     };
-    pub use crate::error::{ClientErr, ResultExt};
-    pub use crate::serialization_formats::{ApiFormat, JsonFormat, SerialFormat};
-    pub use crate::{ApiClient, JsonApiClient, ReceiveJson, ReceiveResp};
+    pub use crate::error::{ClientErr, ErrorKind, ExecuteErr, ResultExt};
+    pub use crate::graphql::{GraphQlClient, GraphQlError};
+    pub use crate::health::{Health, HealthCheck};
+    #[cfg(feature = "file-cache")]
+    pub use crate::http_cache::HttpCache;
+    pub use crate::http_client::{HttpClientConfig, PoolConfig, ProxyConfig};
+    pub use crate::jsonrpc::{JsonRpcClient, JsonRpcError};
+    pub use crate::link_header::Links;
+    pub use crate::logging::DebugLogger;
+    pub use crate::metrics::{ClientMetrics, Metrics};
+    pub use crate::middleware::{ApiMiddleware, Next};
+    pub use crate::multipart::{MultipartPart, MultipartRequestBuilder};
+    pub use crate::ndjson::ReceiveNdjson;
+    pub use crate::oauth2::OAuth2ClientCredentials;
+    pub use crate::pagination::{CursorPagination, Paginated, Pagination};
+    #[cfg(feature = "protobuf")]
+    pub use crate::protobuf::{ProtobufFormat, ReceiveProtobuf};
+    pub use crate::redirect::RedirectPolicy;
+    pub use crate::request_id::with_request_id;
+    pub use crate::retry::{RetryBudget, RetryPolicy};
+    pub use crate::serialization_formats::{
+        ApiFormat, FormUrlEncodedFormat, JsonFormat, SerialFormat,
+    };
+    pub use crate::signing::{RequestSigner, Signed};
+    pub use crate::single_flight::SingleFlight;
+    pub use crate::soap::{SoapClient, SoapFault, SoapFormat};
+    pub use crate::sse::{ReceiveSse, SseEvent};
+    pub use crate::status_errors::ErrorBody;
+    pub use crate::streaming::{ByteStream, Progress, ReceiveStream};
+    #[cfg(feature = "opentelemetry")]
+    pub use crate::trace_context::TraceContext;
+    pub use crate::transport::{HttpTransport, MockTransport};
+    pub use crate::ttl_cache::TtlCache;
+    pub use crate::unauthorized::{OnUnauthorized, UnauthorizedHook};
+    pub use crate::upload::FileBody;
+    #[cfg(feature = "file-cache")]
+    pub use crate::vcr::Vcr;
+    pub use crate::webhook::WebhookVerifier;
+    #[cfg(feature = "websocket")]
+    pub use crate::ws::{WsConnection, WsErr};
+    pub use crate::xmlrpc::{XmlRpcClient, XmlRpcFault, XmlRpcFormat};
+    pub use crate::{
+        ApiClient, ApiRequestBuilder, JsonApiClient, ReceiveForm, ReceiveJson, ReceiveResp,
+    };
 }
 
 // Goals
@@ -39,30 +180,145 @@ pub mod prelude {
 pub trait ApiClient<Format: ApiFormat> {
     fn base_url(&self) -> &str;
     fn http_client(&self) -> &reqwest::Client;
+    /// middlewares every request of this client passes through, outermost first
+    fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+        &[]
+    }
+    /// retry policy applied to every request of this client, unless overridden per request
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+    /// sends requests instead of http_client when set, e.g. a MockTransport in tests
+    fn transport(&self) -> Option<Arc<dyn HttpTransport>> {
+        None
+    }
 
-    fn path(&self, url_path: &str) -> String {
-        let origin = self.base_url().trim().trim_end_matches('/');
-        let path = url_path.trim().trim_start_matches('/');
-        format!("{origin}/{path}")
+    /// `url_path` under base_url, see `url_join::join`
+    fn path(&self, url_path: &str) -> Result<reqwest::Url, url_join::UrlJoinErr> {
+        url_join::join(self.base_url(), url_path)
+    }
+    /// `url_path` with its `{name}` placeholders filled and percent-encoded, to pass to get/post...
+    /// Params are anything Display: numbers, strings, typed-ids `Id`s.
+    fn path_with(
+        &self,
+        url_path: &str,
+        params: &[(&str, &dyn std::fmt::Display)],
+    ) -> Result<String, path_params::PathParamsErr> {
+        path_params::fill(url_path, params)
+    }
+
+    /// timeout, default headers and user agent, 5s timeout and no extra headers unless overridden
+    fn config(&self) -> ClientConfig {
+        ClientConfig::default()
     }
 
     fn default_params(&self, request_builder: RequestBuilder) -> RequestBuilder {
-        Format::with_accept_header(request_builder.timeout(Duration::new(5, 0)))
+        Format::with_accept_header(request_builder)
     }
-    fn get(&self, url_path: &str) -> RequestBuilder {
-        self.default_params(self.http_client().get(self.path(url_path)))
+    /// attach the client-level middlewares and policies to a request
+    fn api_request(&self, request_builder: RequestBuilder) -> ApiRequestBuilder {
+        ApiRequestBuilder::new(request_builder, self.middlewares().to_vec())
+            .with_retry(self.retry_policy())
+            .with_transport(self.transport())
+            .with_config(self.config())
+    }
+    /// request to `url_path`, `map` applied before the default params.
+    /// A path rejected by `path()` fails the request when sent, with ClientErr::Middleware.
+    fn request_for(
+        &self,
+        method: Method,
+        url_path: &str,
+        map: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> ApiRequestBuilder {
+        let (builder, rejected) = match self.path(url_path) {
+            Ok(url) => (self.http_client().request(method, url), None),
+            Err(err) => (
+                self.http_client().request(method, self.base_url()),
+                Some(err),
+            ),
+        };
+        let mut request = self.api_request(self.default_params(map(builder)));
+        if let Some(err) = rejected {
+            // first, so no other middleware sees a request that won't be sent
+            let rejected: Arc<dyn ApiMiddleware> = Arc::new(url_join::Rejected(err));
+            request.middlewares.insert(0, rejected);
+        }
+        request
     }
-    fn post(&self, url_path: &str) -> RequestBuilder {
-        self.default_params(Format::with_content_type_header(
-            self.http_client().post(self.path(url_path)),
+    /// any method, with the default params, and the format's content-type for methods carrying a body
+    fn request(&self, method: Method, url_path: &str) -> ApiRequestBuilder {
+        let has_body = matches!(method, Method::POST | Method::PUT | Method::PATCH);
+        self.request_for(method, url_path, |builder| match has_body {
+            true => Format::with_content_type_header(builder),
+            false => builder,
+        })
+    }
+    fn get(&self, url_path: &str) -> ApiRequestBuilder {
+        self.request(Method::GET, url_path)
+    }
+    fn post(&self, url_path: &str) -> ApiRequestBuilder {
+        self.request(Method::POST, url_path)
+    }
+    fn put(&self, url_path: &str) -> ApiRequestBuilder {
+        self.request(Method::PUT, url_path)
+    }
+    fn patch(&self, url_path: &str) -> ApiRequestBuilder {
+        self.request(Method::PATCH, url_path)
+    }
+    fn delete(&self, url_path: &str) -> ApiRequestBuilder {
+        self.request(Method::DELETE, url_path)
+    }
+    fn head(&self, url_path: &str) -> ApiRequestBuilder {
+        self.request(Method::HEAD, url_path)
+    }
+    /// POST a file streamed from disk, with the Content-Type of the file rather than the format's
+    fn post_file(&self, url_path: &str, file: upload::FileBody) -> ApiRequestBuilder {
+        self.request_for(Method::POST, url_path, |builder| builder)
+            .file_body(file)
+    }
+    fn put_file(&self, url_path: &str, file: upload::FileBody) -> ApiRequestBuilder {
+        self.request_for(Method::PUT, url_path, |builder| builder)
+            .file_body(file)
+    }
+    /// multipart/form-data POST, add fields with `.text()` / `.part()`
+    fn post_multipart(&self, url_path: &str) -> multipart::MultipartRequestBuilder {
+        multipart::MultipartRequestBuilder::new(self.request_for(
+            Method::POST,
+            url_path,
+            |builder| builder,
         ))
     }
+    /// open a websocket on the same base url, with the same middlewares (auth, signing) as REST calls
+    #[cfg(feature = "websocket")]
+    fn ws<In, Out, ErrResp>(
+        &self,
+        url_path: &str,
+    ) -> impl Future<Output = error::aliases::JsonClientResult<ws::WsConnection<In, Out>, ErrResp>>
+    where
+        In: DeserializeOwned,
+        Out: serde::Serialize,
+        ErrResp: ErrorBody,
+    {
+        self.get(url_path).connect_ws()
+    }
 }
 
 /// Convenience alias trait for ApiClient<JsonFormat> since JSON is most common
 pub trait JsonApiClient {
     fn base_url(&self) -> &str;
     fn http_client(&self) -> &reqwest::Client;
+    fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+        &[]
+    }
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+    fn transport(&self) -> Option<Arc<dyn HttpTransport>> {
+        None
+    }
+    fn config(&self) -> ClientConfig {
+        ClientConfig::default()
+    }
 }
 impl<T: JsonApiClient> ApiClient<JsonFormat> for T {
     fn base_url(&self) -> &str {
@@ -71,6 +327,165 @@ impl<T: JsonApiClient> ApiClient<JsonFormat> for T {
     fn http_client(&self) -> &reqwest::Client {
         <Self as JsonApiClient>::http_client(self)
     }
+    fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+        <Self as JsonApiClient>::middlewares(self)
+    }
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        <Self as JsonApiClient>::retry_policy(self)
+    }
+    fn transport(&self) -> Option<Arc<dyn HttpTransport>> {
+        <Self as JsonApiClient>::transport(self)
+    }
+    fn config(&self) -> ClientConfig {
+        <Self as JsonApiClient>::config(self)
+    }
+}
+
+/// A reqwest RequestBuilder that remembers the middlewares of the ApiClient it was created from
+pub struct ApiRequestBuilder {
+    pub builder: RequestBuilder,
+    pub middlewares: Middlewares,
+    pub retry: Option<RetryPolicy>,
+    /// client defaults, only filling in what the request doesn't set itself
+    pub config: Option<ClientConfig>,
+    /// None to send with the reqwest client the builder comes from
+    pub transport: Option<Arc<dyn HttpTransport>>,
+    /// overrides the config's max_response_size
+    pub max_response_size: Option<u64>,
+    /// replace the Accept / Content-Type the ApiFormat set, see `accept` and `content_type`
+    pub format_headers: HeaderMap,
+}
+impl ApiRequestBuilder {
+    pub fn new(builder: RequestBuilder, middlewares: Middlewares) -> Self {
+        Self {
+            builder,
+            middlewares,
+            retry: None,
+            config: None,
+            transport: None,
+            max_response_size: None,
+            format_headers: HeaderMap::new(),
+        }
+    }
+    pub fn with_transport(self, transport: Option<Arc<dyn HttpTransport>>) -> Self {
+        Self { transport, ..self }
+    }
+    pub fn with_config(self, config: ClientConfig) -> Self {
+        Self {
+            config: Some(config),
+            ..self
+        }
+    }
+    /// escape hatch for reqwest builder methods not mirrored here
+    pub fn map(self, f: impl FnOnce(RequestBuilder) -> RequestBuilder) -> Self {
+        Self {
+            builder: f(self.builder),
+            ..self
+        }
+    }
+    /// add a middleware for this request only, it runs after the client's middlewares
+    pub fn with_middleware(mut self, middleware: impl ApiMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+    /// override the client's retry policy for this request
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        self.with_retry(Some(policy))
+    }
+    pub fn no_retry(self) -> Self {
+        self.with_retry(None)
+    }
+    pub fn with_retry(self, retry: Option<RetryPolicy>) -> Self {
+        Self { retry, ..self }
+    }
+    /// override the client's body size limit for this request, e.g. for a known large export
+    pub fn max_response_size(self, bytes: u64) -> Self {
+        Self {
+            max_response_size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Ask for another format than the client's on this request, e.g. `.accept("text/csv")`
+    /// then `recv_text` or `recv_bytes`: error statuses still become ClientErr with the
+    /// error body parsed in the client's format.
+    pub fn accept(self, media_type: &str) -> Self {
+        self.format_header(ACCEPT, media_type)
+    }
+    /// send a body in another format than the client's, e.g. `.content_type("text/csv").body(csv)`
+    pub fn content_type(self, media_type: &str) -> Self {
+        self.format_header(CONTENT_TYPE, media_type)
+    }
+    fn format_header(mut self, name: HeaderName, media_type: &str) -> Self {
+        match HeaderValue::try_from(media_type) {
+            Ok(value) => {
+                self.format_headers.insert(name, value);
+                self
+            }
+            // left to reqwest, which fails the request with ClientErr::BuildRequest
+            Err(_) => self.header(name, media_type),
+        }
+    }
+
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        reqwest::header::HeaderName: TryFrom<K>,
+        <reqwest::header::HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        reqwest::header::HeaderValue: TryFrom<V>,
+        <reqwest::header::HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.map(|b| b.header(key, value))
+    }
+    pub fn headers(self, headers: reqwest::header::HeaderMap) -> Self {
+        self.map(|b| b.headers(headers))
+    }
+    pub fn bearer_auth<T: std::fmt::Display>(self, token: T) -> Self {
+        self.map(|b| b.bearer_auth(token))
+    }
+    pub fn basic_auth<U: std::fmt::Display, P: std::fmt::Display>(
+        self,
+        username: U,
+        password: Option<P>,
+    ) -> Self {
+        self.map(|b| b.basic_auth(username, password))
+    }
+    pub fn query<T: serde::Serialize + ?Sized>(self, query: &T) -> Self {
+        self.map(|b| b.query(query))
+    }
+    pub fn json<T: serde::Serialize + ?Sized>(self, json: &T) -> Self {
+        self.map(|b| b.json(json))
+    }
+    pub fn form<T: serde::Serialize + ?Sized>(self, form: &T) -> Self {
+        self.map(|b| b.form(form))
+    }
+    pub fn body<T: Into<reqwest::Body>>(self, body: T) -> Self {
+        self.map(|b| b.body(body))
+    }
+    /// stream a file as the body, with its Content-Type and Content-Length
+    pub fn file_body(self, file: upload::FileBody) -> Self {
+        let (len, content_type) = (file.len, file.content_type.clone());
+        self.map(|b| {
+            b.body(reqwest::Body::wrap_stream(file.into_stream()))
+                .header(reqwest::header::CONTENT_LENGTH, len)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+        })
+    }
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.map(|b| b.timeout(timeout))
+    }
+
+    /// None if the body is a stream
+    pub fn try_clone(&self) -> Option<Self> {
+        Some(Self {
+            builder: self.builder.try_clone()?,
+            middlewares: self.middlewares.clone(),
+            retry: self.retry.clone(),
+            config: self.config.clone(),
+            transport: self.transport.clone(),
+            max_response_size: self.max_response_size,
+            format_headers: self.format_headers.clone(),
+        })
+    }
 }
 
 pub mod serialization_formats {
@@ -80,6 +495,13 @@ pub mod serialization_formats {
     pub trait SerialFormat {
         type Error: std::fmt::Debug;
         fn from_str<T: for<'a> Deserialize<'a>>(input: &str) -> Result<T, Self::Error>;
+        /// for formats that depend on the response Content-Type, the others just read `input`
+        fn from_str_with_content_type<T: for<'a> Deserialize<'a>>(
+            input: &str,
+            _content_type: Option<&str>,
+        ) -> Result<T, Self::Error> {
+            Self::from_str(input)
+        }
     }
     #[derive(Debug)]
     pub struct JsonFormat;
@@ -89,6 +511,15 @@ pub mod serialization_formats {
             serde_json::from_str(input)
         }
     }
+    /// `a=1&b=two`, for OAuth token endpoints and legacy APIs
+    #[derive(Debug)]
+    pub struct FormUrlEncodedFormat;
+    impl SerialFormat for FormUrlEncodedFormat {
+        type Error = serde_urlencoded::de::Error;
+        fn from_str<T: for<'a> Deserialize<'a>>(input: &str) -> Result<T, Self::Error> {
+            serde_urlencoded::from_str(input)
+        }
+    }
     #[derive(Debug)]
     pub struct XmlFormat;
     impl SerialFormat for XmlFormat {
@@ -110,6 +541,14 @@ pub mod serialization_formats {
             builder.header("Content-Type", "application/json")
         }
     }
+    impl ApiFormat for FormUrlEncodedFormat {
+        fn with_accept_header(builder: RequestBuilder) -> RequestBuilder {
+            builder.header("Accept", "application/x-www-form-urlencoded")
+        }
+        fn with_content_type_header(builder: RequestBuilder) -> RequestBuilder {
+            builder.header("Content-Type", "application/x-www-form-urlencoded")
+        }
+    }
     impl ApiFormat for XmlFormat {
         fn with_accept_header(builder: RequestBuilder) -> RequestBuilder {
             builder.header("Accept", "application/xml")
@@ -122,85 +561,178 @@ pub mod serialization_formats {
 
 impl<T: Sized + ToRequestClient> ReceiveResp<JsonFormat> for T {} // auto-implement for RequestClient, RequestBuilder and more
 pub trait ReceiveResp<F: SerialFormat>: Sized + ToRequestClient {
-    async fn expect_ok<Ok: DeserializeOwned, ErrResp: DeserializeOwned>(
+    async fn expect_ok<Ok: DeserializeOwned, ErrResp: ErrorBody>(
         self,
     ) -> Result<Ok, ClientErr<ErrResp, F>> {
         self.partial_expect().await.map(|ok| ok.ok_body)
     }
-    async fn expect_err_resp<Ok: DeserializeOwned, ErrResp: DeserializeOwned>(
+    async fn expect_err_resp<Ok: DeserializeOwned, ErrResp: ErrorBody>(
         self,
         expect_status: StatusCode,
     ) -> Result<ErrResp, ClientErr<ErrResp, F>> {
         match self.partial_expect::<Ok, ErrResp>().await {
             Ok(ok) => Err(ClientErr::ExpectedErrorResponse {
-                context: Some(ok.context),
+                context: Some(Box::new(ok.context)),
             }),
             Err(err) => err.try_into_err_resp(expect_status),
         }
     }
 
-    // async fn expect_status<Ok: DeserializeOwned, ErrResp: DeserializeOwned>(
-    //     self,
-    //     expect_status: StatusCode,
-    // ) -> Result<Ok, RequestErr<ErrResp, F>> {
-    // }
+    /// Like expect_ok, but any status other than `expect_status` is ClientErr::ExpectedStatus.
+    /// An expected error status still comes back as ClientErr::ErrorResponse, with its body read as ErrResp.
+    async fn expect_status<Ok: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+        expect_status: StatusCode,
+    ) -> Result<Ok, ClientErr<ErrResp, F>> {
+        match self.partial_expect::<Ok, ErrResp>().await {
+            Ok(ok) => {
+                ok.context.expect_status(expect_status)?;
+                Ok(ok.ok_body)
+            }
+            Err(err) => {
+                if let Some(context) = err.context() {
+                    context.expect_status(expect_status)?;
+                }
+                Err(err)
+            }
+        }
+    }
 
-    fn partial_expect<Ok: DeserializeOwned, ErrResp: DeserializeOwned>(
+    /// body as text, for CSV exports, HTML... error statuses are still turned into ClientErr
+    async fn recv_text<ErrResp: ErrorBody>(self) -> Result<String, ClientErr<ErrResp, F>> {
+        self.expect_success()
+            .await
+            .map(|context| context.response_text)
+    }
+    /// raw body bytes, error statuses are still turned into ClientErr
+    async fn recv_bytes<ErrResp: ErrorBody>(self) -> Result<bytes::Bytes, ClientErr<ErrResp, F>> {
+        let request = self.try_into().map_err(ClientErr::BuildRequest)?;
+        let sent = request.sent();
+        let max_response_size = request.max_response_size;
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(ClientErr::from_error_response(sent, response).await);
+        }
+        match max_response_size {
+            Some(limit) => read_bytes_limited(response, limit).await,
+            None => response.bytes().await.map_err(ClientErr::ReadRespBodyText),
+        }
+    }
+
+    /// send the request and read the body, with non-2xx statuses turned into ClientErr
+    fn expect_success<ErrResp: ErrorBody>(
+        self,
+    ) -> impl Future<Output = Result<RespContext, ClientErr<ErrResp, F>>> {
+        async move {
+            let request = self.try_into().map_err(ClientErr::BuildRequest)?;
+            read_success(request).await
+        }
+    }
+
+    fn partial_expect<Ok: DeserializeOwned, ErrResp: ErrorBody>(
         self,
     ) -> impl Future<Output = Result<OkRespWithContext<Ok>, ClientErr<ErrResp, F>>> {
         async move {
-            let RequestClient { request, client } =
-                self.try_into().map_err(ClientErr::BuildRequest)?;
-            let (method, url) = { (request.method().clone(), request.url().clone()) };
+            let request = self.try_into().map_err(ClientErr::BuildRequest)?;
+            #[cfg(feature = "tracing")]
+            let span = instrument::request_span(&request.request);
 
-            let response = client
-                .execute(request)
-                .await
-                .map_err(ClientErr::ExecuteRequest)?;
-            let got_status = response.status();
-            let context = RespContext {
-                method,
-                url: Box::new(url),
-                got_status: response.status(),
-                response_text: response.text().await.map_err(ClientErr::ReadRespBodyText)?,
-            };
+            let result = async move {
+                let context = read_success(request).await?;
 
-            // if err, try to deserialize error body into ErrResp type
-            if !got_status.is_success() {
-                match F::from_str::<ErrResp>(&context.response_text) {
-                    Ok(source) => {
-                        return Err(ClientErr::ErrorResponse {
-                            context,
-                            err_body: source,
-                        })
-                    }
-                    Err(deserialize_error) => {
-                        return Err(ClientErr::DeserializeError {
-                            context,
-                            deserialize_error,
-                        })
-                    }
+                // try to deserialize ok response
+                let content_type = context.header("content-type");
+                let ok_body = F::from_str_with_content_type(&context.response_text, content_type);
+                match ok_body {
+                    Ok(v) => Ok(OkRespWithContext {
+                        ok_body: v,
+                        context,
+                    }),
+                    Err(deserialize_error) => Err(ClientErr::DeserializeError {
+                        context: Box::new(context),
+                        deserialize_error,
+                    }),
                 }
-            }
+            };
+            #[cfg(feature = "tracing")]
+            let result = instrument::record(span, result);
+            result.await
+        }
+    }
+}
 
-            // try to deserialize ok response
-            match F::from_str(&context.response_text) {
-                Ok(v) => Ok(OkRespWithContext {
-                    ok_body: v,
-                    context,
-                }),
-                Err(deserialize_error) => Err(ClientErr::DeserializeError {
-                    context,
-                    deserialize_error,
-                }),
-            }
+async fn read_success<ErrResp: ErrorBody, F: SerialFormat>(
+    request: RequestClient,
+) -> Result<RespContext, ClientErr<ErrResp, F>> {
+    let sent = request.sent();
+    let max_response_size = request.max_response_size;
+
+    let response = request.send().await?;
+    let head = ResponseHead::of(&response);
+    let got_status = head.status;
+    let response_text = match max_response_size {
+        Some(limit) => read_text_limited(response, limit).await?,
+        None => response.text().await.map_err(ClientErr::ReadRespBodyText)?,
+    };
+    let context = sent.response_context(head, response_text);
+
+    // if err, try to deserialize error body into ErrResp type
+    if !got_status.is_success() {
+        return Err(ClientErr::from_error_context(context));
+    }
+    Ok(context)
+}
+
+/// read_bytes_limited, as text
+async fn read_text_limited<ErrResp, F: SerialFormat>(
+    response: reqwest::Response,
+    limit: u64,
+) -> Result<String, ClientErr<ErrResp, F>> {
+    let body = read_bytes_limited(response, limit).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+/// stop reading once the body goes over `limit`, before buffering all of it
+async fn read_bytes_limited<ErrResp, F: SerialFormat>(
+    mut response: reqwest::Response,
+    limit: u64,
+) -> Result<bytes::Bytes, ClientErr<ErrResp, F>> {
+    if let Some(announced) = response.content_length().filter(|&len| len > limit) {
+        return Err(ClientErr::ResponseTooLarge {
+            limit,
+            read: 0,
+            announced: Some(announced),
+        });
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(ClientErr::ReadRespBodyText)?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() as u64 > limit {
+            return Err(ClientErr::ResponseTooLarge {
+                limit,
+                read: body.len() as u64,
+                announced: None,
+            });
         }
     }
+    Ok(body.into())
 }
 
 pub struct RequestClient {
     pub request: reqwest::Request,
     pub client: reqwest::Client,
+    pub middlewares: Middlewares,
+    pub retry: Option<RetryPolicy>,
+    /// replaces `client` for sending when set
+    pub transport: Option<Arc<dyn HttpTransport>>,
+    /// bodies read whole stop at this many bytes
+    pub max_response_size: Option<u64>,
+    /// header the config put a request id in, to keep it in the RespContext
+    pub request_id_header: Option<HeaderName>,
 }
 // impl TryFrom<RequestBuilder> for RequestClient {
 //     type Error = reqwest::Error;
@@ -208,6 +740,84 @@ pub struct RequestClient {
 //         builder.try_build_split()
 //     }
 // }
+impl RequestClient {
+    /// send the request through the middlewares, and the retry policy if any
+    pub async fn execute(self) -> Result<reqwest::Response, error::ExecuteErr> {
+        let Self {
+            request,
+            client,
+            middlewares,
+            retry,
+            transport,
+            max_response_size,
+            ..
+        } = self;
+        let transport: &dyn HttpTransport = match &transport {
+            Some(transport) => transport,
+            None => &client,
+        };
+        let next = Next::new(transport, &middlewares).with_max_response_size(max_response_size);
+        match retry {
+            Some(policy) => policy.execute(request, next).await,
+            None => next.run(request).await,
+        }
+    }
+    /// execute, with failures as a ClientErr keeping a snapshot of the request
+    pub async fn send<ErrResp, F: SerialFormat>(
+        self,
+    ) -> Result<reqwest::Response, ClientErr<ErrResp, F>> {
+        let snapshot = self.snapshot();
+        let result = self.execute().await;
+        result.map_err(|err| ClientErr::from_execute(err, snapshot))
+    }
+    /// execute, with the request timeout only bounding the wait for the response head:
+    /// a streamed body then takes as long as it needs
+    pub async fn execute_streamed(mut self) -> Result<reqwest::Response, error::ExecuteErr> {
+        let Some(timeout) = self.request.timeout_mut().take() else {
+            return self.execute().await;
+        };
+        match tokio::time::timeout(timeout, self.execute()).await {
+            Ok(result) => result,
+            Err(elapsed) => Err(error::ExecuteErr::Backend(backend::BackendErr::new(
+                error::ErrorKind::Timeout,
+                elapsed,
+            ))),
+        }
+    }
+    /// send, with the timeout of execute_streamed
+    pub async fn send_streamed<ErrResp, F: SerialFormat>(
+        self,
+    ) -> Result<reqwest::Response, ClientErr<ErrResp, F>> {
+        let snapshot = self.snapshot();
+        let result = self.execute_streamed().await;
+        result.map_err(|err| ClientErr::from_execute(err, snapshot))
+    }
+    /// None if the body is a stream
+    pub fn try_clone(&self) -> Option<Self> {
+        Some(Self {
+            request: self.request.try_clone()?,
+            client: self.client.clone(),
+            middlewares: self.middlewares.clone(),
+            retry: self.retry.clone(),
+            transport: self.transport.clone(),
+            max_response_size: self.max_response_size,
+            request_id_header: self.request_id_header.clone(),
+        })
+    }
+    /// the request as it is before going through the middlewares, for errors without a response
+    pub fn snapshot(&self) -> error_report::RequestSnapshot {
+        error_report::RequestSnapshot::of(&self.request)
+    }
+    /// what the RespContext of the response will need of the request
+    pub fn sent(&self) -> SentRequest {
+        let mut sent = SentRequest::of(&self.request);
+        sent.request_id = self.request_id_header.as_ref().and_then(|name| {
+            let id = self.request.headers().get(name)?.to_str().ok()?;
+            Some(id.to_string())
+        });
+        sent
+    }
+}
 pub trait ToRequestClient {
     fn try_into(self) -> Result<RequestClient, reqwest::Error>;
 }
@@ -221,34 +831,105 @@ impl ToRequestClient for RequestBuilder {
         self.try_build_split()
     }
 }
+impl ToRequestClient for ApiRequestBuilder {
+    fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+        let RequestClient {
+            mut request,
+            client,
+            ..
+        } = self.builder.try_build_split()?;
+        for (name, value) in &self.format_headers {
+            request.headers_mut().insert(name, value.clone());
+        }
+        let mut max_response_size = self.max_response_size;
+        let mut request_id_header = None;
+        if let Some(config) = self.config {
+            max_response_size = max_response_size.or(config.max_response_size);
+            request_id_header = config.request_id_header.clone();
+            config.apply_defaults(&mut request);
+        }
+        Ok(RequestClient {
+            request,
+            client,
+            middlewares: self.middlewares,
+            retry: self.retry,
+            transport: self.transport,
+            max_response_size,
+            request_id_header,
+        })
+    }
+}
 
 pub trait ReceiveJson {
-    fn recv_json<Ok: DeserializeOwned, ErrResp: DeserializeOwned>(
+    fn recv_json<Ok: DeserializeOwned, ErrResp: ErrorBody>(
         self,
     ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, JsonFormat>>>;
 }
 // auto-impl ReceiveJson for all ReceiveResp
 impl<T: ReceiveResp<JsonFormat>> ReceiveJson for T {
-    fn recv_json<Ok: DeserializeOwned, ErrResp: DeserializeOwned>(
+    fn recv_json<Ok: DeserializeOwned, ErrResp: ErrorBody>(
         self,
     ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, JsonFormat>>> {
         self.expect_ok()
     }
 }
 
+pub trait ReceiveForm {
+    fn recv_form<Ok: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+    ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, FormUrlEncodedFormat>>>;
+}
+// wrapper so that ReceiveResp<FormUrlEncodedFormat> doesn't make JSON calls like `.expect_ok()` ambiguous
+struct FormEncoded<T>(T);
+impl<T: ToRequestClient> ToRequestClient for FormEncoded<T> {
+    fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+        self.0.try_into()
+    }
+}
+impl<T: ToRequestClient> ReceiveResp<FormUrlEncodedFormat> for FormEncoded<T> {}
+impl<T: ToRequestClient> ReceiveForm for T {
+    fn recv_form<Ok: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+    ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, FormUrlEncodedFormat>>> {
+        ReceiveResp::<FormUrlEncodedFormat>::expect_ok(FormEncoded(self))
+    }
+}
+
 pub mod context {
     use super::prelude::*;
-    use reqwest::{Method, StatusCode, Url};
+    use reqwest::header::HeaderMap;
+    use reqwest::{Method, Request, Response, StatusCode, Url, Version};
     use serde::de::DeserializeOwned;
+    use std::time::{Duration, Instant, SystemTime};
 
     #[derive(Debug, Clone)]
     pub struct RespContext {
         pub method: Method,
         pub url: Box<Url>,
         pub got_status: StatusCode,
+        /// rate limits, pagination links, request ids...
+        pub headers: Box<HeaderMap>,
+        pub version: Version,
+        /// urls followed by `RedirectPolicy` before this response, oldest first
+        pub redirects: Box<[Url]>,
+        /// set by the `Decompression` middleware
+        pub body_size: Option<BodySize>,
         pub response_text: String,
+        /// the `Idempotency-Key` the request was sent with, the same across its retries
+        pub idempotency_key: Option<String>,
+        /// the id sent with `ClientConfig::request_ids()`
+        pub request_id: Option<String>,
+        pub timing: Timing,
     }
     impl RespContext {
+        /// None if missing or not valid UTF-8
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers.get(name)?.to_str().ok()
+        }
+        /// how long the server asks to wait before trying again, on 429 and 503 mostly
+        pub fn retry_after(&self) -> Option<std::time::Duration> {
+            crate::retry::retry_after(&self.headers)
+        }
         pub fn body_from_json<B: DeserializeOwned>(&self) -> anyhow::Result<B> {
             serde_json::from_str(&self.response_text).map_err(anyhow::Error::from)
         }
@@ -267,6 +948,125 @@ pub mod context {
         }
     }
 
+    /// How long a call took, from handing the request to the middlewares to having read the body
+    #[derive(Debug, Clone, Copy)]
+    pub struct Timing {
+        pub started_at: SystemTime,
+        pub duration: Duration,
+        /// 1 unless the retry policy re-sent the request
+        pub attempts: u32,
+    }
+    impl Timing {
+        pub fn ended_at(&self) -> SystemTime {
+            self.started_at + self.duration
+        }
+    }
+
+    /// What is kept of a request once it is handed to the middlewares, to build the RespContext of its response
+    #[derive(Debug, Clone)]
+    pub struct SentRequest {
+        pub method: Method,
+        pub url: Url,
+        pub idempotency_key: Option<String>,
+        pub request_id: Option<String>,
+        pub started_at: SystemTime,
+        /// monotonic twin of started_at, for the duration
+        pub started: Instant,
+    }
+    impl SentRequest {
+        /// a request starting now
+        pub fn new(method: Method, url: Url) -> Self {
+            Self {
+                method,
+                url,
+                idempotency_key: None,
+                request_id: None,
+                started_at: SystemTime::now(),
+                started: Instant::now(),
+            }
+        }
+        pub fn of(request: &Request) -> Self {
+            let idempotency_key = request
+                .headers()
+                .get(crate::config::IDEMPOTENCY_KEY)
+                .and_then(|key| key.to_str().ok())
+                .map(str::to_string);
+            Self {
+                idempotency_key,
+                ..Self::new(request.method().clone(), request.url().clone())
+            }
+        }
+        /// context without response headers, for responses not coming off the wire
+        pub fn context(self, got_status: StatusCode, response_text: String) -> RespContext {
+            self.response_context(ResponseHead::status(got_status), response_text)
+        }
+        pub fn response_context(self, head: ResponseHead, response_text: String) -> RespContext {
+            RespContext {
+                method: self.method,
+                url: Box::new(self.url),
+                got_status: head.status,
+                headers: Box::new(head.headers),
+                version: head.version,
+                redirects: head.redirects.into_boxed_slice(),
+                body_size: head.body_size,
+                response_text,
+                idempotency_key: self.idempotency_key,
+                request_id: self.request_id,
+                timing: Timing {
+                    started_at: self.started_at,
+                    duration: self.started.elapsed(),
+                    attempts: head.attempts,
+                },
+            }
+        }
+    }
+
+    /// Everything about a response but its body, taken before the body is read
+    #[derive(Debug, Clone)]
+    pub struct ResponseHead {
+        pub status: StatusCode,
+        pub headers: HeaderMap,
+        pub version: Version,
+        pub redirects: Vec<Url>,
+        pub body_size: Option<BodySize>,
+        pub attempts: u32,
+    }
+    impl ResponseHead {
+        pub fn of(response: &Response) -> Self {
+            let redirects = response
+                .extensions()
+                .get::<crate::redirect::RedirectChain>();
+            Self {
+                status: response.status(),
+                headers: response.headers().clone(),
+                version: response.version(),
+                redirects: redirects.map(|chain| chain.0.clone()).unwrap_or_default(),
+                body_size: response.extensions().get::<BodySize>().copied(),
+                attempts: response
+                    .extensions()
+                    .get::<crate::retry::Attempts>()
+                    .map_or(1, |attempts| attempts.0),
+            }
+        }
+        pub fn status(status: StatusCode) -> Self {
+            Self {
+                status,
+                headers: HeaderMap::new(),
+                version: Version::default(),
+                redirects: vec![],
+                body_size: None,
+                attempts: 1,
+            }
+        }
+    }
+
+    /// Bytes received vs bytes after decoding the Content-Encoding
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BodySize {
+        pub wire: u64,
+        pub decoded: u64,
+    }
+
     #[derive(Debug)]
     pub struct OkRespWithContext<Body> {
         pub ok_body: Body,
@@ -284,6 +1084,7 @@ pub mod context {
 pub mod error {
     use self::aliases::ApiResult;
     use super::*;
+    use crate::error_report::RequestSnapshot;
 
     pub mod aliases {
         use super::*;
@@ -298,41 +1099,233 @@ pub mod error {
     #[derive(thiserror::Error, Debug)]
     pub enum ClientErr<ErrResp, F: SerialFormat> {
         BuildRequest(reqwest::Error),
-        ExecuteRequest(reqwest::Error),
+        ExecuteRequest {
+            source: reqwest::Error,
+            request: Box<RequestSnapshot>,
+        },
+        /// the HttpBackend sending in place of reqwest failed
+        Backend {
+            source: crate::backend::BackendErr,
+            request: Box<RequestSnapshot>,
+        },
+        /// `request` is None for failures outside of sending, e.g. a stream that can't paginate
+        Middleware {
+            source: anyhow::Error,
+            request: Option<Box<RequestSnapshot>>,
+        },
+        CircuitOpen {
+            retry_in: Duration,
+            request: Box<RequestSnapshot>,
+        },
+        /// given up on before the response was read, see `CancelExt`
+        Cancelled {
+            reason: crate::cancel::CancelReason,
+        },
         ReadRespBodyText(reqwest::Error),
+        /// the body ended before the Content-Length announced
+        IncompleteBody {
+            expected: u64,
+            received: u64,
+        },
+        WriteFile {
+            path: std::path::PathBuf,
+            source: std::io::Error,
+        },
+        /// the body went over the max_response_size, reading stopped after `read` bytes
+        ResponseTooLarge {
+            limit: u64,
+            read: u64,
+            /// the Content-Length, when it gave the size away before reading
+            announced: Option<u64>,
+        },
         ExpectedErrorResponse {
-            context: Option<RespContext>,
+            context: Option<Box<RespContext>>,
         },
         ExpectedStatus {
             context: Box<RespContext>,
             expected_status: StatusCode,
         },
         DeserializeError {
-            context: RespContext,
+            context: Box<RespContext>,
             deserialize_error: F::Error,
         },
         ErrorResponse {
-            context: RespContext,
+            context: Box<RespContext>,
             err_body: ErrResp,
         },
+        /// error status whose body isn't an ErrResp, e.g. an HTML page from a proxy, the text is in the context
+        UnparsedErrorResponse {
+            status: StatusCode,
+            context: Box<RespContext>,
+        },
+        GraphQlErrors {
+            context: Box<RespContext>,
+            errors: Vec<crate::graphql::GraphQlError>,
+        },
     }
     impl<ErrResp, F: SerialFormat> ClientErr<ErrResp, F> {
         pub fn context(&self) -> Option<&RespContext> {
             match self {
                 ClientErr::BuildRequest(_) => None,
-                ClientErr::ExecuteRequest(_) => None,
+                ClientErr::ExecuteRequest { .. } => None,
+                ClientErr::Backend { .. } => None,
+                ClientErr::Middleware { .. } => None,
+                ClientErr::CircuitOpen { .. } => None,
+                ClientErr::Cancelled { .. } => None,
                 ClientErr::ReadRespBodyText(_) => None,
-                ClientErr::ExpectedErrorResponse { context } => context.as_ref(),
+                ClientErr::IncompleteBody { .. } => None,
+                ClientErr::WriteFile { .. } => None,
+                ClientErr::ResponseTooLarge { .. } => None,
+                ClientErr::ExpectedErrorResponse { context } => context.as_deref(),
                 ClientErr::ExpectedStatus { context, .. } => Some(context),
                 ClientErr::DeserializeError { context, .. } => Some(context),
                 ClientErr::ErrorResponse { context, .. } => Some(context),
+                ClientErr::UnparsedErrorResponse { context, .. } => Some(context),
+                ClientErr::GraphQlErrors { context, .. } => Some(context),
+            }
+        }
+        /// the request as sent, for errors without a response: the others have the method and url in their context
+        pub fn request(&self) -> Option<&RequestSnapshot> {
+            match self {
+                ClientErr::ExecuteRequest { request, .. } => Some(request),
+                ClientErr::Backend { request, .. } => Some(request),
+                ClientErr::Middleware { request, .. } => request.as_deref(),
+                ClientErr::CircuitOpen { request, .. } => Some(request),
+                _ => None,
             }
         }
         pub fn response_text(&self) -> Option<&str> {
             self.context().map(|ctx| ctx.response_text.as_str())
         }
+        /// turn ErrorResponse into any error, keeping the other variants as they are
+        pub fn map_err_body<E2>(
+            self,
+            f: impl FnOnce(Box<RespContext>, ErrResp) -> ClientErr<E2, F>,
+        ) -> ClientErr<E2, F> {
+            match self {
+                ClientErr::BuildRequest(e) => ClientErr::BuildRequest(e),
+                ClientErr::ExecuteRequest { source, request } => {
+                    ClientErr::ExecuteRequest { source, request }
+                }
+                ClientErr::Backend { source, request } => ClientErr::Backend { source, request },
+                ClientErr::Middleware { source, request } => {
+                    ClientErr::Middleware { source, request }
+                }
+                ClientErr::CircuitOpen { retry_in, request } => {
+                    ClientErr::CircuitOpen { retry_in, request }
+                }
+                ClientErr::Cancelled { reason } => ClientErr::Cancelled { reason },
+                ClientErr::ReadRespBodyText(e) => ClientErr::ReadRespBodyText(e),
+                ClientErr::IncompleteBody { expected, received } => {
+                    ClientErr::IncompleteBody { expected, received }
+                }
+                ClientErr::WriteFile { path, source } => ClientErr::WriteFile { path, source },
+                ClientErr::ResponseTooLarge {
+                    limit,
+                    read,
+                    announced,
+                } => ClientErr::ResponseTooLarge {
+                    limit,
+                    read,
+                    announced,
+                },
+                ClientErr::ExpectedErrorResponse { context } => {
+                    ClientErr::ExpectedErrorResponse { context }
+                }
+                ClientErr::ExpectedStatus {
+                    context,
+                    expected_status,
+                } => ClientErr::ExpectedStatus {
+                    context,
+                    expected_status,
+                },
+                ClientErr::DeserializeError {
+                    context,
+                    deserialize_error,
+                } => ClientErr::DeserializeError {
+                    context,
+                    deserialize_error,
+                },
+                ClientErr::ErrorResponse { context, err_body } => f(context, err_body),
+                ClientErr::UnparsedErrorResponse { status, context } => {
+                    ClientErr::UnparsedErrorResponse { status, context }
+                }
+                ClientErr::GraphQlErrors { context, errors } => {
+                    ClientErr::GraphQlErrors { context, errors }
+                }
+            }
+        }
+        /// the `Retry-After` of the error response, if any
+        pub fn retry_after(&self) -> Option<Duration> {
+            self.context()?.retry_after()
+        }
+        pub fn kind(&self) -> ErrorKind {
+            match self {
+                ClientErr::BuildRequest(e) => ErrorKind::of_request(e),
+                ClientErr::ExecuteRequest { source, .. } => ErrorKind::of_request(source),
+                ClientErr::Backend { source, .. } => source.kind,
+                ClientErr::Middleware { .. } => ErrorKind::Middleware,
+                ClientErr::CircuitOpen { .. } => ErrorKind::CircuitOpen,
+                ClientErr::Cancelled { .. } => ErrorKind::Cancelled,
+                ClientErr::ReadRespBodyText(e) => ErrorKind::of_request(e),
+                ClientErr::IncompleteBody { .. } => ErrorKind::Protocol,
+                ClientErr::WriteFile { .. } => ErrorKind::Io,
+                ClientErr::ResponseTooLarge { .. } => ErrorKind::Protocol,
+                ClientErr::ExpectedErrorResponse { .. } => ErrorKind::Protocol,
+                ClientErr::ExpectedStatus { context, .. } => {
+                    ErrorKind::of_status(context.got_status)
+                }
+                ClientErr::DeserializeError { .. } => ErrorKind::Deserialize,
+                ClientErr::ErrorResponse { context, .. } => {
+                    ErrorKind::of_status(context.got_status)
+                }
+                ClientErr::UnparsedErrorResponse { status, .. } => ErrorKind::of_status(*status),
+                ClientErr::GraphQlErrors { context, .. } => {
+                    ErrorKind::of_status(context.got_status)
+                }
+            }
+        }
+        /// Worth sending again as is: timeouts, connection failures, 5xx and 429 responses.
+        /// Same as the default RetryPolicy, plus 429.
+        pub fn is_retryable(&self) -> bool {
+            let too_many_requests = self
+                .context()
+                .is_some_and(|context| context.got_status == StatusCode::TOO_MANY_REQUESTS);
+            too_many_requests
+                || matches!(
+                    self.kind(),
+                    ErrorKind::Timeout | ErrorKind::Connect | ErrorKind::ServerError
+                )
+        }
     }
-    impl<ErrResp: DeserializeOwned, F: SerialFormat> ClientErr<ErrResp, F> {
+    impl<ErrResp: ErrorBody, F: SerialFormat> ClientErr<ErrResp, F> {
+        /// ErrorResponse if the body parses as ErrResp, UnparsedErrorResponse otherwise
+        pub fn from_error_context(context: RespContext) -> Self {
+            let context = Box::new(context);
+            let content_type = context.header("content-type");
+            let err_body = ErrResp::from_status_body::<F>(
+                context.got_status,
+                content_type,
+                &context.response_text,
+            );
+            match err_body {
+                Ok(err_body) => ClientErr::ErrorResponse { context, err_body },
+                Err(_) => ClientErr::UnparsedErrorResponse {
+                    status: context.got_status,
+                    context,
+                },
+            }
+        }
+        /// read the body of a non-2xx response into ErrorResponse / DeserializeError
+        pub async fn from_error_response(sent: SentRequest, response: reqwest::Response) -> Self {
+            let head = ResponseHead::of(&response);
+            match response.text().await {
+                Ok(response_text) => {
+                    Self::from_error_context(sent.response_context(head, response_text))
+                }
+                Err(e) => ClientErr::ReadRespBodyText(e),
+            }
+        }
         pub fn try_into_err_resp(
             self,
             expect_status: StatusCode,
@@ -353,44 +1346,209 @@ pub mod error {
         F::Error: std::fmt::Display,
     {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            if let Some(RespContext { method, url, .. }) = self.context() {
+            if let Some(RespContext {
+                method,
+                url,
+                timing,
+                ..
+            }) = self.context()
+            {
+                write!(f, "{method} {url} after {:?}", timing.duration)?;
+                match timing.attempts {
+                    1 => writeln!(f)?,
+                    attempts => writeln!(f, " and {attempts} attempts")?,
+                }
+            } else if let Some(RequestSnapshot { method, url, .. }) = self.request() {
                 writeln!(f, "{method} {url}")?;
             }
 
             let error_msg_core = match self {
-                    ClientErr::BuildRequest(e) => format!("Failed building request: {e}"),
-                    ClientErr::ExecuteRequest(e) => format!("Failed executing request: {e}"),
-                    ClientErr::ReadRespBodyText(e) => format!("Failed reading response text: {e}"),
-                    ClientErr::ExpectedErrorResponse { .. } => {
-"Expected error response, got success".to_string()
+                ClientErr::BuildRequest(e) => format!("Failed building request: {e}"),
+                ClientErr::ExecuteRequest { source, .. } => {
+                    format!("Failed executing request: {source}")
+                }
+                ClientErr::Backend { source, .. } => format!("Failed executing request: {source}"),
+                ClientErr::Middleware { source, .. } => format!("Middleware failed: {source}"),
+                ClientErr::CircuitOpen { retry_in, .. } => {
+                    format!("Circuit open, upstream considered down, retry in {retry_in:?}")
+                }
+                ClientErr::Cancelled { reason } => format!("Request cancelled: {reason}"),
+                ClientErr::ReadRespBodyText(e) => format!("Failed reading response text: {e}"),
+                ClientErr::IncompleteBody { expected, received } => {
+                    format!("Response body ended after {received} of {expected} bytes")
+                }
+                ClientErr::WriteFile { path, source } => {
+                    format!("Failed writing {}: {source}", path.display())
+                }
+                ClientErr::ResponseTooLarge {
+                    limit,
+                    read,
+                    announced,
+                } => match announced {
+                    Some(len) => {
+                        format!("Response body of {len} bytes over the {limit} bytes limit")
                     }
-                    ClientErr::ExpectedStatus {
-                        context,
-                        expected_status,
-                    } => {
-let got_status = context.got_status;
-                        format!("Expected status: {expected_status}, got: {got_status}")
-                    },
-                    ClientErr::DeserializeError {
-                        context: RespContext { response_text, .. },
-                        deserialize_error,
-                    } => format!(
-                        "Failed deserializing JSON response: {deserialize_error}, response_body: {response_text}"
+                    None => format!(
+                        "Response body over the {limit} bytes limit, stopped after {read} bytes"
                     ),
-                    ClientErr::ErrorResponse { err_body: source, .. } => {
-                        format!("Got API error response: {source}")
-                    }
-                };
+                },
+                ClientErr::ExpectedErrorResponse { .. } => {
+                    "Expected error response, got success".to_string()
+                }
+                ClientErr::ExpectedStatus {
+                    context,
+                    expected_status,
+                } => {
+                    let got_status = context.got_status;
+                    format!("Expected status: {expected_status}, got: {got_status}")
+                }
+                ClientErr::DeserializeError {
+                    context,
+                    deserialize_error,
+                } => {
+                    let response_text = context.truncated_text();
+                    format!("Failed deserializing JSON response: {deserialize_error}, response_body: {response_text}")
+                }
+                ClientErr::ErrorResponse {
+                    err_body: source, ..
+                } => {
+                    format!("Got API error response: {source}")
+                }
+                ClientErr::UnparsedErrorResponse { status, .. } => {
+                    format!("Got error response {status} not matching the expected error type")
+                }
+                ClientErr::GraphQlErrors { errors, .. } => {
+                    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+                    format!("Got GraphQL errors: {}", messages.join("; "))
+                }
+            };
             writeln!(f, "{error_msg_core}")?;
 
-            if let Some(response_text) = self.response_text() {
-                writeln!(f, "{response_text}")?;
+            if let Some(context) = self.context() {
+                writeln!(f, "{}", context.truncated_text())?;
             }
 
             Ok(())
         }
     }
 
+    /// Error of the middleware chain, before the response is read and deserialized
+    #[derive(thiserror::Error, Debug)]
+    pub enum ExecuteErr {
+        #[error("{0}")]
+        Request(reqwest::Error),
+        #[error("{0}")]
+        Backend(crate::backend::BackendErr),
+        #[error("{0}")]
+        Middleware(anyhow::Error),
+        #[error("circuit open, retry in {retry_in:?}")]
+        CircuitOpen { retry_in: Duration },
+        /// a middleware buffering the body went over the max_response_size
+        #[error("response body over {limit} bytes")]
+        ResponseTooLarge {
+            limit: u64,
+            read: u64,
+            announced: Option<u64>,
+        },
+    }
+    impl ExecuteErr {
+        pub fn kind(&self) -> ErrorKind {
+            match self {
+                ExecuteErr::Request(e) => ErrorKind::of_request(e),
+                ExecuteErr::Backend(e) => e.kind,
+                ExecuteErr::Middleware(_) => ErrorKind::Middleware,
+                ExecuteErr::CircuitOpen { .. } => ErrorKind::CircuitOpen,
+                ExecuteErr::ResponseTooLarge { .. } => ErrorKind::Protocol,
+            }
+        }
+    }
+
+    /// Coarse error category, for metrics labels and logs
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum ErrorKind {
+        Timeout,
+        Connect,
+        /// any other failure sending the request or reading the response
+        Request,
+        Middleware,
+        CircuitOpen,
+        Cancelled,
+        /// 4xx response
+        ClientError,
+        /// 5xx response
+        ServerError,
+        /// a body that isn't what the caller expected
+        Deserialize,
+        /// the server broke the exchange: truncated or oversized body, unexpected status...
+        Protocol,
+        Io,
+    }
+    impl ErrorKind {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                ErrorKind::Timeout => "timeout",
+                ErrorKind::Connect => "connect",
+                ErrorKind::Request => "request",
+                ErrorKind::Middleware => "middleware",
+                ErrorKind::CircuitOpen => "circuit_open",
+                ErrorKind::Cancelled => "cancelled",
+                ErrorKind::ClientError => "client_error",
+                ErrorKind::ServerError => "server_error",
+                ErrorKind::Deserialize => "deserialize",
+                ErrorKind::Protocol => "protocol",
+                ErrorKind::Io => "io",
+            }
+        }
+        fn of_status(status: StatusCode) -> Self {
+            match status {
+                s if s.is_client_error() => ErrorKind::ClientError,
+                s if s.is_server_error() => ErrorKind::ServerError,
+                _ => ErrorKind::Protocol,
+            }
+        }
+        fn of_request(e: &reqwest::Error) -> Self {
+            match e {
+                e if e.is_timeout() => ErrorKind::Timeout,
+                e if e.is_connect() => ErrorKind::Connect,
+                _ => ErrorKind::Request,
+            }
+        }
+    }
+
+    impl<ErrResp, F: SerialFormat> ClientErr<ErrResp, F> {
+        /// failure of the middleware chain sending `request`
+        pub fn from_execute(err: ExecuteErr, request: RequestSnapshot) -> Self {
+            let request = Box::new(request);
+            match err {
+                ExecuteErr::Request(source) => ClientErr::ExecuteRequest { source, request },
+                ExecuteErr::Backend(source) => ClientErr::Backend { source, request },
+                ExecuteErr::Middleware(source) => ClientErr::Middleware {
+                    source,
+                    request: Some(request),
+                },
+                ExecuteErr::CircuitOpen { retry_in } => {
+                    ClientErr::CircuitOpen { retry_in, request }
+                }
+                ExecuteErr::ResponseTooLarge {
+                    limit,
+                    read,
+                    announced,
+                } => ClientErr::ResponseTooLarge {
+                    limit,
+                    read,
+                    announced,
+                },
+            }
+        }
+        /// a middleware-like failure outside of sending a request
+        pub fn middleware(source: impl Into<anyhow::Error>) -> Self {
+            ClientErr::Middleware {
+                source: source.into(),
+                request: None,
+            }
+        }
+    }
+
     pub trait ResultExt<F: SerialFormat> {
         type ErrResp;
         fn try_into_err_resp(
@@ -398,7 +1556,7 @@ let got_status = context.got_status;
             expect_status: StatusCode,
         ) -> Result<Self::ErrResp, ClientErr<Self::ErrResp, F>>;
     }
-    impl<Ok, ErrResp: DeserializeOwned, F: SerialFormat> ResultExt<F> for ApiResult<Ok, ErrResp, F> {
+    impl<Ok, ErrResp: ErrorBody, F: SerialFormat> ResultExt<F> for ApiResult<Ok, ErrResp, F> {
         type ErrResp = ErrResp;
         fn try_into_err_resp(
             self,
@@ -419,20 +1577,29 @@ impl RequestBuilderExt for RequestBuilder {
     fn try_build_split(self) -> Result<RequestClient, reqwest::Error> {
         let (client, request_result) = self.build_split();
         let request = request_result?;
-        Ok(RequestClient { request, client })
+        Ok(RequestClient {
+            request,
+            client,
+            middlewares: Middlewares::new(),
+            retry: None,
+            transport: None,
+            max_response_size: None,
+            request_id_header: None,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     #![allow(non_snake_case)]
-    use crate::context::RespContext;
+    use crate::context::{RespContext, Timing};
     use crate::serialization_formats::JsonFormat;
     use crate::{prelude::*, ToRequestClient};
     use crate::{ApiClient, JsonApiClient, ReceiveJson};
     use reqwest::{Method, StatusCode};
     use serde::Deserialize;
     use serde_json::Value;
+    use std::time::{Duration, SystemTime};
 
     #[derive(Default)]
     pub struct ExampleApi {
@@ -489,12 +1656,23 @@ mod tests {
             method: Method::GET,
             url: Box::new("http://hello.com".parse()?),
             got_status: StatusCode::BAD_REQUEST,
+            headers: Default::default(),
+            version: Default::default(),
+            redirects: Box::default(),
+            body_size: None,
             response_text: format!("{{\"message\":\"{ERR_MSG}\"}}"),
+            idempotency_key: None,
+            request_id: None,
+            timing: Timing {
+                started_at: SystemTime::now(),
+                duration: Duration::ZERO,
+                attempts: 1,
+            },
         };
 
         // with inner err
         let inner_err = ClientErr::<CustomApiError, JsonFormat>::ErrorResponse {
-            context: err_context.clone(),
+            context: Box::new(err_context.clone()),
             err_body: err_context.body_from_json()?,
         };
         let parsed_err = inner_err.try_into_err_resp(StatusCode::BAD_REQUEST)?;
@@ -504,7 +1682,7 @@ mod tests {
 
         let err_result =
             ClientResult::<CustomApiError>::Err(ClientErr::<CustomApiError, _>::ErrorResponse {
-                context: err_context.clone(),
+                context: Box::new(err_context.clone()),
                 err_body: err_context.body_from_json()?,
             });
         let parsed_err = err_result.try_into_err_resp(StatusCode::BAD_REQUEST)?;
@@ -520,12 +1698,23 @@ mod tests {
             method: Method::GET,
             url: Box::new("http://hello.com".parse()?),
             got_status: StatusCode::BAD_REQUEST,
+            headers: Default::default(),
+            version: Default::default(),
+            redirects: Box::default(),
+            body_size: None,
             response_text: format!("{{\"message\":\"{ERR_MSG}\"}}"),
+            idempotency_key: None,
+            request_id: None,
+            timing: Timing {
+                started_at: SystemTime::now(),
+                duration: Duration::ZERO,
+                attempts: 1,
+            },
         };
 
         // with inner err
         let inner_err = ClientErr::<CustomApiError, JsonFormat>::ErrorResponse {
-            context: err_context.clone(),
+            context: Box::new(err_context.clone()),
             err_body: err_context.body_from_json()?,
         };
 
@@ -538,6 +1727,306 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_request_methods() -> anyhow::Result<()> {
+        let api = ExampleApi::default();
+        let put = ToRequestClient::try_into(api.put("/pet/1").json(&serde_json::json!({})))?;
+        assert_eq!(put.request.method(), Method::PUT);
+        assert_eq!(
+            put.request.url().as_str(),
+            "https://petstore.swagger.io/v2/pet/1"
+        );
+        assert_eq!(put.request.headers()["Content-Type"], "application/json");
+
+        let delete = ToRequestClient::try_into(api.delete("/pet/1"))?;
+        assert_eq!(delete.request.method(), Method::DELETE);
+        assert!(!delete.request.headers().contains_key("Content-Type"));
+        assert_eq!(delete.request.headers()["Accept"], "application/json");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_override() -> anyhow::Result<()> {
+        use reqwest::header::{ACCEPT, CONTENT_TYPE};
+        let api = ExampleApi::default();
+        let export = ToRequestClient::try_into(api.get("/export").accept("text/csv"))?;
+        let accept: Vec<_> = export.request.headers().get_all(ACCEPT).iter().collect();
+        assert_eq!(accept, ["text/csv"]);
+
+        let import = api.post("/import").content_type("text/csv").body("id\n1\n");
+        let import = ToRequestClient::try_into(import)?;
+        let content_type: Vec<_> = import
+            .request
+            .headers()
+            .get_all(CONTENT_TYPE)
+            .iter()
+            .collect();
+        assert_eq!(content_type, ["text/csv"]);
+        assert_eq!(import.request.headers()[ACCEPT], "application/json");
+
+        assert!(ToRequestClient::try_into(api.get("/export").accept("text/csv\n")).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recv_text() -> anyhow::Result<()> {
+        use crate::middleware::tests::StubResponse;
+        let request = |status, body| {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get("http://localhost/export.csv"),
+                vec![],
+            )
+            .with_middleware(StubResponse::new(status, body))
+        };
+
+        let csv = request(200, "id,name\n1,rex\n")
+            .recv_text::<CustomApiError>()
+            .await?;
+        assert_eq!(csv, "id,name\n1,rex\n");
+
+        let err = request(404, r#"{"message":"no export"}"#)
+            .recv_bytes::<CustomApiError>()
+            .await
+            .try_into_err_resp(StatusCode::NOT_FOUND)?;
+        assert_eq!(err.message, "no export");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unparsed_error_response() -> anyhow::Result<()> {
+        use crate::middleware::tests::StubResponse;
+        let err =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+                .with_middleware(StubResponse::new(502, "<html>Bad Gateway</html>"))
+                .recv_json::<Value, CustomApiError>()
+                .await
+                .err()
+                .unwrap();
+        let ClientErr::UnparsedErrorResponse { status, context } = err else {
+            anyhow::bail!("expected UnparsedErrorResponse, got {err:?}");
+        };
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(context.response_text, "<html>Bad Gateway</html>");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_context_keeps_response_headers() -> anyhow::Result<()> {
+        use crate::middleware::response_from_parts;
+        use futures::future::BoxFuture;
+
+        struct RateLimited(StatusCode);
+        impl ApiMiddleware for RateLimited {
+            fn handle<'a>(
+                &'a self,
+                _request: reqwest::Request,
+                _next: Next<'a>,
+            ) -> BoxFuture<'a, Result<reqwest::Response, ExecuteErr>> {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert("x-ratelimit-remaining", 0.into());
+                let response = response_from_parts(self.0, headers, r#"{"message":"slow down"}"#);
+                Box::pin(async move { Ok(response) })
+            }
+        }
+        let request = |status| {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+                .with_middleware(RateLimited(status))
+        };
+
+        let ok = ReceiveResp::<JsonFormat>::partial_expect::<serde_json::Value, CustomApiError>(
+            request(StatusCode::OK),
+        )
+        .await?;
+        assert_eq!(ok.context.header("x-ratelimit-remaining"), Some("0"));
+
+        let err = request(StatusCode::TOO_MANY_REQUESTS)
+            .recv_json::<serde_json::Value, CustomApiError>()
+            .await
+            .unwrap_err();
+        let context = err.context().expect("error response has a context");
+        assert_eq!(context.header("x-ratelimit-remaining"), Some("0"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expect_status() -> anyhow::Result<()> {
+        use crate::middleware::tests::StubResponse;
+        let request = |status, body| {
+            ApiRequestBuilder::new(reqwest::Client::new().post("http://localhost/pets"), vec![])
+                .with_middleware(StubResponse::new(status, body))
+        };
+
+        let created: Value = request(201, r#"{"id":1}"#)
+            .expect_status::<_, CustomApiError>(StatusCode::CREATED)
+            .await?;
+        assert_eq!(created["id"], 1);
+
+        let not_created = request(200, r#"{"id":1}"#)
+            .expect_status::<Value, CustomApiError>(StatusCode::CREATED)
+            .await;
+        let Err(ClientErr::ExpectedStatus {
+            context,
+            expected_status,
+        }) = not_created
+        else {
+            anyhow::bail!("expected ExpectedStatus, got {not_created:?}");
+        };
+        assert_eq!(
+            (context.got_status, expected_status),
+            (StatusCode::OK, StatusCode::CREATED)
+        );
+
+        let conflict = request(409, r#"{"message":"taken"}"#)
+            .expect_status::<Value, CustomApiError>(StatusCode::CONFLICT)
+            .await
+            .try_into_err_resp(StatusCode::CONFLICT)?;
+        assert_eq!(conflict.message, "taken");
+        let wrong_error = request(500, r#"{"message":"boom"}"#)
+            .expect_status::<Value, CustomApiError>(StatusCode::CONFLICT)
+            .await;
+        assert!(matches!(wrong_error, Err(ClientErr::ExpectedStatus { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_error_kind() -> anyhow::Result<()> {
+        use crate::middleware::tests::StubResponse;
+        let request = |status, body| {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+                .with_middleware(StubResponse::new(status, body))
+                .recv_json::<Value, CustomApiError>()
+        };
+
+        let not_found = request(404, r#"{"message":"no such pet"}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(not_found.kind(), ErrorKind::ClientError);
+        assert!(!not_found.is_retryable());
+        let throttled = request(429, r#"{"message":"slow down"}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(throttled.kind(), ErrorKind::ClientError);
+        assert!(throttled.is_retryable());
+        let unavailable = request(503, "<html>maintenance</html>").await.unwrap_err();
+        assert!(matches!(
+            unavailable,
+            ClientErr::UnparsedErrorResponse { .. }
+        ));
+        assert_eq!(unavailable.kind(), ErrorKind::ServerError);
+        assert!(unavailable.is_retryable());
+        let garbled = request(200, "{").await.unwrap_err();
+        assert_eq!(garbled.kind(), ErrorKind::Deserialize);
+        assert!(!garbled.is_retryable());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() -> anyhow::Result<()> {
+        use crate::middleware::response_from_parts;
+        use futures::future::BoxFuture;
+
+        /// a body of 100 chunks of 1KiB, without Content-Length
+        struct EndlessExport;
+        impl ApiMiddleware for EndlessExport {
+            fn handle<'a>(
+                &'a self,
+                _request: reqwest::Request,
+                _next: Next<'a>,
+            ) -> BoxFuture<'a, Result<reqwest::Response, ExecuteErr>> {
+                let chunks = (0..100).map(|_| Ok::<_, std::io::Error>(vec![b'x'; 1024]));
+                let body = reqwest::Body::wrap_stream(futures::stream::iter(chunks));
+                let response = response_from_parts(StatusCode::OK, Default::default(), body);
+                Box::pin(async move { Ok(response) })
+            }
+        }
+        let request = || {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get("http://localhost/export"),
+                vec![],
+            )
+            .with_config(ClientConfig::default().max_response_size(4096))
+        };
+
+        let streamed = request()
+            .with_middleware(EndlessExport)
+            .recv_text::<Value>()
+            .await;
+        let Err(ClientErr::ResponseTooLarge { limit, read, .. }) = streamed else {
+            anyhow::bail!("expected ResponseTooLarge, got {streamed:?}");
+        };
+        assert_eq!(limit, 4096);
+        assert_eq!(read, 5 * 1024, "stops at the first chunk over the limit");
+        let bytes = request()
+            .with_middleware(EndlessExport)
+            .recv_bytes::<Value>()
+            .await;
+        assert!(
+            matches!(bytes, Err(ClientErr::ResponseTooLarge { read, .. }) if read == 5 * 1024),
+            "{bytes:?}"
+        );
+
+        let announced = request()
+            .with_middleware(crate::middleware::tests::StubResponse::new(
+                200,
+                &"y".repeat(5000),
+            ))
+            .recv_text::<Value>()
+            .await;
+        assert!(matches!(
+            announced,
+            Err(ClientErr::ResponseTooLarge {
+                read: 0,
+                announced: Some(5000),
+                ..
+            })
+        ));
+
+        let allowed = request()
+            .max_response_size(200 * 1024)
+            .with_middleware(EndlessExport)
+            .recv_text::<Value>()
+            .await?;
+        assert_eq!(allowed.len(), 100 * 1024);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recv_form() -> anyhow::Result<()> {
+        use crate::middleware::tests::StubResponse;
+
+        #[derive(Deserialize)]
+        struct LegacyToken {
+            access_token: String,
+            token_type: String,
+        }
+        #[derive(thiserror::Error, Deserialize, Debug)]
+        #[error("{error}")]
+        struct LegacyErr {
+            error: String,
+        }
+        let request = |status, body| {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().post("http://localhost/login/oauth/access_token"),
+                vec![],
+            )
+            .form(&[("code", "abc")])
+            .with_middleware(StubResponse::new(status, body))
+        };
+
+        let token: LegacyToken = request(200, "access_token=e72e16c7&token_type=bearer")
+            .recv_form::<_, LegacyErr>()
+            .await?;
+        assert_eq!(token.access_token, "e72e16c7");
+        assert_eq!(token.token_type, "bearer");
+
+        let err = request(400, "error=bad_verification_code")
+            .recv_form::<LegacyToken, LegacyErr>()
+            .await
+            .try_into_err_resp(StatusCode::BAD_REQUEST)?;
+        assert_eq!(err.error, "bad_verification_code");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_api__request_client() -> anyhow::Result<()> {
         let req_builder = ExampleApi::default()
@@ -0,0 +1,229 @@
+use crate::context::RespContext;
+use crate::error::aliases::JsonClientResult;
+use crate::pagination::CursorPagination;
+use crate::status_errors::ErrorBody;
+use crate::ApiRequestBuilder;
+use futures::stream::Stream;
+use reqwest::header::{HeaderMap, LINK};
+use serde::de::DeserializeOwned;
+
+/// One entry of a `Link` header: `<https://api/items?page=3>; rel="next"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// as sent, possibly relative to the request url
+    pub target: String,
+    /// a link can have several: `rel="next last"`
+    pub rels: Vec<String>,
+    /// the other params, e.g. `title`, `type`
+    pub params: Vec<(String, String)>,
+}
+
+/// The links of a response, RFC 8288 (formerly 5988), as GitHub, GitLab & co paginate with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Links(pub Vec<Link>);
+impl Links {
+    /// Entries that don't parse are skipped, the others kept
+    pub fn parse(header: &str) -> Self {
+        let mut links = vec![];
+        let mut rest = header;
+        while let Some(start) = rest.find('<') {
+            let Some(len) = rest[start..].find('>') else {
+                break;
+            };
+            let target = rest[start + 1..start + len].trim().to_string();
+            rest = &rest[start + len + 1..];
+            let (params, after) = split_params(rest);
+            rest = after;
+
+            let mut link = Link {
+                target,
+                rels: vec![],
+                params: vec![],
+            };
+            for (name, value) in params {
+                match name.as_str() {
+                    "rel" => link
+                        .rels
+                        .extend(value.split_whitespace().map(str::to_ascii_lowercase)),
+                    _ => link.params.push((name, value)),
+                }
+            }
+            links.push(link);
+        }
+        Links(links)
+    }
+    /// every `Link` header of a response, an API may send one per relation
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let links = headers
+            .get_all(LINK)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|header| Self::parse(header).0);
+        Links(links.collect())
+    }
+
+    /// target of the first link with relation `rel`, matched case-insensitively
+    pub fn rel(&self, rel: &str) -> Option<&str> {
+        let rel = rel.to_ascii_lowercase();
+        let link = self.0.iter().find(|link| link.rels.contains(&rel))?;
+        Some(&link.target)
+    }
+    pub fn next(&self) -> Option<&str> {
+        self.rel("next")
+    }
+    pub fn prev(&self) -> Option<&str> {
+        self.rel("prev").or_else(|| self.rel("previous"))
+    }
+    pub fn first(&self) -> Option<&str> {
+        self.rel("first")
+    }
+    pub fn last(&self) -> Option<&str> {
+        self.rel("last")
+    }
+}
+
+/// `; name="value"; name=value` up to the comma ending the link, quoted values can hold `,` and `;`
+fn split_params(input: &str) -> (Vec<(String, String)>, &str) {
+    let mut end = input.len();
+    let mut in_quotes = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    let mut params = vec![];
+    let mut in_quotes = false;
+    let mut param_start = 0;
+    let raw = &input[..end];
+    let mut raw_params = vec![];
+    for (i, c) in raw.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                raw_params.push(&raw[param_start..i]);
+                param_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    raw_params.push(&raw[param_start..]);
+    for param in raw_params {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        params.push((name.trim().to_ascii_lowercase(), value.to_string()));
+    }
+    (params, &input[end..])
+}
+
+impl RespContext {
+    pub fn links(&self) -> Links {
+        Links::from_headers(&self.headers)
+    }
+}
+
+impl ApiRequestBuilder {
+    /// Fetch every page of a Link-paginated API (GitHub, GitLab...), yielding their items.
+    /// Each page's body is a JSON array of items, the next page is the `rel="next"` link of its
+    /// response: `recv_json_cursor` with `CursorPagination::link_header("")`.
+    pub fn recv_json_linked<Item, ErrResp>(
+        self,
+    ) -> impl Stream<Item = JsonClientResult<Item, ErrResp>>
+    where
+        Item: DeserializeOwned,
+        ErrResp: ErrorBody,
+    {
+        self.recv_json_cursor(CursorPagination::link_header(""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use futures::TryStreamExt;
+    use reqwest::{Request, Response};
+
+    /// GitHub-like: `?page=n` of `1..=total` three by three, with next/last links
+    struct ReposApi {
+        total: usize,
+    }
+    impl ApiMiddleware for ReposApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let page: usize = (request.url().query_pairs())
+                .find(|(k, _)| k == "page")
+                .and_then(|(_, v)| v.parse().ok())
+                .unwrap_or(1);
+            let items: Vec<usize> = ((page - 1) * 3 + 1..=page * 3)
+                .filter(|n| *n <= self.total)
+                .collect();
+            let last = self.total.div_ceil(3);
+            let mut links = vec![format!(r#"</repos?page={last}>; rel="last""#)];
+            if page < last {
+                links.insert(0, format!(r#"</repos?page={}>; rel="next""#, page + 1));
+            }
+            let link = links.join(", ");
+            Box::pin(async move {
+                let mut response = stub_response(200, &serde_json::to_string(&items).unwrap())?;
+                response.headers_mut().insert(LINK, link.parse().unwrap());
+                Ok(response)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recv_json_linked() -> anyhow::Result<()> {
+        let repos: Vec<usize> =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/repos"), vec![])
+                .with_middleware(ReposApi { total: 7 })
+                .recv_json_linked::<usize, serde_json::Value>()
+                .try_collect()
+                .await?;
+        assert_eq!(repos, (1..=7).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_links() {
+        let github = r#"<https://api.github.com/repos?page=3&per_page=2>; rel="next", <https://api.github.com/repos?page=50>; rel="last", <https://api.github.com/repos?page=1>; rel="first""#;
+        let links = Links::parse(github);
+        assert_eq!(
+            links.next(),
+            Some("https://api.github.com/repos?page=3&per_page=2")
+        );
+        assert_eq!(links.last(), Some("https://api.github.com/repos?page=50"));
+        assert_eq!(links.first(), Some("https://api.github.com/repos?page=1"));
+        assert_eq!(links.prev(), None);
+
+        let tricky =
+            r#"</items?a=1,2>; rel="prev next"; title="page; two, of three", </about>;rel=about"#;
+        let links = Links::parse(tricky);
+        assert_eq!(links.0.len(), 2);
+        assert_eq!(links.next(), Some("/items?a=1,2"));
+        assert_eq!(links.prev(), Some("/items?a=1,2"));
+        assert_eq!(
+            links.0[0].params,
+            vec![("title".to_string(), "page; two, of three".to_string())]
+        );
+        assert_eq!(links.rel("ABOUT"), Some("/about"));
+
+        assert_eq!(Links::parse("not a link"), Links::default());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next, ResponseParts};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::{Request, Response};
+use serde_json::Value;
+use std::sync::Arc;
+
+pub(crate) const REDACTED: &str = "[REDACTED]";
+/// never logged as is
+pub(crate) const SECRET_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+pub type LogSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Opt-in middleware printing every exchange (method, url, headers, body),
+/// with secret headers and JSON fields redacted before anything is logged.
+#[derive(Clone)]
+pub struct DebugLogger {
+    pub redact_headers: Vec<HeaderName>,
+    /// JSON object keys, matched case-insensitively at any depth
+    pub redact_fields: Vec<String>,
+    /// bodies are cut after this many bytes
+    pub max_body: usize,
+    pub sink: LogSink,
+}
+impl Default for DebugLogger {
+    fn default() -> Self {
+        Self {
+            redact_headers: SECRET_HEADERS
+                .into_iter()
+                .map(HeaderName::from_static)
+                .collect(),
+            redact_fields: [
+                "password",
+                "token",
+                "access_token",
+                "refresh_token",
+                "client_secret",
+                "secret",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            max_body: 4096,
+            sink: Arc::new(|line| eprintln!("{line}")),
+        }
+    }
+}
+impl DebugLogger {
+    pub fn redact_header(mut self, name: HeaderName) -> Self {
+        self.redact_headers.push(name);
+        self
+    }
+    pub fn redact_field(mut self, name: &str) -> Self {
+        self.redact_fields.push(name.to_string());
+        self
+    }
+    pub fn max_body(self, max_body: usize) -> Self {
+        Self { max_body, ..self }
+    }
+    pub fn sink(self, sink: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            ..self
+        }
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> String {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = match self.redact_headers.contains(name) {
+                    true => REDACTED.into(),
+                    false => String::from_utf8_lossy(value.as_bytes()),
+                };
+                format!("\n  {name}: {value}")
+            })
+            .collect()
+    }
+
+    fn body(&self, body: &[u8]) -> String {
+        let text = match serde_json::from_slice::<Value>(body) {
+            Ok(mut json) => {
+                self.redact_json(&mut json);
+                json.to_string()
+            }
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        };
+        truncate(text, self.max_body, body.len())
+    }
+
+    fn redact_json(&self, json: &mut Value) {
+        match json {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match self
+                        .redact_fields
+                        .iter()
+                        .any(|f| f.eq_ignore_ascii_case(key))
+                    {
+                        true => *value = Value::String(REDACTED.to_string()),
+                        false => self.redact_json(value),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_json(item)),
+            _ => {}
+        }
+    }
+}
+
+/// `text` cut after `max_chars`, saying how long the whole body (`len` bytes) was
+fn truncate(text: String, max_chars: usize, len: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}... ({len} bytes)", &text[..cut]),
+        None => text,
+    }
+}
+
+impl ApiMiddleware for DebugLogger {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            let body = match request.body() {
+                Some(body) => match body.as_bytes() {
+                    Some(bytes) => self.body(bytes),
+                    None => "<stream>".to_string(),
+                },
+                None => String::new(),
+            };
+            (self.sink)(&format!(
+                "--> {} {}{}\n{body}",
+                request.method(),
+                request.url(),
+                self.headers(request.headers())
+            ));
+
+            let mut response = match next.run(request).await {
+                Ok(response) => response,
+                Err(err) => {
+                    (self.sink)(&format!("<-- failed: {err}"));
+                    return Err(err);
+                }
+            };
+
+            // the body is read to be logged, then handed on in a rebuilt response
+            let parts = ResponseParts::take(&mut response);
+            let bytes = response.bytes().await.map_err(ExecuteErr::Request)?;
+            (self.sink)(&format!(
+                "<-- {}{}\n{}",
+                parts.status,
+                self.headers(&parts.headers),
+                self.body(&bytes)
+            ));
+
+            Ok(parts.with_body(bytes))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BodySize;
+    use crate::middleware::tests::StubResponse;
+    use crate::prelude::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_secrets_are_redacted() -> anyhow::Result<()> {
+        let lines = Arc::new(Mutex::new(Vec::<String>::new()));
+        let logger = {
+            let lines = lines.clone();
+            DebugLogger::default().sink(move |line| lines.lock().unwrap().push(line.to_string()))
+        };
+
+        let got: serde_json::Value = ApiRequestBuilder::new(
+            reqwest::Client::new().post("http://localhost/login"),
+            vec![],
+        )
+        .header("x-api-key", "key-123")
+        .json(&serde_json::json!({ "user": "bob", "Password": "hunter2" }))
+        .with_middleware(logger)
+        .with_middleware(StubResponse::new(200, r#"{"session":{"token":"tok-456"}}"#))
+        .recv_json::<_, serde_json::Value>()
+        .await?;
+        assert_eq!(
+            got["session"]["token"], "tok-456",
+            "only the log is redacted"
+        );
+
+        let log = lines.lock().unwrap().join("\n");
+        assert!(log.contains("x-api-key: [REDACTED]"));
+        assert!(log.contains(r#""user":"bob""#));
+        for secret in ["key-123", "hunter2", "tok-456"] {
+            assert!(!log.contains(secret), "{secret} leaked in:\n{log}");
+        }
+        Ok(())
+    }
+
+    /// records a BodySize, as Decompression does
+    struct Measure;
+    impl ApiMiddleware for Measure {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            Box::pin(async move {
+                let mut response = next.run(request).await?;
+                let size = BodySize {
+                    wire: 1,
+                    decoded: 2,
+                };
+                response.extensions_mut().insert(size);
+                Ok(response)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extensions_kept() -> anyhow::Result<()> {
+        let request =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pet"), vec![])
+                .with_middleware(DebugLogger::default().sink(|_| {}))
+                .with_middleware(Measure)
+                .with_middleware(StubResponse::new(200, "{}"));
+        let resp =
+            ReceiveResp::<JsonFormat>::partial_expect::<serde_json::Value, serde_json::Value>(
+                request,
+            )
+            .await?;
+        assert_eq!(resp.context.body_size.map(|size| size.decoded), Some(2));
+        Ok(())
+    }
+}
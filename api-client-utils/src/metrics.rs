@@ -0,0 +1,94 @@
+use crate::error::{ErrorKind, ExecuteErr};
+use crate::middleware::{ApiMiddleware, Next};
+use futures::future::BoxFuture;
+use reqwest::{Method, Request, Response, StatusCode, Url};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What metrics are labelled with, captured before the request is sent
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub method: Method,
+    pub url: Url,
+}
+
+/// Callbacks to feed counters/histograms (Prometheus, StatsD...) for every request of a client.
+/// Register with `Metrics(my_metrics)` in `ApiClient::middlewares()`.
+pub trait ClientMetrics: Send + Sync {
+    fn on_request_start(&self, _request: &RequestInfo) {}
+    /// any response, error statuses included
+    fn on_response(&self, _request: &RequestInfo, _status: StatusCode, _duration: Duration) {}
+    /// no response at all: connection failures, timeouts, middleware errors
+    fn on_error(&self, _request: &RequestInfo, _kind: ErrorKind, _duration: Duration) {}
+}
+
+impl<M: ClientMetrics + ?Sized> ClientMetrics for Arc<M> {
+    fn on_request_start(&self, request: &RequestInfo) {
+        self.as_ref().on_request_start(request)
+    }
+    fn on_response(&self, request: &RequestInfo, status: StatusCode, duration: Duration) {
+        self.as_ref().on_response(request, status, duration)
+    }
+    fn on_error(&self, request: &RequestInfo, kind: ErrorKind, duration: Duration) {
+        self.as_ref().on_error(request, kind, duration)
+    }
+}
+
+/// Middleware reporting to a ClientMetrics, register it first to measure the whole chain (retries included)
+pub struct Metrics<M: ClientMetrics>(pub M);
+impl<M: ClientMetrics> ApiMiddleware for Metrics<M> {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            let info = RequestInfo {
+                method: request.method().clone(),
+                url: request.url().clone(),
+            };
+            self.0.on_request_start(&info);
+            let start = Instant::now();
+            let result = next.run(request).await;
+            match &result {
+                Ok(response) => self
+                    .0
+                    .on_response(&info, response.status(), start.elapsed()),
+                Err(err) => self.0.on_error(&info, err.kind(), start.elapsed()),
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::StubResponse;
+    use crate::prelude::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct StatusCounts(Mutex<Vec<(String, u16)>>);
+    impl ClientMetrics for StatusCounts {
+        fn on_response(&self, request: &RequestInfo, status: StatusCode, _duration: Duration) {
+            let label = format!("{} {}", request.method, request.url.path());
+            self.0.lock().unwrap().push((label, status.as_u16()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_callbacks() -> anyhow::Result<()> {
+        let counts = Arc::new(StatusCounts::default());
+        let _ = ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+            .with_middleware(Metrics(counts.clone()))
+            .with_middleware(StubResponse::new(503, "null"))
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await;
+        assert_eq!(
+            *counts.0.lock().unwrap(),
+            vec![("GET /pets".to_string(), 503)]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use crate::error::ExecuteErr;
+use crate::transport::HttpTransport;
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, ACCEPT, AUTHORIZATION};
+use reqwest::{Request, Response, StatusCode};
+use std::sync::Arc;
+
+pub type Middlewares = Vec<Arc<dyn ApiMiddleware>>;
+
+/// A layer every request/response of an ApiClient passes through, in registration order.
+///
+/// Simple middlewares only implement `on_request` / `on_response`,
+/// middlewares that need to wrap execution (retry, caching, short-circuit) override `handle`.
+pub trait ApiMiddleware: Send + Sync {
+    fn on_request(&self, _request: &mut Request) -> Result<(), ExecuteErr> {
+        Ok(())
+    }
+    fn on_response(&self, _response: &mut Response) -> Result<(), ExecuteErr> {
+        Ok(())
+    }
+
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            self.on_request(&mut request)?;
+            let mut response = next.run(request).await?;
+            self.on_response(&mut response)?;
+            Ok(response)
+        })
+    }
+}
+
+// lets shared middlewares (circuit breakers, caches) be registered while the client keeps a handle
+impl<M: ApiMiddleware + ?Sized> ApiMiddleware for Arc<M> {
+    fn on_request(&self, request: &mut Request) -> Result<(), ExecuteErr> {
+        self.as_ref().on_request(request)
+    }
+    fn on_response(&self, response: &mut Response) -> Result<(), ExecuteErr> {
+        self.as_ref().on_response(response)
+    }
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        self.as_ref().handle(request, next)
+    }
+}
+
+/// Rebuild a response from its parts, for middlewares that read the body or serve it from elsewhere
+pub fn response_from_parts(
+    status: StatusCode,
+    headers: HeaderMap,
+    body: impl Into<reqwest::Body>,
+) -> Response {
+    let mut response = http::Response::new(body.into());
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Response::from(response)
+}
+
+/// Identifies the responses a GET may share: same url, Accept and Authorization
+pub(crate) fn cache_key(request: &Request) -> String {
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+            .unwrap_or_default()
+    };
+    format!(
+        "{}\n{}\n{}",
+        request.url(),
+        header(ACCEPT),
+        header(AUTHORIZATION)
+    )
+}
+
+/// What a middleware reading the body (to log, cache or transform it) needs to hand the response
+/// on: besides status and headers, the http version and the extensions other middlewares
+/// recorded, e.g. the redirects and body_size of RespContext
+pub struct ResponseParts {
+    pub status: StatusCode,
+    pub version: http::Version,
+    pub headers: HeaderMap,
+    pub extensions: http::Extensions,
+}
+impl ResponseParts {
+    /// leaves `response` with only its body, to be read
+    pub fn take(response: &mut Response) -> Self {
+        Self {
+            status: response.status(),
+            version: response.version(),
+            headers: std::mem::take(response.headers_mut()),
+            extensions: std::mem::take(response.extensions_mut()),
+        }
+    }
+    pub fn with_body(self, body: impl Into<reqwest::Body>) -> Response {
+        let mut response = http::Response::new(body.into());
+        *response.status_mut() = self.status;
+        *response.version_mut() = self.version;
+        *response.headers_mut() = self.headers;
+        *response.extensions_mut() = self.extensions;
+        Response::from(response)
+    }
+}
+
+/// The rest of the middleware chain, ending with the actual http call
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    transport: &'a dyn HttpTransport,
+    middlewares: &'a [Arc<dyn ApiMiddleware>],
+    max_response_size: Option<u64>,
+}
+impl<'a> Next<'a> {
+    pub fn new(
+        transport: &'a dyn HttpTransport,
+        middlewares: &'a [Arc<dyn ApiMiddleware>],
+    ) -> Self {
+        Self {
+            transport,
+            middlewares,
+            max_response_size: None,
+        }
+    }
+    pub fn with_max_response_size(self, max_response_size: Option<u64>) -> Self {
+        Self {
+            max_response_size,
+            ..self
+        }
+    }
+    pub fn transport(&self) -> &'a dyn HttpTransport {
+        self.transport
+    }
+    /// the request's max_response_size, for middlewares that buffer the body
+    pub fn max_response_size(&self) -> Option<u64> {
+        self.max_response_size
+    }
+
+    pub fn run(self, request: Request) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => first.handle(
+                request,
+                Next {
+                    middlewares: rest,
+                    ..self
+                },
+            ),
+            None => self.transport.execute(request),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::RequestClient;
+    use reqwest::header::HeaderValue;
+    use std::sync::Mutex;
+
+    pub(crate) fn stub_response(status: u16, body: &str) -> Result<Response, ExecuteErr> {
+        let response = http::Response::builder()
+            .status(status)
+            .body(body.to_string())
+            .map_err(|e| ExecuteErr::Middleware(e.into()))?;
+        Ok(Response::from(response))
+    }
+
+    /// Ends the chain with a canned response instead of calling the network
+    pub(crate) struct StubResponse {
+        pub status: u16,
+        pub body: String,
+    }
+    impl StubResponse {
+        pub fn new(status: u16, body: &str) -> Self {
+            Self {
+                status,
+                body: body.to_string(),
+            }
+        }
+    }
+    impl ApiMiddleware for StubResponse {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            Box::pin(async move { stub_response(self.status, &self.body) })
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordOrder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+    impl ApiMiddleware for RecordOrder {
+        fn on_request(&self, request: &mut Request) -> Result<(), ExecuteErr> {
+            let name = self.name;
+            self.log.lock().unwrap().push(format!("req {name}"));
+            request
+                .headers_mut()
+                .append("x-seen-by", HeaderValue::from_static(name));
+            Ok(())
+        }
+        fn on_response(&self, _response: &mut Response) -> Result<(), ExecuteErr> {
+            self.log.lock().unwrap().push(format!("resp {}", self.name));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middlewares_run_in_order() -> anyhow::Result<()> {
+        let log = Arc::new(Mutex::new(vec![]));
+        let client = reqwest::Client::new();
+        let request = RequestClient {
+            request: client.get("http://localhost/some/path").build()?,
+            client,
+            retry: None,
+            transport: None,
+            max_response_size: None,
+            request_id_header: None,
+            middlewares: vec![
+                Arc::new(RecordOrder {
+                    name: "first",
+                    log: log.clone(),
+                }),
+                Arc::new(RecordOrder {
+                    name: "second",
+                    log: log.clone(),
+                }),
+                Arc::new(StubResponse::new(200, r#"{"ok":true}"#)),
+            ],
+        };
+
+        let got: serde_json::Value = request.recv_json::<_, serde_json::Value>().await?;
+        assert_eq!(got["ok"], true);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["req first", "req second", "resp second", "resp first"]
+        );
+        Ok(())
+    }
+}
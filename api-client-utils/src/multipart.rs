@@ -0,0 +1,226 @@
+use crate::{ApiRequestBuilder, RequestClient, ToRequestClient};
+use reqwest::multipart::{Form, Part};
+use std::path::Path;
+
+/// One field of a multipart/form-data body
+#[derive(Debug, Clone)]
+pub enum MultipartPart {
+    Text {
+        name: String,
+        value: String,
+    },
+    /// file contents, or any binary blob
+    Bytes {
+        name: String,
+        bytes: Vec<u8>,
+        file_name: Option<String>,
+        mime: Option<String>,
+    },
+}
+impl MultipartPart {
+    pub fn text(name: &str, value: impl Into<String>) -> Self {
+        Self::Text {
+            name: name.to_string(),
+            value: value.into(),
+        }
+    }
+    pub fn bytes(name: &str, bytes: impl Into<Vec<u8>>) -> Self {
+        Self::Bytes {
+            name: name.to_string(),
+            bytes: bytes.into(),
+            file_name: None,
+            mime: None,
+        }
+    }
+    /// reads the file, using its name as file name and its extension to pick the mime type
+    pub fn file(name: &str, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let part = Self::bytes(name, std::fs::read(path)?);
+        let part = match path.file_name() {
+            Some(file_name) => part.file_name(&file_name.to_string_lossy()),
+            None => part,
+        };
+        Ok(match mime_from_extension(path) {
+            Some(mime) => part.mime(mime),
+            None => part,
+        })
+    }
+    /// no effect on text parts
+    pub fn file_name(self, file_name: &str) -> Self {
+        match self {
+            Self::Bytes {
+                name, bytes, mime, ..
+            } => Self::Bytes {
+                name,
+                bytes,
+                file_name: Some(file_name.to_string()),
+                mime,
+            },
+            text => text,
+        }
+    }
+    /// no effect on text parts
+    pub fn mime(self, mime: &str) -> Self {
+        match self {
+            Self::Bytes {
+                name,
+                bytes,
+                file_name,
+                ..
+            } => Self::Bytes {
+                name,
+                bytes,
+                file_name,
+                mime: Some(mime.to_string()),
+            },
+            text => text,
+        }
+    }
+
+    fn into_named_part(self) -> Result<(String, Part), reqwest::Error> {
+        match self {
+            Self::Text { name, value } => Ok((name, Part::text(value))),
+            Self::Bytes {
+                name,
+                bytes,
+                file_name,
+                mime,
+            } => {
+                let mut part = Part::bytes(bytes);
+                if let Some(file_name) = file_name {
+                    part = part.file_name(file_name);
+                }
+                if let Some(mime) = mime {
+                    part = part.mime_str(&mime)?;
+                }
+                Ok((name, part))
+            }
+        }
+    }
+}
+
+pub(crate) fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "html" => "text/html",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    })
+}
+
+/// Request with a multipart/form-data body, the boundary and Content-Type are set when the request is built
+pub struct MultipartRequestBuilder {
+    pub request: ApiRequestBuilder,
+    pub parts: Vec<MultipartPart>,
+}
+impl MultipartRequestBuilder {
+    pub fn new(request: ApiRequestBuilder) -> Self {
+        Self {
+            request,
+            parts: vec![],
+        }
+    }
+    pub fn part(mut self, part: MultipartPart) -> Self {
+        self.parts.push(part);
+        self
+    }
+    pub fn text(self, name: &str, value: impl Into<String>) -> Self {
+        self.part(MultipartPart::text(name, value))
+    }
+    /// set headers, middlewares etc. on the underlying request
+    pub fn map_request(self, f: impl FnOnce(ApiRequestBuilder) -> ApiRequestBuilder) -> Self {
+        Self {
+            request: f(self.request),
+            ..self
+        }
+    }
+}
+impl ToRequestClient for MultipartRequestBuilder {
+    fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+        let mut form = Form::new();
+        for part in self.parts {
+            let (name, part) = part.into_named_part()?;
+            form = form.part(name, part);
+        }
+        ToRequestClient::try_into(self.request.map(|b| b.multipart(form)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::ReceiveJson;
+    use futures::future::BoxFuture;
+    use reqwest::header::CONTENT_TYPE;
+    use reqwest::{Request, Response};
+
+    /// answers with the request's content-type and body
+    struct EchoMultipart;
+    impl ApiMiddleware for EchoMultipart {
+        fn handle<'a>(
+            &'a self,
+            mut request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            Box::pin(async move {
+                let content_type = request.headers()[CONTENT_TYPE]
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                let body = request.body_mut().take().unwrap();
+                let body = read_body(body).await.map_err(ExecuteErr::Request)?;
+                let echo = serde_json::json!({
+                    "content_type": content_type,
+                    "body": String::from_utf8_lossy(&body),
+                });
+                stub_response(200, &echo.to_string())
+            })
+        }
+    }
+    /// reqwest only exposes multipart (streamed) bodies through a Response
+    async fn read_body(body: reqwest::Body) -> reqwest::Result<bytes::Bytes> {
+        Response::from(http::Response::new(body)).bytes().await
+    }
+
+    #[tokio::test]
+    async fn test_multipart_body() -> anyhow::Result<()> {
+        let echo: serde_json::Value = MultipartRequestBuilder::new(
+            ApiRequestBuilder::new(
+                reqwest::Client::new().post("http://localhost/upload"),
+                vec![],
+            )
+            .with_middleware(EchoMultipart),
+        )
+        .text("title", "report")
+        .part(
+            MultipartPart::bytes("attachment", "a,b\n1,2")
+                .file_name("report.csv")
+                .mime("text/csv"),
+        )
+        .recv_json::<_, serde_json::Value>()
+        .await?;
+
+        let content_type = echo["content_type"].as_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = echo["body"].as_str().unwrap();
+        assert!(body.contains(&format!("--{boundary}")));
+        assert!(body.contains("Content-Disposition: form-data; name=\"title\"\r\n\r\nreport"));
+        assert!(body.contains("name=\"attachment\"; filename=\"report.csv\"\r\nContent-Type: text/csv\r\n\r\na,b\n1,2"));
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::context::SentRequest;
+use crate::error::ClientErr;
+use crate::serialization_formats::JsonFormat;
+use crate::status_errors::ErrorBody;
+use crate::ToRequestClient;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use reqwest::header::{HeaderValue, ACCEPT};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+
+/// Splits a body fed chunk by chunk into lines, a line can span several chunks
+#[derive(Default)]
+struct LineSplitter {
+    buffer: Vec<u8>,
+}
+impl LineSplitter {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = vec![];
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            lines.extend(Self::non_blank(&line));
+        }
+        lines
+    }
+    /// the last line, when the body doesn't end with a newline
+    fn finish(&mut self) -> Option<String> {
+        Self::non_blank(&std::mem::take(&mut self.buffer))
+    }
+    fn non_blank(line: &[u8]) -> Option<String> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        (!line.is_empty()).then(|| line.to_string())
+    }
+}
+
+struct NdjsonBody {
+    sent: SentRequest,
+    status: StatusCode,
+    body: BoxStream<'static, reqwest::Result<Bytes>>,
+    lines: LineSplitter,
+    pending: VecDeque<String>,
+    done: bool,
+}
+impl NdjsonBody {
+    async fn next_item<T: DeserializeOwned, ErrResp>(
+        &mut self,
+    ) -> Option<Result<T, ClientErr<ErrResp, JsonFormat>>> {
+        loop {
+            if let Some(line) = self.pending.pop_front() {
+                return Some(self.deserialize(line));
+            }
+            if self.done {
+                return None;
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.pending.extend(self.lines.push(&chunk)),
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(ClientErr::ReadRespBodyText(e)));
+                }
+                None => {
+                    self.done = true;
+                    self.pending.extend(self.lines.finish());
+                }
+            }
+        }
+    }
+
+    fn deserialize<T: DeserializeOwned, ErrResp>(
+        &self,
+        line: String,
+    ) -> Result<T, ClientErr<ErrResp, JsonFormat>> {
+        serde_json::from_str(&line).map_err(|deserialize_error| ClientErr::DeserializeError {
+            context: Box::new(self.sent.clone().context(self.status, line)),
+            deserialize_error,
+        })
+    }
+}
+
+pub trait ReceiveNdjson: Sized + ToRequestClient {
+    /// Yield each line of a newline-delimited JSON body (`application/x-ndjson`, JSON Lines)
+    /// as soon as it arrives. A line that doesn't deserialize is an error item, the next lines still come.
+    fn recv_ndjson<Item: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+    ) -> impl Stream<Item = Result<Item, ClientErr<ErrResp, JsonFormat>>> {
+        let connect = async move {
+            let mut request = self.try_into().map_err(ClientErr::BuildRequest)?;
+            request
+                .request
+                .headers_mut()
+                .insert(ACCEPT, HeaderValue::from_static("application/x-ndjson"));
+            let sent = request.sent();
+
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ClientErr::from_error_response(sent, response).await);
+            }
+            Ok(NdjsonBody {
+                sent,
+                status,
+                body: response.bytes_stream().boxed(),
+                lines: LineSplitter::default(),
+                pending: VecDeque::new(),
+                done: false,
+            })
+        };
+        stream::once(connect)
+            .map(|body| {
+                stream::unfold(Some(body), |state| async move {
+                    match state? {
+                        Ok(mut body) => {
+                            let item = body.next_item().await?;
+                            Some((item, Some(Ok(body))))
+                        }
+                        Err(err) => Some((Err(err), None)),
+                    }
+                })
+            })
+            .flatten()
+    }
+}
+impl<T: ToRequestClient> ReceiveNdjson for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::response_from_parts;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::ApiRequestBuilder;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response};
+    use serde::Deserialize;
+
+    /// log lines cut mid-line across chunks, with a blank line and no final newline
+    struct LogExport;
+    impl ApiMiddleware for LogExport {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let chunks = [
+                "{\"level\":\"info\",\"msg\":\"start",
+                "ed\"}\n\n{\"level\":\"warn\",\"msg\":\"slow\"}\r\nnot json\n",
+                "{\"level\":\"info\",\"msg\":\"done\"}",
+            ];
+            let chunks = chunks.map(|c| Ok::<_, std::io::Error>(c.as_bytes().to_vec()));
+            let body = reqwest::Body::wrap_stream(stream::iter(chunks));
+            let response = response_from_parts(StatusCode::OK, Default::default(), body);
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct LogLine {
+        level: String,
+        msg: String,
+    }
+
+    #[tokio::test]
+    async fn test_recv_ndjson() -> anyhow::Result<()> {
+        let lines: Vec<_> =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/logs"), vec![])
+                .with_middleware(LogExport)
+                .recv_ndjson::<LogLine, serde_json::Value>()
+                .collect()
+                .await;
+
+        assert_eq!(lines.len(), 4);
+        let msgs: Vec<&str> = lines
+            .iter()
+            .filter_map(|line| line.as_ref().ok())
+            .map(|line| line.msg.as_str())
+            .collect();
+        assert_eq!(msgs, vec!["started", "slow", "done"]);
+        assert!(
+            matches!(&lines[2], Err(ClientErr::DeserializeError { context, .. }) if context.response_text == "not json")
+        );
+        assert!(matches!(&lines[1], Ok(line) if line.level == "warn"));
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+use crate::auth::{bearer_header, AuthProvider};
+use crate::middleware::{ApiMiddleware, Middlewares};
+use crate::{ApiRequestBuilder, ReceiveJson};
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// tokens are refreshed this long before they actually expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    /// seconds
+    expires_in: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedToken {
+    pub access_token: String,
+    /// unix timestamp (seconds), None if the token endpoint didn't say
+    pub expires_at: Option<u64>,
+}
+impl CachedToken {
+    pub fn is_fresh(&self) -> bool {
+        let Some(expires_at) = self.expires_at else {
+            return true;
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        now + EXPIRY_MARGIN < Duration::from_secs(expires_at)
+    }
+}
+
+/// OAuth2 client-credentials grant: fetches a token from `token_url`,
+/// keeps it until it expires, and fetches a new one when the API answers 401
+pub struct OAuth2ClientCredentials {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    http_client: reqwest::Client,
+    /// middlewares applied to the token request itself
+    middlewares: Middlewares,
+    cached: tokio::sync::Mutex<Option<CachedToken>>,
+    #[cfg(feature = "file-cache")]
+    token_file: Option<TokenFile>,
+}
+/// where `cache_file` keeps the token, in plain text
+#[cfg(feature = "file-cache")]
+struct TokenFile {
+    location: Box<dyn file_cache::CacheLocation + Send + Sync>,
+    file_id: String,
+}
+#[cfg(feature = "file-cache")]
+impl TokenFile {
+    fn path(&self) -> anyhow::Result<std::path::PathBuf> {
+        use file_cache::FileBytes;
+        let dir = self.location.dir()?.join(CachedToken::namespace());
+        Ok(dir.join(&self.file_id))
+    }
+}
+impl OAuth2ClientCredentials {
+    pub fn new(token_url: &str, client_id: &str, client_secret: &str) -> Self {
+        Self {
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scopes: vec![],
+            http_client: reqwest::Client::new(),
+            middlewares: vec![],
+            cached: tokio::sync::Mutex::new(None),
+            #[cfg(feature = "file-cache")]
+            token_file: None,
+        }
+    }
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scopes.push(scope.to_string());
+        self
+    }
+    pub fn with_http_client(self, http_client: reqwest::Client) -> Self {
+        Self {
+            http_client,
+            ..self
+        }
+    }
+    pub fn with_middleware(mut self, middleware: impl ApiMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+    /// persist the token in the git repo's file-cache dir so it survives restarts
+    #[cfg(feature = "file-cache")]
+    pub fn cache_file(self, file_id: &str) -> Self {
+        self.cache_file_in(file_cache::GitRepoCacheDir {}, file_id)
+    }
+    /// cache_file, in another cache dir
+    #[cfg(feature = "file-cache")]
+    pub fn cache_file_in(
+        self,
+        location: impl file_cache::CacheLocation + Send + Sync + 'static,
+        file_id: &str,
+    ) -> Self {
+        let token_file = TokenFile {
+            location: Box::new(location),
+            file_id: file_id.to_string(),
+        };
+        Self {
+            token_file: Some(token_file),
+            ..self
+        }
+    }
+
+    /// current access token, fetching a new one if missing or expired
+    pub async fn token(&self) -> anyhow::Result<CachedToken> {
+        let mut cached = self.cached.lock().await;
+        #[cfg(feature = "file-cache")]
+        if cached.is_none() {
+            *cached = self.load_cache_file();
+        }
+        if let Some(token) = cached.as_ref().filter(|t| t.is_fresh()) {
+            return Ok(token.clone());
+        }
+
+        let token = self.fetch_token().await?;
+        #[cfg(feature = "file-cache")]
+        self.save_cache_file(&token)?;
+        *cached = Some(token.clone());
+        Ok(token)
+    }
+
+    async fn fetch_token(&self) -> anyhow::Result<CachedToken> {
+        let scope = self.scopes.join(" ");
+        let mut params = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", &self.client_id),
+            ("client_secret", &self.client_secret),
+        ];
+        if !scope.is_empty() {
+            params.push(("scope", &scope));
+        }
+        let request = self
+            .http_client
+            .post(&self.token_url)
+            .header("Accept", "application/json")
+            .form(&params);
+
+        let resp: TokenResponse = ApiRequestBuilder::new(request, self.middlewares.clone())
+            .recv_json::<_, serde_json::Value>()
+            .await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        Ok(CachedToken {
+            access_token: resp.access_token,
+            expires_at: resp.expires_in.map(|secs| now.as_secs() + secs),
+        })
+    }
+
+    #[cfg(feature = "file-cache")]
+    fn load_cache_file(&self) -> Option<CachedToken> {
+        use file_cache::FileBytes;
+        let path = self.token_file.as_ref()?.path().ok()?;
+        CachedToken::from_file(&path).ok()
+    }
+    #[cfg(feature = "file-cache")]
+    fn save_cache_file(&self, token: &CachedToken) -> anyhow::Result<()> {
+        use file_cache::FileBytes;
+        let Some(token_file) = &self.token_file else {
+            return Ok(());
+        };
+        let path = token_file.path()?;
+        std::fs::create_dir_all(path.parent().unwrap_or(&path))?;
+        token.to_file(&path)
+    }
+    /// so that a token the API rejected isn't loaded again
+    #[cfg(feature = "file-cache")]
+    fn remove_cache_file(&self) {
+        let Some(token_file) = &self.token_file else {
+            return;
+        };
+        if let Ok(path) = token_file.path() {
+            let _ = std::fs::remove_file(file_cache::meta::EntryMeta::path(&path));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl AuthProvider for OAuth2ClientCredentials {
+    async fn auth_header(&self) -> anyhow::Result<HeaderValue> {
+        bearer_header(&self.token().await?.access_token)
+    }
+    async fn invalidate(&self) {
+        let mut cached = self.cached.lock().await;
+        #[cfg(feature = "file-cache")]
+        self.remove_cache_file();
+        *cached = None;
+    }
+}
+
+#[cfg(feature = "file-cache")]
+impl file_cache::FileBytes for CachedToken {
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Auth;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::Next;
+    use futures::future::BoxFuture;
+    use reqwest::header::AUTHORIZATION;
+    use reqwest::{Request, Response};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// token endpoint handing out "token-1", "token-2", ...
+    #[derive(Default)]
+    struct TokenEndpoint {
+        issued: Arc<AtomicU32>,
+    }
+    impl ApiMiddleware for TokenEndpoint {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let n = self.issued.fetch_add(1, Ordering::SeqCst) + 1;
+            let body = format!(r#"{{"access_token":"token-{n}","expires_in":3600}}"#);
+            Box::pin(async move { stub_response(200, &body) })
+        }
+    }
+
+    /// API only accepting the latest token
+    struct Api {
+        issued: Arc<AtomicU32>,
+    }
+    impl ApiMiddleware for Api {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let expected = format!("Bearer token-{}", self.issued.load(Ordering::SeqCst));
+            let status = match request.headers().get(AUTHORIZATION) {
+                Some(value) if value == expected.as_str() => 200,
+                _ => 401,
+            };
+            Box::pin(async move { stub_response(status, "null") })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached_and_refreshed_on_401() -> anyhow::Result<()> {
+        let issued = Arc::new(AtomicU32::new(0));
+        let provider = Arc::new(
+            OAuth2ClientCredentials::new("http://localhost/token", "id", "secret").with_middleware(
+                TokenEndpoint {
+                    issued: issued.clone(),
+                },
+            ),
+        );
+        let call = || {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/api"), vec![])
+                .with_middleware(Auth(provider.clone()))
+                .with_middleware(Api {
+                    issued: issued.clone(),
+                })
+                .recv_json::<(), serde_json::Value>()
+        };
+
+        call().await?;
+        call().await?;
+        assert_eq!(issued.load(Ordering::SeqCst), 1);
+
+        // server-side revocation: the cached token-1 gets a 401, a new token is fetched transparently
+        issued.fetch_add(1, Ordering::SeqCst);
+        call().await?;
+        assert_eq!(issued.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    #[cfg(feature = "file-cache")]
+    #[tokio::test]
+    async fn test_cache_file_dropped_on_401() -> anyhow::Result<()> {
+        use file_cache::FileBytes;
+        let temp = file_cache::TempCacheDir::new()?;
+        let issued = Arc::new(AtomicU32::new(0));
+        let provider = Arc::new(
+            OAuth2ClientCredentials::new("http://localhost/token", "id", "secret")
+                .with_middleware(TokenEndpoint {
+                    issued: issued.clone(),
+                })
+                .cache_file_in(temp.path().to_path_buf(), "token"),
+        );
+        let call = || {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/api"), vec![])
+                .with_middleware(Auth(provider.clone()))
+                .with_middleware(Api {
+                    issued: issued.clone(),
+                })
+                .recv_json::<(), serde_json::Value>()
+        };
+        call().await?;
+
+        // the revoked token-1 isn't reloaded from the cache file
+        issued.fetch_add(1, Ordering::SeqCst);
+        let result = call().await;
+        let path = temp.path().join(CachedToken::namespace()).join("token");
+        let cached = std::fs::read_to_string(&path);
+        provider.invalidate().await;
+        result?;
+        assert_eq!(issued.load(Ordering::SeqCst), 3);
+        assert!(cached?.contains("token-3"));
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+//! Client generation from an OpenAPI 3 document (JSON), meant to run in a build script:
+//!
+//! ```text
+//! // build.rs
+//! let out = PathBuf::from(std::env::var("OUT_DIR")?).join("pet_store.rs");
+//! api_client_utils::openapi::write_client(Path::new("pet_store.json"), "PetStore", &out)?;
+//! println!("cargo:rerun-if-changed=pet_store.json");
+//!
+//! // src/pet_store.rs
+//! include!(concat!(env!("OUT_DIR"), "/pet_store.rs"));
+//! ```
+//!
+//! Every schema of `components.schemas` becomes a struct, every operation an async method of
+//! `{client_name}` returning the first 2xx JSON response, with `default` / 4xx JSON bodies as ErrResp.
+//! The generated code uses `serde` and `reqwest` by path, so the including crate needs both.
+
+use serde_json::Value;
+use std::fmt::Write;
+use std::path::Path;
+
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+
+/// Rust source of the structs and client described by `spec`
+pub fn generate_client(spec: &str, client_name: &str) -> anyhow::Result<String> {
+    let spec: Value = serde_json::from_str(spec)?;
+    let spec = Spec(&spec);
+    let mut out = String::from("// @generated from an OpenAPI document, do not edit\n\n");
+
+    if let Some(schemas) = spec
+        .0
+        .pointer("/components/schemas")
+        .and_then(Value::as_object)
+    {
+        for (name, schema) in schemas {
+            out.push_str(&spec.schema_item(&pascal_case(name), schema));
+        }
+    }
+
+    writeln!(
+        out,
+        "pub struct {client_name} {{
+    pub base_url: String,
+    pub http_client: reqwest::Client,
+}}
+impl {client_name} {{
+    pub fn new(base_url: &str) -> Self {{
+        Self {{
+            base_url: base_url.to_string(),
+            http_client: reqwest::Client::new(),
+        }}
+    }}
+}}
+impl api_client_utils::JsonApiClient for {client_name} {{
+    fn base_url(&self) -> &str {{
+        &self.base_url
+    }}
+    fn http_client(&self) -> &reqwest::Client {{
+        &self.http_client
+    }}
+}}
+impl {client_name} {{"
+    )?;
+    let paths = spec.0.get("paths").and_then(Value::as_object);
+    for (path, item) in paths.into_iter().flatten() {
+        for method in METHODS {
+            if let Some(operation) = item.get(method) {
+                out.push_str(&spec.operation(method, path, item, operation)?);
+            }
+        }
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// generate_client from a file, writing the result to `out`
+pub fn write_client(spec_path: &Path, client_name: &str, out: &Path) -> anyhow::Result<()> {
+    let spec = std::fs::read_to_string(spec_path)?;
+    std::fs::write(out, generate_client(&spec, client_name)?)?;
+    Ok(())
+}
+
+struct Param {
+    name: String,
+    ident: String,
+    rust_type: String,
+    location: String,
+    required: bool,
+}
+
+struct Spec<'a>(&'a Value);
+impl<'a> Spec<'a> {
+    /// follow `$ref`s within the document
+    fn resolve(&self, value: &'a Value) -> &'a Value {
+        match value.get("$ref").and_then(Value::as_str) {
+            Some(reference) => reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.0.pointer(pointer))
+                .map(|target| self.resolve(target))
+                .unwrap_or(value),
+            None => value,
+        }
+    }
+
+    fn rust_type(&self, schema: &Value) -> String {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return pascal_case(reference.rsplit('/').next().unwrap_or(reference));
+        }
+        let format = schema.get("format").and_then(Value::as_str);
+        match (schema.get("type").and_then(Value::as_str), format) {
+            (Some("string"), _) => "String".to_string(),
+            (Some("integer"), Some("int32")) => "i32".to_string(),
+            (Some("integer"), _) => "i64".to_string(),
+            (Some("number"), Some("float")) => "f32".to_string(),
+            (Some("number"), _) => "f64".to_string(),
+            (Some("boolean"), _) => "bool".to_string(),
+            (Some("array"), _) => match schema.get("items") {
+                Some(items) => format!("Vec<{}>", self.rust_type(items)),
+                None => "Vec<serde_json::Value>".to_string(),
+            },
+            _ => "serde_json::Value".to_string(),
+        }
+    }
+
+    /// struct for objects, enum for string enums, type alias for anything else
+    fn schema_item(&self, name: &str, schema: &Value) -> String {
+        const DERIVE: &str =
+            "#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]";
+        if let Some(variants) = schema.get("enum").and_then(Value::as_array) {
+            let mut item = format!("{DERIVE}\npub enum {name} {{\n");
+            for variant in variants.iter().filter_map(Value::as_str) {
+                let _ = writeln!(
+                    item,
+                    "    #[serde(rename = {variant:?})]\n    {},",
+                    pascal_case(variant)
+                );
+            }
+            return item + "}\n\n";
+        }
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            return format!("pub type {name} = {};\n\n", self.rust_type(schema));
+        };
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut item = format!("{DERIVE}\npub struct {name} {{\n");
+        for (field, field_schema) in properties {
+            let ident = rust_ident(&snake_case(field));
+            if ident.trim_start_matches("r#") != field {
+                let _ = writeln!(item, "    #[serde(rename = {field:?})]");
+            }
+            let rust_type = self.rust_type(field_schema);
+            match required.contains(&field.as_str()) {
+                true => {
+                    let _ = writeln!(item, "    pub {ident}: {rust_type},");
+                }
+                false => {
+                    let _ = writeln!(
+                        item,
+                        "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub {ident}: Option<{rust_type}>,"
+                    );
+                }
+            }
+        }
+        item + "}\n\n"
+    }
+
+    fn params(&self, path_item: &'a Value, operation: &'a Value) -> Vec<Param> {
+        let declared = [path_item, operation]
+            .into_iter()
+            .filter_map(|v| v.get("parameters").and_then(Value::as_array))
+            .flatten()
+            .map(|p| self.resolve(p));
+        let mut params: Vec<Param> = vec![];
+        for param in declared {
+            let (Some(name), Some(location)) = (
+                param.get("name").and_then(Value::as_str),
+                param.get("in").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            // operation-level parameters override the path item's
+            params.retain(|p| !(p.name == name && p.location == location));
+            params.push(Param {
+                name: name.to_string(),
+                ident: rust_ident(&snake_case(name)),
+                rust_type: param
+                    .get("schema")
+                    .map(|s| self.rust_type(s))
+                    .unwrap_or_else(|| "String".to_string()),
+                location: location.to_string(),
+                required: location == "path" || param["required"].as_bool().unwrap_or(false),
+            });
+        }
+        params
+    }
+
+    fn json_schema(&self, content_holder: &'a Value) -> Option<&'a Value> {
+        self.resolve(content_holder)
+            .get("content")?
+            .as_object()?
+            .iter()
+            .find(|(mime, _)| mime.contains("json"))?
+            .1
+            .get("schema")
+    }
+
+    fn operation(
+        &self,
+        method: &str,
+        path: &str,
+        path_item: &'a Value,
+        operation: &'a Value,
+    ) -> anyhow::Result<String> {
+        let name = match operation.get("operationId").and_then(Value::as_str) {
+            Some(id) => snake_case(id),
+            None => snake_case(&format!(
+                "{method}{}",
+                path.replace(['/', '{', '}', '-'], "_")
+            )),
+        };
+        let params = self.params(path_item, operation);
+        let body = operation
+            .get("requestBody")
+            .and_then(|b| self.json_schema(b))
+            .map(|s| self.rust_type(s));
+
+        let responses = operation.get("responses").and_then(Value::as_object);
+        let mut statuses: Vec<(&String, &Value)> = responses.into_iter().flatten().collect();
+        statuses.sort_by_key(|(status, _)| status.as_str());
+        let ok = statuses
+            .iter()
+            .find(|(status, _)| status.starts_with('2'))
+            .and_then(|(_, r)| self.json_schema(r))
+            .map(|s| self.rust_type(s));
+        let err = statuses
+            .iter()
+            .filter(|(status, _)| *status == "default" || status.starts_with(['4', '5']))
+            .find_map(|(_, r)| self.json_schema(r))
+            .map(|s| self.rust_type(s))
+            .unwrap_or_else(|| "serde_json::Value".to_string());
+
+        let mut args = String::new();
+        let mut url_path = path.to_string();
+        for param in &params {
+            if !matches!(param.location.as_str(), "path" | "query") {
+                continue;
+            }
+            match param.required {
+                true => write!(args, ", {}: {}", param.ident, param.rust_type)?,
+                false => write!(args, ", {}: Option<{}>", param.ident, param.rust_type)?,
+            }
+            if param.location == "path" {
+                let placeholder = param.ident.trim_start_matches("r#");
+                url_path = url_path.replace(
+                    &format!("{{{}}}", param.name),
+                    &format!("{{{placeholder}}}"),
+                );
+            }
+        }
+        if let Some(body) = &body {
+            write!(args, ", body: &{body}")?;
+        }
+
+        let url = match url_path.contains('{') {
+            true => format!("&format!({url_path:?})"),
+            false => format!("{url_path:?}"),
+        };
+        let returns = ok
+            .clone()
+            .unwrap_or_else(|| "api_client_utils::context::RespContext".to_string());
+        let mut method_src = String::new();
+        if let Some(summary) = operation.get("summary").and_then(Value::as_str) {
+            writeln!(method_src, "    /// {summary}")?;
+        }
+        writeln!(
+            method_src,
+            "    pub async fn {name}(&self{args}) -> api_client_utils::error::aliases::JsonClientResult<{returns}, {err}> {{
+        let request = api_client_utils::ApiClient::<api_client_utils::serialization_formats::JsonFormat>::request(
+            self,
+            reqwest::Method::{},
+            {url},
+        );",
+            method.to_uppercase()
+        )?;
+        for param in params.iter().filter(|p| p.location == "query") {
+            let (name, ident) = (&param.name, &param.ident);
+            match param.required {
+                true => writeln!(
+                    method_src,
+                    "        let request = request.query(&[({name:?}, {ident})]);"
+                )?,
+                false => writeln!(
+                    method_src,
+                    "        let request = match {ident} {{
+            Some(value) => request.query(&[({name:?}, value)]),
+            None => request,
+        }};"
+                )?,
+            }
+        }
+        if body.is_some() {
+            writeln!(method_src, "        let request = request.json(body);")?;
+        }
+        match ok {
+            Some(_) => writeln!(method_src, "        api_client_utils::ReceiveJson::recv_json(request).await")?,
+            None => writeln!(
+                method_src,
+                "        api_client_utils::ReceiveResp::<api_client_utils::serialization_formats::JsonFormat>::expect_success(request).await"
+            )?,
+        }
+        writeln!(method_src, "    }}")?;
+        Ok(method_src)
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        match c {
+            c if c.is_uppercase() => {
+                if prev_lower {
+                    snake.push('_');
+                }
+                snake.extend(c.to_lowercase());
+                prev_lower = false;
+            }
+            c if c.is_alphanumeric() => {
+                snake.push(c);
+                prev_lower = true;
+            }
+            _ => {
+                if !snake.is_empty() && !snake.ends_with('_') {
+                    snake.push('_');
+                }
+                prev_lower = false;
+            }
+        }
+    }
+    snake.trim_end_matches('_').to_string()
+}
+
+fn pascal_case(name: &str) -> String {
+    snake_case(name)
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn rust_ident(name: &str) -> String {
+    const KEYWORDS: [&str; 12] = [
+        "type", "match", "ref", "move", "fn", "impl", "struct", "enum", "mod", "use", "loop",
+        "where",
+    ];
+    match KEYWORDS.contains(&name) {
+        true => format!("r#{name}"),
+        false => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PET_STORE: &str = r##"{
+        "openapi": "3.0.0",
+        "paths": {
+            "/pets/{petId}": {
+                "parameters": [{ "name": "petId", "in": "path", "required": true, "schema": { "type": "integer" } }],
+                "get": {
+                    "operationId": "getPetById",
+                    "summary": "Info for a specific pet",
+                    "responses": {
+                        "200": { "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } } } },
+                        "default": { "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } } }
+                    }
+                },
+                "delete": {
+                    "operationId": "deletePet",
+                    "responses": { "204": { "description": "deleted" } }
+                }
+            },
+            "/pets": {
+                "get": {
+                    "operationId": "listPets",
+                    "parameters": [{ "name": "limit", "in": "query", "schema": { "type": "integer", "format": "int32" } }],
+                    "responses": { "200": { "content": { "application/json": { "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Pet" } } } } } }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Pet": {
+                    "required": ["id", "name"],
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "name": { "type": "string" },
+                        "type": { "$ref": "#/components/schemas/PetType" },
+                        "birthDate": { "type": "string", "format": "date" }
+                    }
+                },
+                "PetType": { "type": "string", "enum": ["cat", "dog"] },
+                "Error": { "required": ["message"], "properties": { "message": { "type": "string" } } }
+            }
+        }
+    }"##;
+
+    #[test]
+    fn test_generate_pet_store() -> anyhow::Result<()> {
+        let code = generate_client(PET_STORE, "PetStore")?;
+        for expected in [
+            "pub struct Pet {",
+            "    pub id: i64,",
+            "    pub r#type: Option<PetType>,",
+            "    #[serde(rename = \"birthDate\")]",
+            "pub enum PetType {",
+            "impl api_client_utils::JsonApiClient for PetStore {",
+            "    pub async fn get_pet_by_id(&self, pet_id: i64) -> api_client_utils::error::aliases::JsonClientResult<Pet, Error> {",
+            "            &format!(\"/pets/{pet_id}\"),",
+            "    pub async fn list_pets(&self, limit: Option<i32>) -> api_client_utils::error::aliases::JsonClientResult<Vec<Pet>, serde_json::Value> {",
+            "expect_success(request).await",
+        ] {
+            assert!(code.contains(expected), "missing `{expected}` in:\n{code}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_conversion() {
+        assert_eq!(snake_case("getPetById"), "get_pet_by_id");
+        assert_eq!(snake_case("list-pets"), "list_pets");
+        assert_eq!(pascal_case("pet_type"), "PetType");
+    }
+}
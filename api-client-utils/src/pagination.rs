@@ -0,0 +1,464 @@
+use crate::error::aliases::JsonClientResult;
+use crate::error::ClientErr;
+use crate::serialization_formats::JsonFormat;
+use crate::status_errors::ErrorBody;
+use crate::{ApiRequestBuilder, ReceiveJson, ReceiveResp, RequestClient, ToRequestClient};
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// One page of results as returned by the API
+pub trait Paginated: DeserializeOwned {
+    type Item;
+    fn into_items(self) -> Vec<Self::Item>;
+    /// Some(false) if the API signals this is the last page, None to guess from the item count
+    fn has_more(&self) -> Option<bool> {
+        None
+    }
+}
+// bare JSON arrays
+impl<T: DeserializeOwned> Paginated for Vec<T> {
+    type Item = T;
+    fn into_items(self) -> Vec<T> {
+        self
+    }
+}
+
+/// How pages are requested through query params
+#[derive(Debug, Clone)]
+pub enum Pagination {
+    /// `?page=1&per_page=50`, `first_page` is usually 0 or 1
+    PageNumber {
+        page_param: &'static str,
+        first_page: usize,
+        size_param: &'static str,
+        page_size: usize,
+    },
+    /// `?offset=100&limit=50`
+    Offset {
+        offset_param: &'static str,
+        limit_param: &'static str,
+        limit: usize,
+    },
+}
+impl Pagination {
+    pub fn pages(page_size: usize) -> Self {
+        Pagination::PageNumber {
+            page_param: "page",
+            first_page: 1,
+            size_param: "per_page",
+            page_size,
+        }
+    }
+    pub fn offset(limit: usize) -> Self {
+        Pagination::Offset {
+            offset_param: "offset",
+            limit_param: "limit",
+            limit,
+        }
+    }
+    pub fn page_size(&self) -> usize {
+        match self {
+            Pagination::PageNumber { page_size, .. } => *page_size,
+            Pagination::Offset { limit, .. } => *limit,
+        }
+    }
+    /// query params for the nth (0-based) page
+    pub fn query(&self, page_index: usize) -> [(&'static str, String); 2] {
+        match self {
+            Pagination::PageNumber {
+                page_param,
+                first_page,
+                size_param,
+                page_size,
+            } => [
+                (page_param, (first_page + page_index).to_string()),
+                (size_param, page_size.to_string()),
+            ],
+            Pagination::Offset {
+                offset_param,
+                limit_param,
+                limit,
+            } => [
+                (offset_param, (page_index * limit).to_string()),
+                (limit_param, limit.to_string()),
+            ],
+        }
+    }
+}
+
+impl ApiRequestBuilder {
+    /// Fetch pages one after the other, yielding their items, until the API signals the last page
+    /// (or, if it doesn't say, until a page comes back with fewer items than the page size).
+    pub fn recv_json_paged<P, ErrResp>(
+        self,
+        pagination: Pagination,
+    ) -> impl Stream<Item = JsonClientResult<P::Item, ErrResp>>
+    where
+        P: Paginated,
+        ErrResp: ErrorBody,
+    {
+        stream::unfold(Some(0), move |page_index| {
+            let request = self.try_clone();
+            let pagination = pagination.clone();
+            async move {
+                let page_index = page_index?;
+                let request = match next_page_request(request) {
+                    Ok(request) => request,
+                    Err(err) => return Some((vec![Err(err)], None)),
+                };
+
+                let page = match request
+                    .query(&pagination.query(page_index))
+                    .recv_json::<P, ErrResp>()
+                    .await
+                {
+                    Ok(page) => page,
+                    Err(err) => return Some((vec![Err(err)], None)),
+                };
+                let has_more = page.has_more();
+                let items = page.into_items();
+                let has_more =
+                    has_more.unwrap_or(!items.is_empty() && items.len() >= pagination.page_size());
+
+                let next_state = has_more.then_some(page_index + 1);
+                Some((items.into_iter().map(Ok).collect(), next_state))
+            }
+        })
+        .flat_map(stream::iter)
+    }
+}
+
+/// The copy of a request to send for the next page, from its `try_clone()`: None when
+/// its body is a stream, which can only be sent once
+fn next_page_request<R, ErrResp>(request: Option<R>) -> JsonClientResult<R, ErrResp> {
+    request.ok_or_else(|| {
+        let err = anyhow::anyhow!("can't paginate a request with a streaming body");
+        ClientErr::middleware(err)
+    })
+}
+
+/// Where the next cursor of a cursor-paginated API is found
+#[derive(Debug, Clone)]
+pub enum NextCursor {
+    /// JSON pointer to a cursor value in the body (e.g. `/meta/next_cursor`), sent back as query `param`
+    BodyCursor { pointer: String, param: String },
+    /// JSON pointer to the full URL of the next page in the body (e.g. `/links/next`)
+    BodyUrl { pointer: String },
+    /// `Link: <https://api/items?cursor=abc>; rel="next"` response header
+    LinkHeader,
+}
+
+#[derive(Debug, Clone)]
+pub struct CursorPagination {
+    /// JSON pointer to the array of items in each page, "" if the body is the array itself
+    pub items: String,
+    pub next: NextCursor,
+}
+impl CursorPagination {
+    pub fn body_cursor(items: &str, cursor_pointer: &str, cursor_param: &str) -> Self {
+        Self {
+            items: items.to_string(),
+            next: NextCursor::BodyCursor {
+                pointer: cursor_pointer.to_string(),
+                param: cursor_param.to_string(),
+            },
+        }
+    }
+    pub fn body_url(items: &str, url_pointer: &str) -> Self {
+        Self {
+            items: items.to_string(),
+            next: NextCursor::BodyUrl {
+                pointer: url_pointer.to_string(),
+            },
+        }
+    }
+    pub fn link_header(items: &str) -> Self {
+        Self {
+            items: items.to_string(),
+            next: NextCursor::LinkHeader,
+        }
+    }
+
+    /// URL of the page after the one requested with `current`, None when there is no next page
+    fn next_url(&self, current: &Url, body: &Value, link_next: Option<String>) -> Option<Url> {
+        let non_empty_str = |v: &Value| match v {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        match &self.next {
+            NextCursor::BodyCursor { pointer, param } => {
+                let cursor = body.pointer(pointer).and_then(non_empty_str)?;
+                Some(with_query_param(current, param, &cursor))
+            }
+            NextCursor::BodyUrl { pointer } => {
+                let next = body.pointer(pointer).and_then(non_empty_str)?;
+                current.join(&next).ok()
+            }
+            NextCursor::LinkHeader => current.join(&link_next?).ok(),
+        }
+    }
+}
+
+fn with_query_param(url: &Url, param: &str, value: &str) -> Url {
+    let mut url = url.clone();
+    let others: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != param)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(others)
+        .append_pair(param, value);
+    url
+}
+
+impl ApiRequestBuilder {
+    /// Follow a cursor-paginated API, yielding the items of each page until no next cursor is given,
+    /// or the one given leads back to a page already fetched
+    pub fn recv_json_cursor<Item, ErrResp>(
+        self,
+        pagination: CursorPagination,
+    ) -> impl Stream<Item = JsonClientResult<Item, ErrResp>>
+    where
+        Item: DeserializeOwned,
+        ErrResp: ErrorBody,
+    {
+        let first = ToRequestClient::try_into(self).map_err(ClientErr::BuildRequest);
+        stream::unfold(Some((first, HashSet::new())), move |state| {
+            let pagination = pagination.clone();
+            async move {
+                let (request, mut visited) = state?;
+                let request = match request {
+                    Ok(request) => request,
+                    Err(err) => return Some((vec![Err(err)], None)),
+                };
+                let next_request = match next_page_request(request.try_clone()) {
+                    Ok(next_request) => next_request,
+                    Err(err) => return Some((vec![Err(err)], None)),
+                };
+                let current_url = request.request.url().clone();
+                visited.insert(current_url.clone());
+
+                let page = match ReceiveResp::<JsonFormat>::partial_expect::<Value, ErrResp>(
+                    request,
+                )
+                .await
+                {
+                    Ok(page) => page,
+                    Err(err) => return Some((vec![Err(err)], None)),
+                };
+                let link_next = page.context.links().next().map(str::to_string);
+                let next_state = pagination
+                    .next_url(&current_url, &page.ok_body, link_next)
+                    .filter(|next| !visited.contains(next))
+                    .map(|url| {
+                        let mut next_request: RequestClient = next_request;
+                        *next_request.request.url_mut() = url;
+                        (Ok(next_request), visited)
+                    });
+
+                let items = match page.ok_body.pointer(&pagination.items) {
+                    Some(Value::Array(items)) => items.clone(),
+                    _ => {
+                        let msg =
+                            format!("no array of items at {:?} in the page", pagination.items);
+                        let err = ClientErr::DeserializeError {
+                            context: Box::new(page.context),
+                            deserialize_error: serde::de::Error::custom(msg),
+                        };
+                        return Some((vec![Err(err)], None));
+                    }
+                };
+                let items = items
+                    .into_iter()
+                    .map(|item| {
+                        serde_json::from_value(item).map_err(|deserialize_error| {
+                            ClientErr::DeserializeError {
+                                context: Box::new(page.context.clone()),
+                                deserialize_error,
+                            }
+                        })
+                    })
+                    .collect();
+                Some((items, next_state))
+            }
+        })
+        .flat_map(stream::iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::{stub_response, StubResponse};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use futures::TryStreamExt;
+    use reqwest::header::LINK;
+    use reqwest::{Request, Response};
+
+    /// serves `1..=total` by pages
+    struct NumbersApi {
+        total: usize,
+    }
+    impl ApiMiddleware for NumbersApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let param = |name: &str| -> usize {
+                let found = request.url().query_pairs().find(|(k, _)| k == name);
+                found.and_then(|(_, v)| v.parse().ok()).unwrap_or_default()
+            };
+            let (page, per_page) = (param("page"), param("per_page"));
+            let items: Vec<usize> = ((page - 1) * per_page + 1..=page * per_page)
+                .filter(|n| *n <= self.total)
+                .collect();
+            let body = serde_json::to_string(&items).unwrap_or_default();
+            Box::pin(async move { stub_response(200, &body) })
+        }
+    }
+
+    /// serves `1..=total` two by two, with the next cursor in the body and in a Link header
+    struct CursorApi {
+        total: usize,
+    }
+    impl ApiMiddleware for CursorApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let after: usize = request
+                .url()
+                .query_pairs()
+                .find(|(k, _)| k == "after")
+                .and_then(|(_, v)| v.parse().ok())
+                .unwrap_or_default();
+            let items: Vec<usize> = (after + 1..=after + 2)
+                .filter(|n| *n <= self.total)
+                .collect();
+            let next = items.last().filter(|last| **last < self.total);
+            let body = serde_json::json!({ "data": items, "meta": { "next": next.map(|n| n.to_string()) } });
+            let link = next.map(|n| format!(r#"</items?after={n}>; rel="next""#));
+            Box::pin(async move {
+                let mut response = stub_response(200, &body.to_string())?;
+                if let Some(link) = link {
+                    response.headers_mut().insert(LINK, link.parse().unwrap());
+                }
+                Ok(response)
+            })
+        }
+    }
+
+    /// answers each request with the page for its `p` query param
+    struct PagesApi(fn(&str) -> Result<Response, ExecuteErr>);
+    impl ApiMiddleware for PagesApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let p = (request.url().query_pairs())
+                .find(|(k, _)| k == "p")
+                .map(|(_, v)| v.into_owned())
+                .unwrap_or_default();
+            let response = (self.0)(&p);
+            Box::pin(async move { response })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recv_json_cursor() -> anyhow::Result<()> {
+        let request = || {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get("http://localhost/items?filter=all"),
+                vec![],
+            )
+            .with_middleware(CursorApi { total: 5 })
+        };
+
+        let from_body: Vec<_> = request()
+            .recv_json_cursor::<usize, serde_json::Value>(CursorPagination::body_cursor(
+                "/data",
+                "/meta/next",
+                "after",
+            ))
+            .try_collect()
+            .await?;
+        assert_eq!(from_body, vec![1, 2, 3, 4, 5]);
+
+        let from_link: Vec<_> = request()
+            .recv_json_cursor::<usize, serde_json::Value>(CursorPagination::link_header("/data"))
+            .try_collect()
+            .await?;
+        assert_eq!(from_link, vec![1, 2, 3, 4, 5]);
+
+        // a server handing out the cursor of the page requested
+        let stuck: Vec<_> = ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/items?after=abc"),
+            vec![],
+        )
+        .with_middleware(StubResponse::new(
+            200,
+            r#"{"data":[1],"meta":{"next":"abc"}}"#,
+        ))
+        .recv_json_cursor::<usize, serde_json::Value>(CursorPagination::body_cursor(
+            "/data",
+            "/meta/next",
+            "after",
+        ))
+        .try_collect()
+        .await?;
+        assert_eq!(stuck, vec![1]);
+
+        // pages linking to each other
+        let cycle = |a_or_b: &str| {
+            let page = match a_or_b {
+                "a" => r#"{"data":[1],"next":"/items?p=b"}"#,
+                _ => r#"{"data":[2],"next":"/items?p=a"}"#,
+            };
+            stub_response(200, page)
+        };
+        let cycled: Vec<_> = ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/items?p=a"),
+            vec![],
+        )
+        .with_middleware(PagesApi(cycle))
+        .recv_json_cursor::<usize, serde_json::Value>(CursorPagination::body_url("/data", "/next"))
+        .try_collect()
+        .await?;
+        assert_eq!(cycled, vec![1, 2]);
+
+        let wrong_pointer: Result<Vec<_>, _> = request()
+            .recv_json_cursor::<usize, serde_json::Value>(CursorPagination::link_header("/items"))
+            .try_collect()
+            .await;
+        assert!(
+            matches!(wrong_pointer, Err(ClientErr::DeserializeError { .. })),
+            "{wrong_pointer:?}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recv_json_paged() -> anyhow::Result<()> {
+        let numbers: Vec<usize> = ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/numbers"),
+            vec![],
+        )
+        .with_middleware(NumbersApi { total: 5 })
+        .recv_json_paged::<Vec<usize>, serde_json::Value>(Pagination::pages(2))
+        .try_collect()
+        .await?;
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+        Ok(())
+    }
+}
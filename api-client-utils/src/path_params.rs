@@ -0,0 +1,92 @@
+use std::fmt::{Display, Write};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum PathParamsErr {
+    #[error("no value for path param `{0}`")]
+    Missing(String),
+    #[error("path param `{0}` not in the path template")]
+    Extra(String),
+    #[error("path param `{0}` given twice")]
+    Duplicate(String),
+    #[error("unclosed `{{` in path template `{0}`")]
+    Unclosed(String),
+}
+
+/// Fill the `{name}` placeholders of `template` with `params`, each percent-encoded as one path segment.
+/// Every placeholder needs a param and every param a placeholder.
+///
+/// ```text
+/// fill("/pets/{id}/visits/{visit_id}", &[("id", &pet.id), ("visit_id", &"2024/01")])
+///     == Ok("/pets/42/visits/2024%2F01")
+/// ```
+pub fn fill(template: &str, params: &[(&str, &dyn Display)]) -> Result<String, PathParamsErr> {
+    for (i, (name, _)) in params.iter().enumerate() {
+        if params[..i].iter().any(|(other, _)| other == name) {
+            return Err(PathParamsErr::Duplicate(name.to_string()));
+        }
+    }
+    let mut used = vec![false; params.len()];
+    let mut path = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        path.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            return Err(PathParamsErr::Unclosed(template.to_string()));
+        };
+        let name = &rest[start + 1..start + len];
+        let Some(i) = params.iter().position(|(param, _)| *param == name) else {
+            return Err(PathParamsErr::Missing(name.to_string()));
+        };
+        used[i] = true;
+        encode_segment(&mut path, &params[i].1.to_string());
+        rest = &rest[start + len + 1..];
+    }
+    path.push_str(rest);
+    match used.iter().position(|used| !used) {
+        Some(unused) => Err(PathParamsErr::Extra(params[unused].0.to_string())),
+        None => Ok(path),
+    }
+}
+
+/// everything but RFC 3986 unreserved characters, so a value can't add segments or a query
+fn encode_segment(out: &mut String, value: &str) {
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => {
+                let _ = write!(out, "%{byte:02X}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let visit = "2024/01 ?check=é";
+        assert_eq!(
+            fill(
+                "/pets/{id}/visits/{visit_id}",
+                &[("visit_id", &visit), ("id", &42)]
+            ),
+            Ok("/pets/42/visits/2024%2F01%20%3Fcheck%3D%C3%A9".to_string())
+        );
+        assert_eq!(
+            fill("/pets/{id}/visits/{visit_id}", &[("id", &42)]),
+            Err(PathParamsErr::Missing("visit_id".to_string()))
+        );
+        assert_eq!(
+            fill("/pets/{id}", &[("id", &42), ("owner", &"me")]),
+            Err(PathParamsErr::Extra("owner".to_string()))
+        );
+        assert_eq!(
+            fill("/pets/{id", &[("id", &42)]),
+            Err(PathParamsErr::Unclosed("/pets/{id".to_string()))
+        );
+    }
+}
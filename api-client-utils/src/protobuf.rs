@@ -0,0 +1,186 @@
+use crate::binary_format::{BinaryFormat, ReceiveBinary};
+use crate::error::ClientErr;
+use crate::serialization_formats::{ApiFormat, SerialFormat};
+use crate::{ApiRequestBuilder, ToRequestClient};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use std::future::Future;
+
+const PROTOBUF: &str = "application/x-protobuf";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProtobufErr {
+    #[error("{0}")]
+    Decode(#[from] prost::DecodeError),
+    #[error("expected a protobuf response, got content-type {0}")]
+    UnexpectedContentType(String),
+    #[error("protobuf bodies are binary, use recv_protobuf()")]
+    TextBody,
+}
+
+/// Protobuf bodies for prost-generated types
+#[derive(Debug)]
+pub struct ProtobufFormat;
+impl SerialFormat for ProtobufFormat {
+    type Error = ProtobufErr;
+    fn from_str<T: for<'a> Deserialize<'a>>(_input: &str) -> Result<T, Self::Error> {
+        Err(ProtobufErr::TextBody)
+    }
+}
+impl ApiFormat for ProtobufFormat {
+    fn with_accept_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Accept", PROTOBUF)
+    }
+    fn with_content_type_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Content-Type", PROTOBUF)
+    }
+}
+impl<T: prost::Message + Default> BinaryFormat<T> for ProtobufFormat {
+    const CONTENT_TYPES: &'static [&'static str] = &[
+        PROTOBUF,
+        "application/protobuf",
+        "application/vnd.google.protobuf",
+        "application/octet-stream",
+    ];
+    fn decode(bytes: &[u8]) -> Result<T, ProtobufErr> {
+        Ok(T::decode(bytes)?)
+    }
+    fn encode(message: &T) -> Vec<u8> {
+        message.encode_to_vec()
+    }
+    fn unexpected_content_type(content_type: &str) -> ProtobufErr {
+        ProtobufErr::UnexpectedContentType(content_type.to_string())
+    }
+}
+
+impl ApiRequestBuilder {
+    /// protobuf-encoded request body
+    pub fn protobuf<T: prost::Message>(self, message: &T) -> Self {
+        self.header(CONTENT_TYPE, PROTOBUF)
+            .body(message.encode_to_vec())
+    }
+}
+
+pub trait ReceiveProtobuf {
+    fn recv_protobuf<Ok, ErrResp>(
+        self,
+    ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, ProtobufFormat>>>
+    where
+        Ok: prost::Message + Default,
+        ErrResp: prost::Message + Default;
+}
+impl<T: ToRequestClient> ReceiveProtobuf for T {
+    fn recv_protobuf<Ok, ErrResp>(
+        self,
+    ) -> impl Future<Output = Result<Ok, ClientErr<ErrResp, ProtobufFormat>>>
+    where
+        Ok: prost::Message + Default,
+        ErrResp: prost::Message + Default,
+    {
+        self.recv_binary::<ProtobufFormat, Ok, ErrResp>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::{ApiMiddleware, Next};
+    use futures::future::BoxFuture;
+    use reqwest::header::ACCEPT;
+    use reqwest::{Request, Response};
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Pet {
+        #[prost(uint64, tag = "1")]
+        id: u64,
+        #[prost(string, tag = "2")]
+        name: String,
+    }
+    #[derive(Clone, PartialEq, prost::Message, thiserror::Error)]
+    #[error("{code}: {message}")]
+    struct Status {
+        #[prost(int32, tag = "1")]
+        code: i32,
+        #[prost(string, tag = "2")]
+        message: String,
+    }
+
+    /// echoes protobuf bodies back, like a legacy JSON-only server when `x-legacy` is set,
+    /// or a gateway failing in html when `x-gateway-down` is
+    struct EchoProtobuf;
+    impl ApiMiddleware for EchoProtobuf {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let accepts_protobuf = request.headers().get(ACCEPT).is_some_and(|v| v == PROTOBUF)
+                && !request.headers().contains_key("x-legacy");
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default()
+                .to_vec();
+            let gateway_down = request.headers().contains_key("x-gateway-down");
+            let response = match accepts_protobuf {
+                _ if gateway_down => http::Response::builder()
+                    .status(502)
+                    .header(CONTENT_TYPE, "text/html")
+                    .body(b"<html>502 Bad Gateway</html>".to_vec()),
+                true => http::Response::builder()
+                    .header(CONTENT_TYPE, PROTOBUF)
+                    .body(body),
+                false => http::Response::builder()
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(b"{}".to_vec()),
+            };
+            Box::pin(async move {
+                let response = response.map_err(|e| ExecuteErr::Middleware(e.into()))?;
+                Ok(Response::from(response))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_roundtrip() -> anyhow::Result<()> {
+        let pet = Pet {
+            id: 7,
+            name: "rex".to_string(),
+        };
+        let request = || {
+            ApiRequestBuilder::new(reqwest::Client::new().post("http://localhost/pet"), vec![])
+                .with_middleware(EchoProtobuf)
+                .protobuf(&pet)
+        };
+
+        let got: Pet = request().recv_protobuf::<_, Status>().await?;
+        assert_eq!(got, pet);
+
+        let legacy = request()
+            .header("x-legacy", "1")
+            .recv_protobuf::<Pet, Status>()
+            .await;
+        assert!(matches!(
+            legacy,
+            Err(ClientErr::DeserializeError {
+                deserialize_error: ProtobufErr::UnexpectedContentType(_),
+                ..
+            })
+        ));
+
+        let gateway = request()
+            .header("x-gateway-down", "1")
+            .recv_protobuf::<Pet, Status>()
+            .await;
+        assert!(
+            matches!(
+                gateway,
+                Err(ClientErr::UnparsedErrorResponse { status, .. }) if status == 502
+            ),
+            "{gateway:?}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! `serialize_with` helpers for query structs passed to `.query(&params)`.
+//! `Option` fields set to None are already left out by serde_urlencoded.
+//!
+//! ```text
+//! #[derive(Serialize)]
+//! struct ListPets {
+//!     status: Option<String>,
+//!     #[serde(serialize_with = "query::comma_separated")]
+//!     tags: Vec<String>,
+//!     #[serde(serialize_with = "query::rfc3339")]
+//!     updated_since: Option<SystemTime>,
+//! }
+//! ```
+use crate::datetime::UtcDateTime;
+use serde::Serializer;
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `tags=a,b,c`, an empty list leaves the param out
+pub fn comma_separated<I, S>(items: I, serializer: S) -> Result<S::Ok, S::Error>
+where
+    I: IntoIterator,
+    I::Item: Display,
+    S: Serializer,
+{
+    let joined = items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    match joined.is_empty() {
+        true => serializer.serialize_none(),
+        false => serializer.serialize_str(&joined),
+    }
+}
+
+/// SystemTime or Option<SystemTime>, None leaves the param out
+pub trait QueryDate {
+    fn as_system_time(&self) -> Option<SystemTime>;
+}
+impl QueryDate for SystemTime {
+    fn as_system_time(&self) -> Option<SystemTime> {
+        Some(*self)
+    }
+}
+impl QueryDate for Option<SystemTime> {
+    fn as_system_time(&self) -> Option<SystemTime> {
+        *self
+    }
+}
+
+/// `2024-01-31T23:59:00Z`
+pub fn rfc3339<D: QueryDate, S: Serializer>(date: &D, serializer: S) -> Result<S::Ok, S::Error> {
+    match date
+        .as_system_time()
+        .and_then(UtcDateTime::from_system_time)
+    {
+        Some(date) => serializer.serialize_str(&date.rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// seconds since the unix epoch
+pub fn unix_secs<D: QueryDate, S: Serializer>(date: &D, serializer: S) -> Result<S::Ok, S::Error> {
+    let secs = date
+        .as_system_time()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+    match secs {
+        Some(secs) => serializer.serialize_u64(secs.as_secs()),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ApiRequestBuilder, ToRequestClient};
+    use serde::Serialize;
+    use std::time::Duration;
+
+    #[derive(Serialize, Default)]
+    struct ListPets {
+        status: Option<&'static str>,
+        limit: Option<u32>,
+        #[serde(serialize_with = "comma_separated")]
+        tags: Vec<&'static str>,
+        #[serde(serialize_with = "comma_separated")]
+        ids: Vec<u64>,
+        #[serde(serialize_with = "rfc3339")]
+        updated_since: Option<SystemTime>,
+        #[serde(serialize_with = "unix_secs")]
+        before: Option<SystemTime>,
+    }
+
+    #[test]
+    fn test_query_struct() -> anyhow::Result<()> {
+        let query = ListPets {
+            status: Some("available"),
+            tags: vec!["dog", "small"],
+            updated_since: Some(UNIX_EPOCH + Duration::from_secs(1440938160)),
+            ..Default::default()
+        };
+        let request =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+                .query(&query);
+        let request = ToRequestClient::try_into(request)?.request;
+        assert_eq!(
+            request.url().query(),
+            Some("status=available&tags=dog%2Csmall&updated_since=2015-08-30T12%3A36%3A00Z")
+        );
+        Ok(())
+    }
+}
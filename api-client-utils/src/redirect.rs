@@ -0,0 +1,221 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use futures::future::BoxFuture;
+use reqwest::header::{
+    HeaderName, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION, PROXY_AUTHORIZATION,
+};
+use reqwest::{Method, Request, Response, StatusCode, Url};
+
+/// Urls a response was redirected through, oldest first, surfaced as `RespContext::redirects`
+#[derive(Debug, Clone, Default)]
+pub struct RedirectChain(pub Vec<Url>);
+
+/// Middleware following redirects itself, so every hop is checked: at most `max_redirects`,
+/// cross-origin hops can be refused, and credentials are dropped when the origin changes.
+/// Register it last, and give the client a reqwest::Client that doesn't follow redirects (see `http_client()`),
+/// otherwise reqwest follows them before this middleware sees them.
+/// `ApiClientBuilder::redirects` does both.
+#[derive(Debug, Clone)]
+pub struct RedirectPolicy {
+    pub max_redirects: usize,
+    pub allow_cross_origin: bool,
+    /// removed from requests redirected to another origin
+    pub sensitive_headers: Vec<HeaderName>,
+}
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: 10,
+            allow_cross_origin: true,
+            sensitive_headers: vec![AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION],
+        }
+    }
+}
+impl RedirectPolicy {
+    pub fn new(max_redirects: usize) -> Self {
+        Self {
+            max_redirects,
+            ..Default::default()
+        }
+    }
+    pub fn same_origin_only(self) -> Self {
+        Self {
+            allow_cross_origin: false,
+            ..self
+        }
+    }
+    /// e.g. `x-api-key`
+    pub fn sensitive_header(mut self, name: HeaderName) -> Self {
+        self.sensitive_headers.push(name);
+        self
+    }
+    /// a reqwest::Client leaving redirects to this policy
+    pub fn http_client() -> reqwest::Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+    }
+
+    /// the request to send for a redirect response, None if it isn't one
+    fn next_request(
+        &self,
+        request: &Request,
+        response: &Response,
+    ) -> Result<Option<Request>, ExecuteErr> {
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|l| l.to_str().ok());
+        let (true, Some(location)) = (status.is_redirection(), location) else {
+            return Ok(None);
+        };
+        let url = request
+            .url()
+            .join(location)
+            .map_err(|e| ExecuteErr::Middleware(e.into()))?;
+
+        let same_origin = url.origin() == request.url().origin();
+        if !same_origin && !self.allow_cross_origin {
+            return Err(ExecuteErr::Middleware(anyhow::anyhow!(
+                "refused cross-origin redirect from {} to {url}",
+                request.url()
+            )));
+        }
+        // 303, and 301/302 after a POST, turn into a GET without body, like browsers do
+        let to_get = status == StatusCode::SEE_OTHER
+            || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+                && request.method() == Method::POST);
+        let mut next = match to_get {
+            true => Request::new(Method::GET, url),
+            false => {
+                let mut next = request.try_clone().ok_or_else(|| {
+                    ExecuteErr::Middleware(anyhow::anyhow!(
+                        "can't redirect a streaming request body"
+                    ))
+                })?;
+                *next.url_mut() = url;
+                next
+            }
+        };
+        if to_get {
+            *next.headers_mut() = request.headers().clone();
+            *next.timeout_mut() = request.timeout().copied();
+            next.headers_mut().remove(CONTENT_TYPE);
+            next.headers_mut().remove(CONTENT_LENGTH);
+        }
+        if !same_origin {
+            for name in &self.sensitive_headers {
+                next.headers_mut().remove(name);
+            }
+        }
+        Ok(Some(next))
+    }
+
+    async fn follow(&self, request: Request, next: Next<'_>) -> Result<Response, ExecuteErr> {
+        let mut chain = vec![];
+        let mut request = request;
+        loop {
+            let spare = request.try_clone();
+            let mut response = next.run(request).await?;
+            let redirect = match &spare {
+                Some(sent) => self.next_request(sent, &response)?,
+                None => None,
+            };
+            let (Some(redirect), Some(sent)) = (redirect, spare) else {
+                if !chain.is_empty() {
+                    response.extensions_mut().insert(RedirectChain(chain));
+                }
+                return Ok(response);
+            };
+            if chain.len() >= self.max_redirects {
+                return Err(ExecuteErr::Middleware(anyhow::anyhow!(
+                    "too many redirects, gave up after {}",
+                    sent.url()
+                )));
+            }
+            chain.push(sent.url().clone());
+            request = redirect;
+        }
+    }
+}
+impl ApiMiddleware for RedirectPolicy {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(self.follow(request, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::response_from_parts;
+    use crate::prelude::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    /// `/old` moved to `/new`, `/away` to another host; 200s tell whether credentials came along
+    struct MovingApi;
+    impl ApiMiddleware for MovingApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let mut headers = HeaderMap::new();
+            let (status, body) = match request.url().path() {
+                "/old" => (StatusCode::MOVED_PERMANENTLY, Some("/new")),
+                "/away" => (StatusCode::FOUND, Some("http://elsewhere.test/landing")),
+                "/loop" => (StatusCode::FOUND, Some("/loop")),
+                _ => (StatusCode::OK, None),
+            };
+            let body = match body {
+                Some(location) => {
+                    headers.insert(LOCATION, HeaderValue::from_static(location));
+                    "null".to_string()
+                }
+                None => request.headers().contains_key(AUTHORIZATION).to_string(),
+            };
+            Box::pin(async move { Ok(response_from_parts(status, headers, body)) })
+        }
+    }
+
+    fn call(path: &str, policy: RedirectPolicy) -> ApiRequestBuilder {
+        ApiRequestBuilder::new(
+            reqwest::Client::new().get(format!("http://localhost{path}")),
+            vec![],
+        )
+        .bearer_auth("secret")
+        .with_middleware(policy)
+        .with_middleware(MovingApi)
+    }
+
+    #[tokio::test]
+    async fn test_redirects() -> anyhow::Result<()> {
+        let moved = ReceiveResp::<JsonFormat>::partial_expect::<bool, serde_json::Value>(call(
+            "/old",
+            RedirectPolicy::default(),
+        ))
+        .await?;
+        assert!(moved.ok_body, "same origin keeps credentials");
+        let chain: Vec<&str> = moved.context.redirects.iter().map(Url::as_str).collect();
+        assert_eq!(chain, vec!["http://localhost/old"]);
+
+        let away: bool = call("/away", RedirectPolicy::default())
+            .recv_json::<_, serde_json::Value>()
+            .await?;
+        assert!(!away, "credentials aren't sent to another origin");
+
+        let refused = call("/away", RedirectPolicy::default().same_origin_only())
+            .recv_json::<bool, serde_json::Value>()
+            .await;
+        assert!(matches!(refused, Err(ClientErr::Middleware { .. })));
+        let looping = call("/loop", RedirectPolicy::new(3))
+            .recv_json::<bool, serde_json::Value>()
+            .await;
+        assert!(matches!(looping, Err(ClientErr::Middleware { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use reqwest::header::HeaderName;
+use std::future::Future;
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Run `fut` with `request_id` sent on every request it makes (with `ClientConfig::request_ids()`),
+/// e.g. the id of the incoming request a handler is serving, to correlate the calls it causes.
+pub async fn with_request_id<Fut: Future>(request_id: impl Into<String>, fut: Fut) -> Fut::Output {
+    REQUEST_ID.scope(request_id.into(), fut).await
+}
+
+/// the id set by an enclosing `with_request_id`, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(String::clone).ok()
+}
+
+/// the ambient id, or a new random one
+pub(crate) fn request_id() -> String {
+    current_request_id().unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::StubResponse;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_request_id() -> anyhow::Result<()> {
+        let request = || {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+                .with_config(ClientConfig::default().request_ids())
+                .with_middleware(StubResponse::new(200, "{}"))
+        };
+
+        let generated = request().expect_success::<serde_json::Value>().await?;
+        let generated = generated.request_id.unwrap_or_default();
+        assert_eq!(generated.len(), 36, "a uuid: {generated}");
+
+        let propagated = with_request_id("incoming-42", async {
+            request().expect_success::<serde_json::Value>().await
+        })
+        .await?;
+        assert_eq!(propagated.request_id.as_deref(), Some("incoming-42"));
+        Ok(())
+    }
+}
@@ -0,0 +1,470 @@
+use crate::datetime::UtcDateTime;
+use crate::error::{ErrorKind, ExecuteErr};
+use crate::middleware::Next;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Request, Response, StatusCode};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// `Retry-After` as a delay from now, sent either in seconds or as an HTTP date (zero once passed)
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = UtcDateTime::parse_http_date(value)?.to_system_time()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// What a finished attempt produced, passed to the `retry_on` predicate
+#[derive(Debug)]
+pub enum RetryCause<'a> {
+    Status(StatusCode),
+    Err(&'a ExecuteErr),
+}
+impl RetryCause<'_> {
+    /// 5xx responses and connection/timeout errors
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RetryCause::Status(status) => status.is_server_error(),
+            RetryCause::Err(ExecuteErr::Request(e)) => e.is_connect() || e.is_timeout(),
+            RetryCause::Err(ExecuteErr::Backend(e)) => {
+                matches!(e.kind, ErrorKind::Connect | ErrorKind::Timeout)
+            }
+            RetryCause::Err(_) => false,
+        }
+    }
+}
+
+/// Caps retries at a share of the requests sent over a sliding window, so a struggling upstream
+/// sees at most `1 + max_ratio` times its normal load instead of `max_attempts` times.
+/// Clones share their counts: give the same budget to every request of a client through its RetryPolicy.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    pub max_ratio: f64,
+    pub window: Duration,
+    /// retries always allowed per window, so clients sending few requests still get some
+    pub min_retries: u32,
+    slots: Arc<Mutex<VecDeque<BudgetSlot>>>,
+}
+/// requests and retries started during a tenth of the window
+#[derive(Debug)]
+struct BudgetSlot {
+    start: Instant,
+    requests: u32,
+    retries: u32,
+}
+impl RetryBudget {
+    /// e.g. 0.1 for retries to be at most 10% of requests, over 10s and with 10 retries allowed anyway
+    pub fn new(max_ratio: f64) -> Self {
+        Self {
+            max_ratio: max_ratio.max(0.0),
+            window: Duration::from_secs(10),
+            min_retries: 10,
+            slots: Default::default(),
+        }
+    }
+    pub fn window(self, window: Duration) -> Self {
+        Self { window, ..self }
+    }
+    pub fn min_retries(self, min_retries: u32) -> Self {
+        Self {
+            min_retries,
+            ..self
+        }
+    }
+
+    /// count a first attempt
+    pub fn record_request(&self) {
+        self.update(|slot| slot.requests += 1);
+    }
+    /// count a retry if the budget allows it, false when it's spent
+    pub fn try_retry(&self) -> bool {
+        let (requests, retries) = self.totals();
+        let allowed = self.min_retries as f64 + self.max_ratio * requests as f64;
+        let can_retry = (retries as f64) < allowed;
+        if can_retry {
+            self.update(|slot| slot.retries += 1);
+        }
+        can_retry
+    }
+    /// requests and retries in the current window
+    pub fn totals(&self) -> (u32, u32) {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut slots, Instant::now());
+        let requests = slots.iter().map(|slot| slot.requests).sum();
+        let retries = slots.iter().map(|slot| slot.retries).sum();
+        (requests, retries)
+    }
+
+    fn update(&self, count: impl FnOnce(&mut BudgetSlot)) {
+        let now = Instant::now();
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut slots, now);
+        let slot_len = self.window / 10;
+        match slots.back_mut() {
+            Some(slot) if now.duration_since(slot.start) < slot_len => count(slot),
+            _ => {
+                let mut slot = BudgetSlot {
+                    start: now,
+                    requests: 0,
+                    retries: 0,
+                };
+                count(&mut slot);
+                slots.push_back(slot);
+            }
+        }
+    }
+    fn expire(&self, slots: &mut VecDeque<BudgetSlot>, now: Instant) {
+        while slots
+            .front()
+            .is_some_and(|slot| now.duration_since(slot.start) >= self.window)
+        {
+            slots.pop_front();
+        }
+    }
+}
+
+/// Response extension with how many attempts the retry policy made, read into `RespContext::timing`
+#[derive(Debug, Clone, Copy)]
+pub struct Attempts(pub u32);
+
+pub type RetryPredicate = Arc<dyn Fn(&RetryCause) -> bool + Send + Sync>;
+
+/// Re-issues a request with exponential backoff while `retry_on` says the attempt failed transiently.
+/// Requests with a streaming body can't be cloned and are never retried.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// fraction of each delay that is randomized, 0.0 = none, 1.0 = full jitter
+    pub jitter: f64,
+    pub retry_on: RetryPredicate,
+    /// wait for `Retry-After` instead of the backoff, when no longer than this.
+    /// 429 responses carrying it are retried too. None to ignore the header.
+    pub max_retry_after: Option<Duration>,
+    /// client-wide cap on retries, on top of max_attempts
+    pub budget: Option<RetryBudget>,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_on: Arc::new(|cause: &RetryCause| cause.is_transient()),
+            max_retry_after: None,
+            budget: None,
+        }
+    }
+}
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("max_retry_after", &self.max_retry_after)
+            .field("budget", &self.budget)
+            .finish_non_exhaustive()
+    }
+}
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+    pub fn backoff(self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            initial_backoff,
+            max_backoff,
+            ..self
+        }
+    }
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        Self { multiplier, ..self }
+    }
+    pub fn jitter(self, jitter: f64) -> Self {
+        Self {
+            jitter: jitter.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+    pub fn retry_on(self, retry_on: impl Fn(&RetryCause) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            retry_on: Arc::new(retry_on),
+            ..self
+        }
+    }
+
+    /// A longer `Retry-After` isn't waited for: the response is returned as is
+    pub fn respect_retry_after(self, max_wait: Duration) -> Self {
+        Self {
+            max_retry_after: Some(max_wait),
+            ..self
+        }
+    }
+
+    /// When the budget is spent, failed attempts are returned as is instead of retried
+    pub fn budget(self, budget: RetryBudget) -> Self {
+        Self {
+            budget: Some(budget),
+            ..self
+        }
+    }
+
+    /// delay to wait after the given (1-based) failed attempt, before jitter
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+    pub(crate) fn jittered(&self, delay: Duration) -> Duration {
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+        let random_part = rand::thread_rng().gen_range(0.0..=self.jitter);
+        delay.mul_f64(1.0 - random_part)
+    }
+
+    pub fn should_retry(&self, result: &Result<Response, ExecuteErr>) -> bool {
+        match result {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                self.requested_wait(result).is_some()
+                    || (self.retry_on)(&RetryCause::Status(response.status()))
+            }
+            Ok(response) => (self.retry_on)(&RetryCause::Status(response.status())),
+            Err(err) => (self.retry_on)(&RetryCause::Err(err)),
+        }
+    }
+    /// the response's Retry-After, if this policy honors it
+    fn requested_wait(&self, result: &Result<Response, ExecuteErr>) -> Option<Duration> {
+        self.max_retry_after?;
+        retry_after(result.as_ref().ok()?.headers())
+    }
+
+    pub async fn execute(&self, request: Request, next: Next<'_>) -> Result<Response, ExecuteErr> {
+        let mut attempts = 0;
+        let mut result = self.run_attempts(request, next, &mut attempts).await;
+        if let Ok(response) = &mut result {
+            response.extensions_mut().insert(Attempts(attempts));
+        }
+        result
+    }
+    /// `attempt` counts as it goes, for execute to tell how many were made
+    async fn run_attempts(
+        &self,
+        mut request: Request,
+        next: Next<'_>,
+        attempt: &mut u32,
+    ) -> Result<Response, ExecuteErr> {
+        *attempt = 1;
+        if let Some(budget) = &self.budget {
+            budget.record_request();
+        }
+        loop {
+            let spare = match *attempt < self.max_attempts {
+                true => request.try_clone(),
+                false => None,
+            };
+            let result = next.run(request).await;
+
+            let Some(spare) = spare else { return result };
+            if !self.should_retry(&result) {
+                return result;
+            }
+            let delay = match (self.requested_wait(&result), self.max_retry_after) {
+                (Some(wait), Some(max_wait)) if wait > max_wait => return result,
+                (Some(wait), _) => wait,
+                (None, _) => self.jittered(self.delay_after(*attempt)),
+            };
+            if !self.budget.as_ref().is_none_or(RetryBudget::try_retry) {
+                return result;
+            }
+            tokio::time::sleep(delay).await;
+            request = spare;
+            *attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::response_from_parts;
+    use crate::middleware::tests::stub_response;
+    use crate::prelude::*;
+    use crate::ApiRequestBuilder;
+    use futures::future::BoxFuture;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// answers 503 until `failures` attempts have been made
+    struct Flaky {
+        failures: u32,
+        attempts: Arc<AtomicU32>,
+    }
+    impl ApiMiddleware for Flaky {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            let status = match attempt <= self.failures {
+                true => 503,
+                false => 200,
+            };
+            Box::pin(async move { stub_response(status, "{}") })
+        }
+    }
+
+    fn flaky_request(failures: u32, attempts: Arc<AtomicU32>) -> ApiRequestBuilder {
+        ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/flaky"),
+            vec![Arc::new(Flaky { failures, attempts })],
+        )
+    }
+
+    #[test]
+    fn test_delay_after() {
+        let policy = RetryPolicy::new(5)
+            .backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .jitter(0.0);
+        assert_eq!(policy.delay_after(1), Duration::from_millis(100));
+        assert_eq!(policy.delay_after(2), Duration::from_millis(200));
+        assert_eq!(policy.delay_after(3), Duration::from_millis(300));
+        assert_eq!(policy.delay_after(4), Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_status() -> anyhow::Result<()> {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RetryPolicy::new(3).backoff(Duration::ZERO, Duration::ZERO);
+
+        flaky_request(2, attempts.clone())
+            .retry(policy)
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await?;
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_retry_after() -> anyhow::Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "Sun, 06 Nov 1994 08:49:37 GMT".parse()?);
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        headers.insert(RETRY_AFTER, "120".parse()?);
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let rate_limited = |retry_after, max_wait| {
+            ApiRequestBuilder::new(
+                reqwest::Client::new().get("http://localhost/limited"),
+                vec![Arc::new(RateLimited {
+                    retry_after,
+                    attempts: attempts.clone(),
+                })],
+            )
+            .retry(RetryPolicy::new(3).respect_retry_after(max_wait))
+            .recv_json::<serde_json::Value, serde_json::Value>()
+        };
+
+        let err = rate_limited("120", Duration::from_secs(5))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            attempts.swap(0, Ordering::SeqCst),
+            1,
+            "2min is over the max wait"
+        );
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(120)));
+
+        rate_limited("0", Duration::from_secs(5)).await?;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    /// answers 429 with `retry_after` to the first attempt
+    struct RateLimited {
+        retry_after: &'static str,
+        attempts: Arc<AtomicU32>,
+    }
+    impl ApiMiddleware for RateLimited {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            let mut headers = HeaderMap::new();
+            let status = match attempt {
+                1 => {
+                    let retry_after = reqwest::header::HeaderValue::from_static(self.retry_after);
+                    headers.insert(RETRY_AFTER, retry_after);
+                    StatusCode::TOO_MANY_REQUESTS
+                }
+                _ => StatusCode::OK,
+            };
+            Box::pin(async move { Ok(response_from_parts(status, headers, "{}")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget() -> anyhow::Result<()> {
+        let budget = RetryBudget::new(0.2).min_retries(1);
+        for _ in 0..9 {
+            budget.record_request();
+        }
+        let allowed = (0..5).filter(|_| budget.try_retry()).count();
+        assert_eq!(allowed, 3, "under 1 + 20% of 9");
+        assert_eq!(budget.totals(), (9, 3));
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RetryPolicy::new(3)
+            .backoff(Duration::ZERO, Duration::ZERO)
+            .budget(budget.clone());
+        let result = flaky_request(5, attempts.clone())
+            .retry(policy)
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1, "budget spent");
+        assert_eq!(budget.totals(), (10, 3));
+
+        let expired = RetryBudget::new(0.0)
+            .min_retries(1)
+            .window(Duration::from_millis(20));
+        assert!(expired.try_retry());
+        assert!(!expired.try_retry());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(expired.try_retry());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() -> anyhow::Result<()> {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RetryPolicy::new(2).backoff(Duration::ZERO, Duration::ZERO);
+
+        let result = flaky_request(5, attempts.clone())
+            .retry(policy)
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await;
+        let err = result.err().unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        let timing = err.context().map(|context| context.timing);
+        assert_eq!(timing.map(|timing| timing.attempts), Some(2));
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use crate::error::ExecuteErr;
+use crate::middleware::ApiMiddleware;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::Request;
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Computes and attaches a signature to a request right before it is sent
+pub trait RequestSigner: Send + Sync {
+    fn sign(&self, request: &mut Request) -> anyhow::Result<()>;
+}
+
+/// Middleware running a RequestSigner on every request.
+/// Register it last so the signature covers headers set by earlier middlewares.
+pub struct Signed<S: RequestSigner>(pub S);
+impl<S: RequestSigner> ApiMiddleware for Signed<S> {
+    fn on_request(&self, request: &mut Request) -> Result<(), ExecuteErr> {
+        self.0.sign(request).map_err(ExecuteErr::Middleware)
+    }
+}
+
+/// The parts of a request that get signed
+#[derive(Debug)]
+pub struct SigningInput<'a> {
+    pub timestamp: &'a str,
+    pub method: &'a str,
+    /// path and query, e.g. `/v2/orders?limit=10`
+    pub path: &'a str,
+    pub body: &'a [u8],
+}
+impl SigningInput<'_> {
+    /// timestamp + METHOD + path + body, the most common layout
+    pub fn concat(&self) -> Vec<u8> {
+        let mut message = format!("{}{}{}", self.timestamp, self.method, self.path).into_bytes();
+        message.extend_from_slice(self.body);
+        message
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SignatureEncoding {
+    Hex,
+    Base64,
+}
+
+/// HMAC-SHA256 over timestamp+method+path+body, written to `signature_header` along with `timestamp_header`
+pub struct HmacSigner {
+    key: Vec<u8>,
+    pub signature_header: HeaderName,
+    pub timestamp_header: HeaderName,
+    pub encoding: SignatureEncoding,
+    pub message: fn(&SigningInput) -> Vec<u8>,
+    /// unix timestamp in milliseconds by default
+    pub timestamp: fn() -> String,
+}
+impl HmacSigner {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            signature_header: HeaderName::from_static("x-signature"),
+            timestamp_header: HeaderName::from_static("x-timestamp"),
+            encoding: SignatureEncoding::Hex,
+            message: |input| input.concat(),
+            timestamp: unix_millis,
+        }
+    }
+    pub fn headers(self, signature_header: HeaderName, timestamp_header: HeaderName) -> Self {
+        Self {
+            signature_header,
+            timestamp_header,
+            ..self
+        }
+    }
+    pub fn encoding(self, encoding: SignatureEncoding) -> Self {
+        Self { encoding, ..self }
+    }
+    pub fn message(self, message: fn(&SigningInput) -> Vec<u8>) -> Self {
+        Self { message, ..self }
+    }
+    pub fn timestamp(self, timestamp: fn() -> String) -> Self {
+        Self { timestamp, ..self }
+    }
+
+    pub fn signature(&self, input: &SigningInput) -> anyhow::Result<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key)?;
+        mac.update(&(self.message)(input));
+        let digest = mac.finalize().into_bytes();
+        Ok(match self.encoding {
+            SignatureEncoding::Hex => hex::encode(digest),
+            SignatureEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(digest),
+        })
+    }
+}
+impl RequestSigner for HmacSigner {
+    fn sign(&self, request: &mut Request) -> anyhow::Result<()> {
+        let timestamp = (self.timestamp)();
+        let url = request.url();
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let body = match request.body() {
+            Some(body) => body
+                .as_bytes()
+                .ok_or_else(|| anyhow::anyhow!("can't sign a streaming request body"))?,
+            None => &[],
+        };
+        let signature = self.signature(&SigningInput {
+            timestamp: &timestamp,
+            method: request.method().as_str(),
+            path: &path,
+            body,
+        })?;
+
+        let headers = request.headers_mut();
+        headers.insert(
+            self.timestamp_header.clone(),
+            HeaderValue::from_str(&timestamp)?,
+        );
+        headers.insert(
+            self.signature_header.clone(),
+            HeaderValue::from_str(&signature)?,
+        );
+        Ok(())
+    }
+}
+
+pub fn unix_millis() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_millis().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_signature() -> anyhow::Result<()> {
+        let signer = HmacSigner::new("secret").timestamp(|| "1700000000000".to_string());
+        let mut request = reqwest::Client::new()
+            .post("http://localhost/v2/orders?limit=10")
+            .body(r#"{"qty":1}"#)
+            .build()?;
+        signer.sign(&mut request)?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret")?;
+        mac.update(br#"1700000000000POST/v2/orders?limit=10{"qty":1}"#);
+        let expected = hex::encode(mac.finalize().into_bytes());
+
+        assert_eq!(request.headers()["x-signature"], expected.as_str());
+        assert_eq!(request.headers()["x-timestamp"], "1700000000000");
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{cache_key, response_from_parts, ApiMiddleware, Next, ResponseParts};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Request, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// the response shared with every waiting caller, errors are only shared as their message
+type Outcome = Result<(StatusCode, HeaderMap, Bytes), String>;
+
+/// Middleware folding identical GETs issued concurrently into one upstream request,
+/// every caller gets a copy of its response. Requests are identical when url, Accept and Authorization match,
+/// so register it after the middlewares setting credentials.
+/// If the request being waited on is cancelled, the callers waiting on it each send their own.
+#[derive(Default)]
+pub struct SingleFlight {
+    in_flight: Mutex<HashMap<String, watch::Receiver<Option<Outcome>>>>,
+}
+impl SingleFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, watch::Receiver<Option<Outcome>>>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn serve(&self, request: Request, next: Next<'_>) -> Result<Response, ExecuteErr> {
+        let key = cache_key(&request);
+        let joined = {
+            let mut in_flight = self.lock();
+            match in_flight.get(&key) {
+                Some(waiting) => Err(waiting.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    in_flight.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+        let sender = match joined {
+            Ok(sender) => sender,
+            Err(mut waiting) => {
+                let outcome = waiting
+                    .wait_for(Option::is_some)
+                    .await
+                    .map(|outcome| outcome.clone());
+                return match outcome {
+                    Ok(Some(Ok((status, headers, body)))) => {
+                        Ok(response_from_parts(status, headers, body))
+                    }
+                    Ok(Some(Err(e))) => Err(ExecuteErr::Middleware(anyhow::anyhow!(
+                        "shared request failed: {e}"
+                    ))),
+                    Ok(None) | Err(_) => next.run(request).await,
+                };
+            }
+        };
+
+        let leaving = Leave { flight: self, key };
+        let result: Result<_, ExecuteErr> = async {
+            let mut response = next.run(request).await?;
+            let parts = ResponseParts::take(&mut response);
+            let body = response.bytes().await.map_err(ExecuteErr::Request)?;
+            Ok((parts, body))
+        }
+        .await;
+        // callers arriving from now on start a new request rather than get this one
+        drop(leaving);
+        sender.send_replace(Some(match &result {
+            Ok((parts, body)) => Ok((parts.status, parts.headers.clone(), body.clone())),
+            Err(e) => Err(e.to_string()),
+        }));
+        result.map(|(parts, body)| parts.with_body(body))
+    }
+}
+impl ApiMiddleware for SingleFlight {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        match request.method() == Method::GET {
+            true => Box::pin(self.serve(request, next)),
+            false => next.run(request),
+        }
+    }
+}
+
+/// removes the in-flight entry, also when the leading request is cancelled
+struct Leave<'a> {
+    flight: &'a SingleFlight,
+    key: String,
+}
+impl Drop for Leave<'_> {
+    fn drop(&mut self) {
+        self.flight.lock().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// slow upstream answering with its call count
+    #[derive(Default)]
+    struct SlowApi {
+        calls: AtomicU32,
+    }
+    impl ApiMiddleware for SlowApi {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(response_from_parts(
+                    StatusCode::OK,
+                    HeaderMap::new(),
+                    n.to_string(),
+                ))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_gets_share_one_request() -> anyhow::Result<()> {
+        let flight = Arc::new(SingleFlight::new());
+        let api = Arc::new(SlowApi::default());
+        let client = reqwest::Client::new();
+        let call = |path: &str| {
+            ApiRequestBuilder::new(client.get(format!("http://localhost{path}")), vec![])
+                .with_middleware(flight.clone())
+                .with_middleware(api.clone())
+                .recv_json::<u32, serde_json::Value>()
+        };
+
+        let (a, b, c) = tokio::join!(call("/users"), call("/users"), call("/users"));
+        assert_eq!((a?, b?, c?), (1, 1, 1));
+        assert_eq!(call("/users").await?, 2, "finished requests aren't reused");
+
+        let (users, orders) = tokio::join!(call("/users"), call("/orders"));
+        assert_ne!(users?, orders?);
+        assert_eq!(api.calls.load(Ordering::SeqCst), 4);
+        Ok(())
+    }
+}
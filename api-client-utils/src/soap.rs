@@ -0,0 +1,259 @@
+use crate::error::ClientErr;
+use crate::serialization_formats::{ApiFormat, SerialFormat};
+use crate::{ApiClient, ReceiveResp, RequestClient, ToRequestClient};
+use reqwest::RequestBuilder;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+const ENVELOPE_NS: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+
+/// SOAP 1.1 bodies: `from_str` reads the element inside `<soap:Body>`, a Fault for error bodies
+#[derive(Debug)]
+pub struct SoapFormat;
+impl SerialFormat for SoapFormat {
+    type Error = serde_xml_rs::Error;
+    fn from_str<T: for<'a> Deserialize<'a>>(input: &str) -> Result<T, Self::Error> {
+        let envelope: Envelope<T> = serde_xml_rs::from_str(input)?;
+        Ok(envelope.body.content)
+    }
+}
+impl ApiFormat for SoapFormat {
+    fn with_accept_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Accept", "text/xml")
+    }
+    fn with_content_type_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Content-Type", "text/xml; charset=utf-8")
+    }
+}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    #[serde(rename = "Body")]
+    body: Body<T>,
+}
+#[derive(Deserialize)]
+struct Body<T> {
+    #[serde(rename = "$value")]
+    content: T,
+}
+
+/// `<soap:Fault>`, returned as ClientErr::ErrorResponse. `Detail` is the service's own
+/// fault payload, ignored by default.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SoapFault<Detail = IgnoredAny> {
+    /// `soap:Client`, `soap:Server`...
+    #[serde(rename = "faultcode")]
+    pub code: String,
+    #[serde(rename = "faultstring")]
+    pub message: String,
+    #[serde(rename = "faultactor")]
+    pub actor: Option<String>,
+    pub detail: Option<Detail>,
+}
+impl<Detail> std::fmt::Display for SoapFault<Detail> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SOAP fault {}: {}", self.code, self.message)
+    }
+}
+
+/// `body` serialized inside a SOAP 1.1 envelope, `namespace` set as its default xmlns
+pub fn to_envelope(
+    body: &impl Serialize,
+    namespace: Option<&str>,
+) -> Result<String, serde_xml_rs::Error> {
+    let body = serde_xml_rs::to_string(body)?;
+    // drop the XML declaration, only allowed at the start of the document
+    let body = match body.strip_prefix("<?xml") {
+        Some(rest) => rest.split_once("?>").map_or("", |(_, body)| body),
+        None => &body,
+    };
+    let body = match namespace {
+        Some(namespace) => with_default_namespace(body, namespace),
+        None => body.to_string(),
+    };
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{ENVELOPE_NS}"><soap:Body>{body}</soap:Body></soap:Envelope>"#
+    ))
+}
+
+/// `<GetPet>...` into `<GetPet xmlns="urn:pets">...`
+fn with_default_namespace(element: &str, namespace: &str) -> String {
+    let namespace = xml::escape::escape_str_attribute(namespace);
+    let tag_end = element
+        .find(['>', ' '])
+        .map(|end| match element[..end].ends_with('/') {
+            true => end - 1,
+            false => end,
+        })
+        .unwrap_or(element.len());
+    let (tag, rest) = element.split_at(tag_end);
+    format!(r#"{tag} xmlns="{namespace}"{rest}"#)
+}
+
+/// SOAP 1.1 on top of an `ApiClient<SoapFormat>`: `impl SoapClient for MyService {}`.
+/// Faults come back as ClientErr::ErrorResponse, whether sent with a 500 as the spec wants or with a 200.
+pub trait SoapClient: ApiClient<SoapFormat> {
+    /// endpoint path, relative to base_url
+    fn soap_path(&self) -> &str {
+        "/"
+    }
+    /// default xmlns of the operation elements, the service's target namespace
+    fn soap_namespace(&self) -> Option<&str> {
+        None
+    }
+
+    /// sends `body` with `SOAPAction: "<action>"`, reads the element of the response Body as Resp
+    fn call<Resp: DeserializeOwned, Detail: DeserializeOwned>(
+        &self,
+        action: &str,
+        body: &impl Serialize,
+    ) -> impl Future<Output = Result<Resp, ClientErr<SoapFault<Detail>, SoapFormat>>> {
+        let envelope = to_envelope(body, self.soap_namespace());
+        let request = self
+            .post(self.soap_path())
+            .header("SOAPAction", format!("\"{action}\""));
+        async move {
+            let envelope = envelope.map_err(ClientErr::middleware)?;
+            let request = SoapRequest(request.body(envelope));
+            let context =
+                ReceiveResp::<SoapFormat>::expect_success::<SoapFault<Detail>>(request).await?;
+            match SoapFormat::from_str(&context.response_text) {
+                Ok(resp) => Ok(resp),
+                Err(deserialize_error) => {
+                    let fault = SoapFormat::from_str::<SoapFault<Detail>>(&context.response_text);
+                    let context = Box::new(context);
+                    Err(match fault {
+                        Ok(err_body) => ClientErr::ErrorResponse { context, err_body },
+                        Err(_) => ClientErr::DeserializeError {
+                            context,
+                            deserialize_error,
+                        },
+                    })
+                }
+            }
+        }
+    }
+}
+
+// wrapper so that ReceiveResp<SoapFormat> doesn't make JSON calls ambiguous
+struct SoapRequest<T>(T);
+impl<T: ToRequestClient> ToRequestClient for SoapRequest<T> {
+    fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+        self.0.try_into()
+    }
+}
+impl<T: ToRequestClient> ReceiveResp<SoapFormat> for SoapRequest<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response};
+    use std::sync::Arc;
+
+    #[derive(Serialize)]
+    struct GetPet {
+        id: u32,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct GetPetResponse {
+        name: String,
+        age: u32,
+    }
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    struct PetFaultDetail {
+        reason: String,
+    }
+
+    /// knows pet 1, faults with a 500 otherwise
+    struct PetService;
+    impl ApiMiddleware for PetService {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let action = request.headers().get("SOAPAction").cloned();
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| String::from_utf8_lossy(b).into_owned())
+                .unwrap_or_default();
+            Box::pin(async move {
+                assert_eq!(
+                    action.as_ref().and_then(|a| a.to_str().ok()),
+                    Some("\"urn:pets#GetPet\"")
+                );
+                assert!(
+                    body.contains(r#"<soap:Body><GetPet xmlns="urn:pets">"#),
+                    "{body}"
+                );
+                match body.contains("<id>1</id>") {
+                    true => stub_response(
+                        200,
+                        r#"<?xml version="1.0"?>
+                        <soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"><soap:Header/><soap:Body>
+                            <m:GetPetResponse xmlns:m="urn:pets"><m:name>rex</m:name><m:age>3</m:age></m:GetPetResponse>
+                        </soap:Body></soap:Envelope>"#,
+                    ),
+                    false => stub_response(
+                        500,
+                        r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"><soap:Body>
+                            <soap:Fault><faultcode>soap:Client</faultcode><faultstring>no such pet</faultstring>
+                            <detail><reason>deleted</reason></detail></soap:Fault>
+                        </soap:Body></soap:Envelope>"#,
+                    ),
+                }
+            })
+        }
+    }
+
+    struct PetApi {
+        http_client: reqwest::Client,
+        middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    }
+    impl ApiClient<SoapFormat> for PetApi {
+        fn base_url(&self) -> &str {
+            "http://localhost/PetService.asmx"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+            &self.middlewares
+        }
+    }
+    impl SoapClient for PetApi {
+        fn soap_namespace(&self) -> Option<&str> {
+            Some("urn:pets")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_soap_call() -> anyhow::Result<()> {
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+            middlewares: vec![Arc::new(PetService)],
+        };
+
+        let pet: GetPetResponse = api
+            .call::<_, IgnoredAny>("urn:pets#GetPet", &GetPet { id: 1 })
+            .await?;
+        assert_eq!(pet.name, "rex");
+        assert_eq!(pet.age, 3);
+
+        let fault = api
+            .call::<GetPetResponse, PetFaultDetail>("urn:pets#GetPet", &GetPet { id: 2 })
+            .await
+            .try_into_err_resp(reqwest::StatusCode::INTERNAL_SERVER_ERROR)?;
+        assert_eq!(fault.code, "soap:Client");
+        assert_eq!(fault.message, "no such pet");
+        assert_eq!(fault.detail.map(|d| d.reason), Some("deleted".to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+use crate::error::ClientErr;
+use crate::retry::RetryPolicy;
+use crate::serialization_formats::SerialFormat;
+use crate::status_errors::ErrorBody;
+use crate::{ReceiveResp, RequestClient, ToRequestClient};
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use reqwest::header::{HeaderValue, ACCEPT};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// One `text/event-stream` event, with `data` deserialized
+#[derive(Debug, Clone)]
+pub struct SseEvent<T> {
+    /// `event:` field, None for the default "message" type
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub data: T,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct RawEvent {
+    event: Option<String>,
+    id: Option<String>,
+    data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies, fed chunk by chunk
+#[derive(Default)]
+struct SseParser {
+    line_buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<RawEvent> {
+        self.line_buffer.extend_from_slice(chunk);
+        let mut events = vec![];
+        while let Some(end) = self.line_buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.line_buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                events.extend(self.dispatch());
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+                "retry" => {
+                    if let Ok(millis) = value.parse() {
+                        self.retry = Some(Duration::from_millis(millis));
+                    }
+                }
+                _ => {} // comments (`: keep-alive`) and unknown fields
+            }
+        }
+        events
+    }
+
+    fn dispatch(&mut self) -> Option<RawEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(RawEvent {
+            event: event.filter(|e| e != "message"),
+            id: self.last_event_id.clone(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+struct SseConnection<ErrResp, F: SerialFormat> {
+    request: RequestClient,
+    reconnect: RetryPolicy,
+    failed_connections: u32,
+    body: Option<BoxStream<'static, reqwest::Result<Bytes>>>,
+    parser: SseParser,
+    pending: VecDeque<RawEvent>,
+    done: bool,
+    _marker: PhantomData<(ErrResp, F)>,
+}
+impl<ErrResp: ErrorBody, F: SerialFormat> SseConnection<ErrResp, F> {
+    async fn next_event<T: DeserializeOwned>(
+        &mut self,
+    ) -> Option<Result<SseEvent<T>, ClientErr<ErrResp, F>>> {
+        loop {
+            if let Some(raw) = self.pending.pop_front() {
+                self.failed_connections = 0;
+                return Some(self.deserialize(raw));
+            }
+            if self.done {
+                return None;
+            }
+            match &mut self.body {
+                Some(body) => match body.next().await {
+                    Some(Ok(chunk)) => self.pending.extend(self.parser.push(&chunk)),
+                    Some(Err(e)) => {
+                        self.body = None;
+                        if !self.may_reconnect() {
+                            self.done = true;
+                            return Some(Err(ClientErr::ReadRespBodyText(e)));
+                        }
+                    }
+                    // servers close event streams to load-balance, clients are expected to come back
+                    None => {
+                        self.body = None;
+                        self.done = !self.may_reconnect();
+                    }
+                },
+                None => {
+                    if let Err(err) = self.connect().await {
+                        self.done = true;
+                        return Some(Err(err));
+                    }
+                }
+            }
+        }
+    }
+
+    fn may_reconnect(&mut self) -> bool {
+        self.failed_connections += 1;
+        self.failed_connections < self.reconnect.max_attempts
+    }
+
+    async fn connect(&mut self) -> Result<(), ClientErr<ErrResp, F>> {
+        if self.failed_connections > 0 {
+            let delay = match self.parser.retry {
+                Some(server_delay) => server_delay,
+                None => self.reconnect.delay_after(self.failed_connections),
+            };
+            tokio::time::sleep(self.reconnect.jittered(delay)).await;
+        }
+        let mut request = self.request.try_clone().ok_or_else(|| {
+            ClientErr::middleware(anyhow::anyhow!(
+                "can't reconnect a request with a streaming body"
+            ))
+        })?;
+        if let Some(id) = &self.parser.last_event_id {
+            let id = HeaderValue::from_str(id).map_err(ClientErr::middleware)?;
+            request.request.headers_mut().insert("Last-Event-ID", id);
+        }
+        let sent = request.sent();
+        let snapshot = request.snapshot();
+
+        let result = request.execute_streamed().await;
+        if self.reconnect.should_retry(&result) && self.may_reconnect() {
+            return Ok(());
+        }
+        let response = result.map_err(|err| ClientErr::from_execute(err, snapshot))?;
+        if !response.status().is_success() {
+            return Err(ClientErr::from_error_response(sent, response).await);
+        }
+        self.body = Some(response.bytes_stream().boxed());
+        Ok(())
+    }
+
+    fn deserialize<T: DeserializeOwned>(
+        &self,
+        raw: RawEvent,
+    ) -> Result<SseEvent<T>, ClientErr<ErrResp, F>> {
+        match F::from_str(&raw.data) {
+            Ok(data) => Ok(SseEvent {
+                event: raw.event,
+                id: raw.id,
+                data,
+            }),
+            Err(deserialize_error) => Err(ClientErr::DeserializeError {
+                context: Box::new(self.request.sent().context(StatusCode::OK, raw.data)),
+                deserialize_error,
+            }),
+        }
+    }
+}
+
+pub trait ReceiveSse<F: SerialFormat>: Sized + ToRequestClient {
+    /// Keep the connection open and yield each event of a `text/event-stream` response.
+    /// Dropped connections are re-established with `Last-Event-ID`, backing off according to
+    /// the request's retry policy (or the default one), where `max_attempts` counts consecutive failed connections.
+    /// The timeout only applies until the response head: a quiet stream isn't a failed connection.
+    fn recv_sse<T: DeserializeOwned, ErrResp: ErrorBody>(
+        self,
+    ) -> impl Stream<Item = Result<SseEvent<T>, ClientErr<ErrResp, F>>> {
+        let connection = self
+            .try_into()
+            .map_err(ClientErr::BuildRequest)
+            .map(|mut request| {
+                request
+                    .request
+                    .headers_mut()
+                    .insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+                let reconnect = request.retry.take().unwrap_or_default();
+                SseConnection {
+                    request,
+                    reconnect,
+                    failed_connections: 0,
+                    body: None,
+                    parser: SseParser::default(),
+                    pending: VecDeque::new(),
+                    done: false,
+                    _marker: PhantomData,
+                }
+            });
+        stream::unfold(Some(connection), |state| async move {
+            match state? {
+                Ok(mut connection) => {
+                    let event = connection.next_event().await?;
+                    Some((event, Some(Ok(connection))))
+                }
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+}
+// events are read in the format the request receives its responses in, JSON unless wrapped
+impl<F: SerialFormat, T: ReceiveResp<F>> ReceiveSse<F> for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClientConfig;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::{stub_response, StubResponse};
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::serialization_formats::FormUrlEncodedFormat;
+    use crate::ApiRequestBuilder;
+    use futures::future::BoxFuture;
+    use futures::TryStreamExt;
+    use reqwest::{Request, Response};
+    use serde::Deserialize;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_split_chunks() {
+        let mut parser = SseParser::default();
+        let mut events = parser.push(b": keep-alive\nevent: tick\nid: 7\ndata: {\"n\"");
+        assert!(events.is_empty());
+        events.extend(parser.push(b":1}\r\n\r\ndata: a\ndata: b\n\n"));
+        assert_eq!(
+            events,
+            vec![
+                RawEvent {
+                    event: Some("tick".to_string()),
+                    id: Some("7".to_string()),
+                    data: r#"{"n":1}"#.to_string(),
+                },
+                RawEvent {
+                    event: None,
+                    id: Some("7".to_string()),
+                    data: "a\nb".to_string(),
+                },
+            ]
+        );
+    }
+
+    /// streams two events per connection, resuming after Last-Event-ID
+    #[derive(Default)]
+    struct EventsApi {
+        connections: AtomicU32,
+    }
+    impl ApiMiddleware for EventsApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            self.connections.fetch_add(1, Ordering::SeqCst);
+            let after: u32 = request
+                .headers()
+                .get("Last-Event-ID")
+                .and_then(|id| id.to_str().ok()?.parse().ok())
+                .unwrap_or_default();
+            let body: String = (after + 1..=after + 2)
+                .map(|n| format!("id: {n}\ndata: {{\"n\":{n}}}\n\n"))
+                .collect();
+            Box::pin(async move { stub_response(200, &body) })
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Tick {
+        n: u32,
+    }
+
+    #[tokio::test]
+    async fn test_recv_sse_reconnects() -> anyhow::Result<()> {
+        let api = std::sync::Arc::new(EventsApi::default());
+        let ticks: Vec<SseEvent<Tick>> = ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/events"),
+            vec![],
+        )
+        .retry(RetryPolicy::new(3).backoff(Duration::ZERO, Duration::ZERO))
+        .with_middleware(api.clone())
+        .recv_sse::<Tick, serde_json::Value>()
+        .take(5)
+        .try_collect()
+        .await?;
+
+        let ns: Vec<u32> = ticks.iter().map(|tick| tick.data.n).collect();
+        assert_eq!(ns, vec![1, 2, 3, 4, 5]);
+        assert_eq!(ticks[4].id.as_deref(), Some("5"));
+        assert_eq!(api.connections.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quiet_stream_outlasts_timeout() -> anyhow::Result<()> {
+        // one connection, with a pause between events longer than the timeout
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/events", listener.local_addr()?);
+        let _server = tokio::spawn(async move {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await;
+            let head =
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+            let _ = socket
+                .write_all(format!("{head}data: {{\"n\":1}}\n\n").as_bytes())
+                .await;
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let _ = socket.write_all(b"data: {\"n\":2}\n\n").await;
+        });
+
+        let client = reqwest::Client::builder().no_proxy().build()?;
+        let ticks: Vec<SseEvent<Tick>> = ApiRequestBuilder::new(client.get(url), vec![])
+            .with_config(ClientConfig::default().timeout(Duration::from_millis(100)))
+            .retry(RetryPolicy::new(1))
+            .recv_sse::<Tick, serde_json::Value>()
+            .take(2)
+            .try_collect()
+            .await?;
+        let ns: Vec<u32> = ticks.iter().map(|tick| tick.data.n).collect();
+        assert_eq!(ns, vec![1, 2]);
+        Ok(())
+    }
+
+    /// receives its responses form-encoded
+    struct FormEncoded<T>(T);
+    impl<T: ToRequestClient> ToRequestClient for FormEncoded<T> {
+        fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+            self.0.try_into()
+        }
+    }
+    impl<T: ToRequestClient> ReceiveResp<FormUrlEncodedFormat> for FormEncoded<T> {}
+
+    #[tokio::test]
+    async fn test_recv_sse_in_request_format() -> anyhow::Result<()> {
+        let request = ApiRequestBuilder::new(
+            reqwest::Client::new().get("http://localhost/events"),
+            vec![],
+        )
+        .with_middleware(StubResponse::new(200, "data: n=1\n\ndata: n=2\n\n"));
+        let ticks: Vec<SseEvent<Tick>> = ReceiveSse::<FormUrlEncodedFormat>::recv_sse::<
+            Tick,
+            serde_json::Value,
+        >(FormEncoded(request))
+        .take(2)
+        .try_collect()
+        .await?;
+        let ns: Vec<u32> = ticks.iter().map(|tick| tick.data.n).collect();
+        assert_eq!(ns, vec![1, 2]);
+        Ok(())
+    }
+}
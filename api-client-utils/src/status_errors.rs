@@ -0,0 +1,129 @@
+use crate::serialization_formats::SerialFormat;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+/// The `ErrResp` of a request: read from the body of an error response, knowing its status.
+/// Any `DeserializeOwned` type is one and ignores the status, `status_errors!` enums don't.
+pub trait ErrorBody: Sized {
+    fn from_status_body<F: SerialFormat>(
+        status: StatusCode,
+        content_type: Option<&str>,
+        body: &str,
+    ) -> Result<Self, F::Error>;
+}
+impl<T: DeserializeOwned> ErrorBody for T {
+    fn from_status_body<F: SerialFormat>(
+        _status: StatusCode,
+        content_type: Option<&str>,
+        body: &str,
+    ) -> Result<Self, F::Error> {
+        F::from_str_with_content_type(body, content_type)
+    }
+}
+
+/// Error body enum whose variant, and so the type deserialized, is picked by the response status.
+/// Use it as the `ErrResp` of a request, the `_` variant catches the other statuses.
+/// It's an ErrorBody but not Deserialize: without a status, there's no telling the variant.
+///
+/// ```text
+/// status_errors! {
+///     #[derive(Debug)]
+///     pub enum PetError {
+///         400 | 422 => Validation(ValidationError),
+///         409 => Conflict(ConflictError),
+///         _ => Other(ApiError),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! status_errors {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $($status:literal)|+ => $variant:ident($ty:ty),
+            )*
+            $(#[$fallback_meta:meta])*
+            _ => $fallback:ident($fallback_ty:ty) $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant($ty),
+            )*
+            $(#[$fallback_meta])*
+            $fallback($fallback_ty),
+        }
+        impl $crate::status_errors::ErrorBody for $name {
+            fn from_status_body<F: $crate::serialization_formats::SerialFormat>(
+                status: $crate::re_exports::reqwest::StatusCode,
+                content_type: Option<&str>,
+                body: &str,
+            ) -> Result<Self, F::Error> {
+                match status.as_u16() {
+                    $(
+                        $($status)|+ => F::from_str_with_content_type::<$ty>(body, content_type).map($name::$variant),
+                    )*
+                    _ => F::from_str_with_content_type::<$fallback_ty>(body, content_type).map($name::$fallback),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::middleware::tests::StubResponse;
+    use crate::prelude::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct ValidationError {
+        fields: Vec<String>,
+    }
+    #[derive(Deserialize, Debug)]
+    struct ConflictError {
+        existing_id: u64,
+    }
+    #[derive(Deserialize, Debug)]
+    struct ApiError {
+        message: String,
+    }
+    crate::status_errors! {
+        #[derive(Debug)]
+        enum PetError {
+            400 | 422 => Validation(ValidationError),
+            409 => Conflict(ConflictError),
+            _ => Other(ApiError),
+        }
+    }
+
+    fn create_pet(status: u16, body: &'static str) -> ApiRequestBuilder {
+        ApiRequestBuilder::new(reqwest::Client::new().post("http://localhost/pets"), vec![])
+            .with_middleware(StubResponse::new(status, body))
+    }
+
+    #[tokio::test]
+    async fn test_error_type_by_status() -> anyhow::Result<()> {
+        let err = |status, body| async move {
+            match create_pet(status, body)
+                .recv_json::<serde_json::Value, PetError>()
+                .await
+            {
+                Err(ClientErr::ErrorResponse { err_body, .. }) => Ok(err_body),
+                other => Err(anyhow::anyhow!("expected an error response, got {other:?}")),
+            }
+        };
+
+        let validation = err(422, r#"{"fields":["name"]}"#).await?;
+        assert!(matches!(validation, PetError::Validation(e) if e.fields == ["name"]));
+        let conflict = err(409, r#"{"existing_id":7}"#).await?;
+        assert!(matches!(conflict, PetError::Conflict(e) if e.existing_id == 7));
+        let other = err(500, r#"{"message":"boom"}"#).await?;
+        assert!(matches!(other, PetError::Other(e) if e.message == "boom"));
+        Ok(())
+    }
+}
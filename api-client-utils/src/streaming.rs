@@ -0,0 +1,225 @@
+use crate::error::ClientErr;
+use crate::serialization_formats::{JsonFormat, SerialFormat};
+use crate::status_errors::ErrorBody;
+use crate::ToRequestClient;
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use reqwest::{Method, StatusCode, Url};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::AsyncWriteExt;
+
+/// A successful response whose body is read chunk by chunk instead of buffered in memory
+pub struct ByteStream {
+    pub method: Method,
+    pub url: Box<Url>,
+    pub got_status: StatusCode,
+    pub content_length: Option<u64>,
+    body: BoxStream<'static, reqwest::Result<Bytes>>,
+}
+impl ByteStream {
+    pub fn into_inner(self) -> BoxStream<'static, reqwest::Result<Bytes>> {
+        self.body
+    }
+
+    /// Write the body to a temporary file next to `path`, renamed to `path` once complete,
+    /// so `path` never holds a partial download. Returns the number of bytes written.
+    pub async fn write_to<ErrResp, F: SerialFormat>(
+        self,
+        path: &Path,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<u64, ClientErr<ErrResp, F>> {
+        let tmp_path = tmp_path_for(path);
+        let written = self.write_tmp(&tmp_path, &mut progress).await;
+        let renamed = match written {
+            Ok(written) => tokio::fs::rename(&tmp_path, path)
+                .await
+                .map(|()| written)
+                .map_err(|source| ClientErr::WriteFile {
+                    path: path.to_path_buf(),
+                    source,
+                }),
+            Err(e) => Err(e),
+        };
+        if renamed.is_err() {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+        }
+        renamed
+    }
+
+    async fn write_tmp<ErrResp, F: SerialFormat>(
+        self,
+        tmp_path: &Path,
+        progress: &mut impl FnMut(Progress),
+    ) -> Result<u64, ClientErr<ErrResp, F>> {
+        let write_err = |source| ClientErr::WriteFile {
+            path: tmp_path.to_path_buf(),
+            source,
+        };
+        let total = self.content_length;
+        let mut file = tokio::fs::File::create(tmp_path).await.map_err(write_err)?;
+        let mut transferred = 0;
+        let mut body = self.body;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(ClientErr::ReadRespBodyText)?;
+            file.write_all(&chunk).await.map_err(write_err)?;
+            transferred += chunk.len() as u64;
+            progress(Progress { transferred, total });
+        }
+        if let Some(expected) = total.filter(|&expected| expected != transferred) {
+            return Err(ClientErr::IncompleteBody {
+                expected,
+                received: transferred,
+            });
+        }
+        file.sync_all().await.map_err(write_err)?;
+        Ok(transferred)
+    }
+}
+
+/// `dir/.name.<random>.part`, in the same directory so the final rename doesn't cross filesystems
+fn tmp_path_for(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_name = format!(".{name}.{}.part", uuid::Uuid::new_v4().simple());
+    path.with_file_name(tmp_name)
+}
+
+/// Reported after each chunk downloaded or uploaded, `total` is the body size when known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub transferred: u64,
+    pub total: Option<u64>,
+}
+
+impl Stream for ByteStream {
+    type Item = reqwest::Result<Bytes>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.body.poll_next_unpin(cx)
+    }
+}
+
+pub trait ReceiveStream<F: SerialFormat>: Sized + ToRequestClient {
+    /// Send the request and hand over the body as a stream of chunks, for downloads too big to buffer.
+    /// Error responses are still read whole and deserialized into ErrResp.
+    /// The timeout only applies until the response head, not to reading the body.
+    async fn recv_bytes_stream<ErrResp: ErrorBody>(
+        self,
+    ) -> Result<ByteStream, ClientErr<ErrResp, F>> {
+        let request = self.try_into().map_err(ClientErr::BuildRequest)?;
+        let sent = request.sent();
+
+        let response = request.send_streamed().await?;
+        let got_status = response.status();
+        if !got_status.is_success() {
+            return Err(ClientErr::from_error_response(sent, response).await);
+        }
+
+        Ok(ByteStream {
+            method: sent.method,
+            url: Box::new(sent.url),
+            got_status,
+            content_length: response.content_length(),
+            body: response.bytes_stream().boxed(),
+        })
+    }
+
+    /// Stream the body to `path` (see `ByteStream::write_to`), calling `progress` after each chunk.
+    async fn download_to<ErrResp: ErrorBody>(
+        self,
+        path: impl AsRef<Path>,
+        progress: impl FnMut(Progress),
+    ) -> Result<u64, ClientErr<ErrResp, F>> {
+        let stream = self.recv_bytes_stream::<ErrResp>().await?;
+        stream.write_to(path.as_ref(), progress).await
+    }
+}
+impl<T: ToRequestClient> ReceiveStream<JsonFormat> for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClientConfig;
+    use crate::middleware::tests::StubResponse;
+    use crate::ApiRequestBuilder;
+    use futures::TryStreamExt;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_recv_bytes_stream() -> anyhow::Result<()> {
+        let request = |status, body| {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/file"), vec![])
+                .with_middleware(StubResponse::new(status, body))
+        };
+
+        let stream = ReceiveStream::<JsonFormat>::recv_bytes_stream::<serde_json::Value>(request(
+            200,
+            "some large file",
+        ))
+        .await?;
+        assert_eq!(stream.got_status, StatusCode::OK);
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        assert_eq!(chunks.concat(), b"some large file");
+
+        let err = request(404, r#"{"message":"not found"}"#)
+            .recv_bytes_stream::<serde_json::Value>()
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            matches!(err, ClientErr::ErrorResponse { err_body, .. } if err_body["message"] == "not found")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_to() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("download-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join("artifact.bin");
+        let request =
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/file"), vec![])
+                .with_middleware(StubResponse::new(200, "0123456789"));
+
+        let mut reported = vec![];
+        let written = request
+            .download_to::<serde_json::Value>(&path, |p| reported.push(p))
+            .await?;
+        assert_eq!(written, 10);
+        assert_eq!(tokio::fs::read(&path).await?, b"0123456789");
+        assert_eq!(reported.last().map(|p| p.transferred), Some(10));
+        let leftovers = std::fs::read_dir(&dir)?.count();
+        assert_eq!(leftovers, 1, "no temporary file left behind");
+        tokio::fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_body_outlasts_timeout() -> anyhow::Result<()> {
+        // answers the head right away, then the body in two chunks further apart than the timeout
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/file", listener.local_addr()?);
+        let _server = tokio::spawn(async move {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await;
+            let head = "HTTP/1.1 200 OK\r\ncontent-length: 10\r\nconnection: close\r\n\r\n";
+            let _ = socket.write_all(format!("{head}01234").as_bytes()).await;
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let _ = socket.write_all(b"56789").await;
+        });
+
+        let client = reqwest::Client::builder().no_proxy().build()?;
+        let stream = ApiRequestBuilder::new(client.get(url), vec![])
+            .with_config(ClientConfig::default().timeout(Duration::from_millis(100)))
+            .recv_bytes_stream::<serde_json::Value>()
+            .await?;
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        assert_eq!(chunks.concat(), b"0123456789");
+        Ok(())
+    }
+}
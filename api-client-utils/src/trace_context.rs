@@ -0,0 +1,109 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use futures::future::BoxFuture;
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+/// W3C `traceparent` of a span: `00-<trace id>-<span id>-<flags>`
+pub fn traceparent(span_context: &SpanContext) -> String {
+    format!(
+        "00-{:032x}-{:016x}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags()
+    )
+}
+
+/// Middleware adding the W3C `traceparent`/`tracestate` headers of the current tracing span,
+/// so the services called join the trace. Needs a `tracing_opentelemetry` layer in the subscriber,
+/// without one (or outside any span) requests go out unchanged.
+///
+/// With the `tracing` feature the current span is the `http.client.request` span of the call.
+/// Headers already set on the request are kept.
+pub struct TraceContext;
+impl ApiMiddleware for TraceContext {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        if span_context.is_valid() && !request.headers().contains_key(TRACEPARENT) {
+            let headers = request.headers_mut();
+            if let Ok(value) = HeaderValue::try_from(traceparent(span_context)) {
+                headers.insert(TRACEPARENT, value);
+            }
+            let trace_state = span_context.trace_state().header();
+            if let Ok(value) = HeaderValue::try_from(trace_state) {
+                if !value.is_empty() {
+                    headers.insert(TRACESTATE, value);
+                }
+            }
+        }
+        Box::pin(next.run(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use opentelemetry::trace::TracerProvider;
+    use tracing::Instrument;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// answers with the traceparent it got
+    struct EchoTraceparent;
+    impl ApiMiddleware for EchoTraceparent {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let traceparent = request.headers().get(TRACEPARENT);
+            let traceparent = traceparent
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            let response = crate::middleware::tests::stub_response(200, traceparent);
+            Box::pin(async move { response })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_of_current_span() -> anyhow::Result<()> {
+        let tracer = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .build()
+            .tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let request = || {
+            ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/pets"), vec![])
+                .with_middleware(TraceContext)
+                .with_middleware(EchoTraceparent)
+        };
+
+        let handler = tracing::info_span!("handler");
+        let trace_id = handler.context().span().span_context().trace_id();
+        let sent = request()
+            .recv_text::<serde_json::Value>()
+            .instrument(handler)
+            .await?;
+        let parts: Vec<&str> = sent.split('-').collect();
+        assert_eq!(parts.len(), 4, "{sent}");
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[1], format!("{trace_id:032x}"));
+        assert_eq!(parts[3], "01", "sampled");
+
+        let outside_spans = request().recv_text::<serde_json::Value>().await?;
+        assert_eq!(outside_spans, "");
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use crate::context::RespContext;
+use crate::error::ExecuteErr;
+use crate::middleware::response_from_parts;
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+use std::sync::{Arc, Mutex};
+
+/// What actually sends a request once it went through the middlewares, reqwest::Client by default.
+/// Swap it with `ApiClient::transport()` to run a client against canned responses in tests.
+pub trait HttpTransport: Send + Sync {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>>;
+}
+impl HttpTransport for reqwest::Client {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            reqwest::Client::execute(self, request)
+                .await
+                .map_err(ExecuteErr::Request)
+        })
+    }
+}
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>> {
+        self.as_ref().execute(request)
+    }
+}
+
+/// Transport answering from canned RespContexts, matched on method and path (and query if the canned url has one).
+/// Requests without a canned response fail, every request received is kept for assertions.
+#[derive(Default)]
+pub struct MockTransport {
+    responses: Vec<RespContext>,
+    received: Mutex<Vec<Request>>,
+}
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the first matching response wins
+    pub fn respond(mut self, response: RespContext) -> Self {
+        self.responses.push(response);
+        self
+    }
+    /// method and url of every request received so far
+    pub fn received(&self) -> Vec<(reqwest::Method, reqwest::Url)> {
+        let received = self.received.lock().unwrap_or_else(|e| e.into_inner());
+        received
+            .iter()
+            .map(|r| (r.method().clone(), r.url().clone()))
+            .collect()
+    }
+
+    fn find(&self, request: &Request) -> Option<&RespContext> {
+        self.responses.iter().find(|canned| {
+            canned.method == request.method()
+                && canned.url.path() == request.url().path()
+                && (canned.url.query().is_none() || canned.url.query() == request.url().query())
+        })
+    }
+}
+impl HttpTransport for MockTransport {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>> {
+        let response = match self.find(&request) {
+            Some(canned) => Ok(response_from_parts(
+                canned.got_status,
+                *canned.headers.clone(),
+                canned.response_text.clone(),
+            )),
+            None => Err(ExecuteErr::Middleware(anyhow::anyhow!(
+                "no canned response for {} {}",
+                request.method(),
+                request.url()
+            ))),
+        };
+        let mut received = self.received.lock().unwrap_or_else(|e| e.into_inner());
+        received.push(request);
+        Box::pin(async move { response })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::SentRequest;
+    use crate::prelude::*;
+    use reqwest::{Method, StatusCode};
+
+    struct PetApi {
+        http_client: reqwest::Client,
+        transport: Arc<MockTransport>,
+    }
+    impl JsonApiClient for PetApi {
+        fn base_url(&self) -> &str {
+            "https://pets.example.com/v1"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn transport(&self) -> Option<Arc<dyn HttpTransport>> {
+            Some(self.transport.clone())
+        }
+    }
+
+    fn canned(method: Method, url: &str, status: u16, body: &str) -> anyhow::Result<RespContext> {
+        let sent = SentRequest::new(method, url.parse()?);
+        Ok(sent.context(StatusCode::from_u16(status)?, body.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport() -> anyhow::Result<()> {
+        let transport = MockTransport::new()
+            .respond(canned(
+                Method::GET,
+                "https://pets.example.com/v1/pets/1",
+                200,
+                r#"{"name":"rex"}"#,
+            )?)
+            .respond(canned(
+                Method::GET,
+                "https://pets.example.com/v1/pets/2",
+                404,
+                r#"{"message":"not found"}"#,
+            )?);
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+            transport: Arc::new(transport),
+        };
+
+        let pet: serde_json::Value = api
+            .get("/pets/1")
+            .recv_json::<_, serde_json::Value>()
+            .await?;
+        assert_eq!(pet["name"], "rex");
+        let missing = api
+            .get("/pets/2")
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await;
+        assert!(matches!(missing, Err(ClientErr::ErrorResponse { .. })));
+        let unknown = api
+            .delete("/pets/1")
+            .recv_json::<(), serde_json::Value>()
+            .await;
+        assert!(matches!(unknown, Err(ClientErr::Middleware { .. })));
+
+        assert_eq!(api.transport.received().len(), 3);
+        Ok(())
+    }
+}
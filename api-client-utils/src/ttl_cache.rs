@@ -0,0 +1,191 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{cache_key, response_from_parts, ApiMiddleware, Next, ResponseParts};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Request, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+struct Entry {
+    expires_at: Instant,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// In-memory cache serving repeated GETs from memory for a fixed time, keyed by url, Accept and Authorization.
+/// Endpoints are marked cacheable by path prefix with `endpoint()`, or per request with `for_request()`.
+#[derive(Default)]
+pub struct TtlCache {
+    /// (path prefix, ttl), the longest matching prefix wins
+    rules: Vec<(String, Duration)>,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+impl TtlCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn endpoint(mut self, path_prefix: &str, ttl: Duration) -> Self {
+        self.rules.push((path_prefix.to_string(), ttl));
+        self
+    }
+    /// middleware caching this one request for `ttl`, sharing the cache's entries
+    pub fn for_request(self: &Arc<Self>, ttl: Duration) -> CacheFor {
+        CacheFor {
+            cache: self.clone(),
+            ttl,
+        }
+    }
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn ttl_for(&self, request: &Request) -> Option<Duration> {
+        let path = request.url().path();
+        self.rules
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, ttl)| *ttl)
+    }
+
+    async fn serve(
+        &self,
+        request: Request,
+        next: Next<'_>,
+        ttl: Duration,
+    ) -> Result<Response, ExecuteErr> {
+        if request.method() != Method::GET {
+            return next.run(request).await;
+        }
+        let key = cache_key(&request);
+        let now = Instant::now();
+        let hit = {
+            let mut entries = self.lock();
+            entries.retain(|_, entry| entry.expires_at > now);
+            entries.get(&key).cloned()
+        };
+        if let Some(entry) = hit {
+            return Ok(response_from_parts(entry.status, entry.headers, entry.body));
+        }
+
+        let mut response = next.run(request).await?;
+        if !response.status().is_success() {
+            return Ok(response);
+        }
+        let parts = ResponseParts::take(&mut response);
+        let body = response.bytes().await.map_err(ExecuteErr::Request)?;
+        let entry = Entry {
+            expires_at: Instant::now() + ttl,
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: body.clone(),
+        };
+        self.lock().insert(key, entry);
+        Ok(parts.with_body(body))
+    }
+}
+impl ApiMiddleware for TtlCache {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        match self.ttl_for(&request) {
+            Some(ttl) => Box::pin(self.serve(request, next, ttl)),
+            None => next.run(request),
+        }
+    }
+}
+
+/// See TtlCache::for_request
+pub struct CacheFor {
+    cache: Arc<TtlCache>,
+    ttl: Duration,
+}
+impl ApiMiddleware for CacheFor {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(self.cache.serve(request, next, self.ttl))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Default)]
+    struct CountingApi {
+        calls: AtomicU32,
+    }
+    impl ApiMiddleware for CountingApi {
+        fn handle<'a>(
+            &'a self,
+            _request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                Ok(response_from_parts(
+                    StatusCode::OK,
+                    HeaderMap::new(),
+                    n.to_string(),
+                ))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ttl_cache() -> anyhow::Result<()> {
+        let cache = Arc::new(TtlCache::new().endpoint("/rates", Duration::from_millis(200)));
+        let api = Arc::new(CountingApi::default());
+        let client = reqwest::Client::new();
+        let call = |path: &str| {
+            ApiRequestBuilder::new(client.get(format!("http://localhost{path}")), vec![])
+                .with_middleware(cache.clone())
+                .with_middleware(api.clone())
+                .recv_json::<u32, serde_json::Value>()
+        };
+
+        assert_eq!(call("/rates/eur").await?, 1);
+        assert_eq!(call("/rates/eur").await?, 1);
+        assert_eq!(call("/rates/usd").await?, 2);
+        assert_eq!(call("/orders").await?, 3);
+        assert_eq!(call("/orders").await?, 4, "endpoint not marked cacheable");
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(call("/rates/eur").await?, 5, "entry expired");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_entries_per_credentials() -> anyhow::Result<()> {
+        let cache = Arc::new(TtlCache::new().endpoint("/me", Duration::from_secs(60)));
+        let api = Arc::new(CountingApi::default());
+        let client = reqwest::Client::new();
+        let call = |token: &str| {
+            let request = client.get("http://localhost/me").bearer_auth(token);
+            ApiRequestBuilder::new(request, vec![])
+                .with_middleware(cache.clone())
+                .with_middleware(api.clone())
+                .recv_json::<u32, serde_json::Value>()
+        };
+
+        assert_eq!(call("alice").await?, 1);
+        assert_eq!(call("bob").await?, 2, "not served alice's response");
+        assert_eq!(call("alice").await?, 1);
+        Ok(())
+    }
+}
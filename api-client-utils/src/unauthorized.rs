@@ -0,0 +1,106 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use futures::future::BoxFuture;
+use reqwest::{Request, Response, StatusCode};
+use std::future::Future;
+
+/// Invoked when a response comes back 401, before the original request is re-issued once
+pub trait UnauthorizedHook: Send + Sync {
+    /// Refresh credentials, adjusting the copy of the request about to be re-issued if needed.
+    /// Return false to give up and hand the 401 to the caller.
+    fn on_unauthorized(
+        &self,
+        retry_request: &mut Request,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+}
+
+/// Run the request, and if it comes back 401, let `hook` refresh credentials and re-issue it once.
+/// Requests with a streaming body can't be cloned and are never re-issued.
+pub async fn reissue_on_unauthorized(
+    hook: &impl UnauthorizedHook,
+    request: Request,
+    next: Next<'_>,
+) -> Result<Response, ExecuteErr> {
+    let retry_request = request.try_clone();
+    let response = next.run(request).await?;
+
+    match (response.status(), retry_request) {
+        (StatusCode::UNAUTHORIZED, Some(mut retry_request)) => {
+            let reissue = hook
+                .on_unauthorized(&mut retry_request)
+                .await
+                .map_err(ExecuteErr::Middleware)?;
+            match reissue {
+                true => next.run(retry_request).await,
+                false => Ok(response),
+            }
+        }
+        _ => Ok(response),
+    }
+}
+
+/// Middleware calling an UnauthorizedHook on 401 responses
+pub struct OnUnauthorized<H: UnauthorizedHook>(pub H);
+impl<H: UnauthorizedHook> ApiMiddleware for OnUnauthorized<H> {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(reissue_on_unauthorized(&self.0, request, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tests::stub_response;
+    use crate::prelude::*;
+    use crate::ApiRequestBuilder;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// accepts requests carrying `x-session: fresh`
+    struct SessionApi;
+    impl ApiMiddleware for SessionApi {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let status = match request.headers().get("x-session") {
+                Some(value) if value == "fresh" => 200,
+                _ => 401,
+            };
+            Box::pin(async move { stub_response(status, "null") })
+        }
+    }
+
+    struct Relogin {
+        calls: Arc<AtomicU32>,
+    }
+    impl UnauthorizedHook for Relogin {
+        async fn on_unauthorized(&self, retry_request: &mut Request) -> anyhow::Result<bool> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            retry_request
+                .headers_mut()
+                .insert("x-session", "fresh".parse()?);
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reissues_once_after_hook() -> anyhow::Result<()> {
+        let calls = Arc::new(AtomicU32::new(0));
+        ApiRequestBuilder::new(reqwest::Client::new().get("http://localhost/me"), vec![])
+            .header("x-session", "stale")
+            .with_middleware(OnUnauthorized(Relogin {
+                calls: calls.clone(),
+            }))
+            .with_middleware(SessionApi)
+            .recv_json::<(), serde_json::Value>()
+            .await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
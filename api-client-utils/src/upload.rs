@@ -0,0 +1,131 @@
+use crate::streaming::Progress;
+use bytes::Bytes;
+use futures::stream::{self, Stream};
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A file sent as a request body chunk by chunk, never held in memory whole.
+/// Streamed bodies can't be replayed, so requests carrying one aren't retried.
+pub struct FileBody {
+    file: tokio::fs::File,
+    pub len: u64,
+    pub content_type: String,
+    progress: Option<Box<dyn FnMut(Progress) + Send>>,
+}
+impl FileBody {
+    /// the Content-Type is guessed from the extension, `application/octet-stream` if unknown
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+        let content_type = crate::multipart::mime_from_extension(path)
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        Ok(Self {
+            file,
+            len,
+            content_type,
+            progress: None,
+        })
+    }
+    pub fn content_type(self, content_type: &str) -> Self {
+        Self {
+            content_type: content_type.to_string(),
+            ..self
+        }
+    }
+    /// called after each chunk handed to the connection
+    pub fn on_progress(self, progress: impl FnMut(Progress) + Send + 'static) -> Self {
+        Self {
+            progress: Some(Box::new(progress)),
+            ..self
+        }
+    }
+
+    pub(crate) fn into_stream(self) -> impl Stream<Item = std::io::Result<Bytes>> + Send {
+        let total = Some(self.len);
+        let state = (self.file, self.progress, 0);
+        stream::try_unfold(
+            state,
+            move |(mut file, mut progress, transferred)| async move {
+                let mut chunk = vec![0; CHUNK_SIZE];
+                let read = file.read(&mut chunk).await?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                chunk.truncate(read);
+                let transferred = transferred + read as u64;
+                if let Some(progress) = &mut progress {
+                    progress(Progress { transferred, total });
+                }
+                Ok(Some((Bytes::from(chunk), (file, progress, transferred))))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::ToRequestClient;
+    use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use std::sync::{Arc, Mutex};
+
+    struct ArtifactApi {
+        http_client: reqwest::Client,
+    }
+    impl JsonApiClient for ArtifactApi {
+        fn base_url(&self) -> &str {
+            "http://localhost/v1"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_file_streams_body() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("upload-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join("report.csv");
+        let contents = "id,name\n".repeat(20_000);
+        tokio::fs::write(&path, &contents).await?;
+
+        let reported = Arc::new(Mutex::new(vec![]));
+        let file = FileBody::open(&path).await?.on_progress({
+            let reported = reported.clone();
+            move |p| reported.lock().unwrap().push(p)
+        });
+        let api = ArtifactApi {
+            http_client: reqwest::Client::new(),
+        };
+        let mut request = ToRequestClient::try_into(api.put_file("/reports/1", file))?.request;
+
+        assert_eq!(request.headers()[CONTENT_TYPE], "text/csv");
+        assert_eq!(
+            request.headers()[CONTENT_LENGTH],
+            contents.len().to_string().as_str()
+        );
+        assert!(
+            request.try_clone().is_none(),
+            "body is streamed, not buffered"
+        );
+        let body = request.body_mut().take().expect("file body");
+        let sent = reqwest::Response::from(http::Response::new(body))
+            .bytes()
+            .await?;
+        assert_eq!(sent, contents.as_bytes());
+
+        let reported = reported.lock().unwrap().clone();
+        assert!(reported.len() > 1);
+        assert_eq!(
+            reported.last().map(|p| p.transferred),
+            Some(contents.len() as u64)
+        );
+        tokio::fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::error::ExecuteErr;
+use crate::middleware::{ApiMiddleware, Next};
+use futures::future::BoxFuture;
+use reqwest::{Request, Response, Url};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum UrlJoinErr {
+    #[error("invalid base url `{base}`: {reason}")]
+    InvalidBase { base: String, reason: String },
+    #[error("invalid path `{path}`: {reason}")]
+    InvalidPath { path: String, reason: String },
+    #[error("path `{0}` climbs out of the base url with `..`")]
+    Traversal(String),
+    #[error("url `{url}` isn't under the base url `{base}`")]
+    OutsideBase { url: String, base: String },
+}
+
+/// `url_path` resolved under `base_url`, keeping the base path: `https://api.com/v2` + `/pets` is `https://api.com/v2/pets`.
+/// Absolute urls are accepted when under the base (e.g. a next-page link), `.`/`..` segments are rejected.
+pub fn join(base_url: &str, url_path: &str) -> Result<Url, UrlJoinErr> {
+    let base_url = base_url.trim();
+    let mut base = Url::parse(base_url).map_err(|e| UrlJoinErr::InvalidBase {
+        base: base_url.to_string(),
+        reason: e.to_string(),
+    })?;
+    // without the trailing slash, joining would replace the last base segment
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    let url_path = url_path.trim();
+
+    let outside = |url: &Url| UrlJoinErr::OutsideBase {
+        url: url.to_string(),
+        base: base.to_string(),
+    };
+    if let Ok(absolute) = Url::parse(url_path) {
+        if matches!(absolute.scheme(), "http" | "https") {
+            let under_base =
+                absolute.origin() == base.origin() && absolute.path().starts_with(base.path());
+            return match under_base {
+                true if has_dot_segment(absolute.path()) => {
+                    Err(UrlJoinErr::Traversal(url_path.to_string()))
+                }
+                true => Ok(absolute),
+                false => Err(outside(&absolute)),
+            };
+        }
+    }
+    if url_path.starts_with("//") {
+        return Err(UrlJoinErr::OutsideBase {
+            url: url_path.to_string(),
+            base: base.to_string(),
+        });
+    }
+
+    let relative = url_path.trim_start_matches('/');
+    let path_part = relative.split(['?', '#']).next().unwrap_or_default();
+    if has_dot_segment(path_part) {
+        return Err(UrlJoinErr::Traversal(url_path.to_string()));
+    }
+    let url = base.join(relative).map_err(|e| UrlJoinErr::InvalidPath {
+        path: url_path.to_string(),
+        reason: e.to_string(),
+    })?;
+    match url.origin() == base.origin() && url.path().starts_with(base.path()) {
+        true => Ok(url),
+        false => Err(outside(&url)),
+    }
+}
+
+/// `.` or `..` segments, percent-encoded too since Url::join decodes them
+fn has_dot_segment(path: &str) -> bool {
+    path.split('/').any(|segment| {
+        let segment = segment.to_ascii_lowercase().replace("%2e", ".");
+        segment == "." || segment == ".."
+    })
+}
+
+/// Fails the request before anything is sent, for a path `join` rejected when the request was built
+pub(crate) struct Rejected(pub UrlJoinErr);
+impl ApiMiddleware for Rejected {
+    fn handle<'a>(
+        &'a self,
+        _request: Request,
+        _next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        let err = anyhow::Error::new(self.0.clone());
+        Box::pin(async move { Err(ExecuteErr::Middleware(err)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn joined(base: &str, path: &str) -> Result<String, UrlJoinErr> {
+        join(base, path).map(String::from)
+    }
+
+    #[test]
+    fn test_join() {
+        let v2 = "https://api.pets.com/v2";
+        assert_eq!(
+            joined(v2, "/pets/1").as_deref(),
+            Ok("https://api.pets.com/v2/pets/1")
+        );
+        assert_eq!(
+            joined(v2, "pets?page=2").as_deref(),
+            Ok("https://api.pets.com/v2/pets?page=2")
+        );
+        assert_eq!(
+            joined("https://api.pets.com/v2/", "").as_deref(),
+            Ok("https://api.pets.com/v2/")
+        );
+        assert_eq!(
+            joined("https://api.pets.com", "/pets").as_deref(),
+            Ok("https://api.pets.com/pets")
+        );
+        // a next-page link handed back as is
+        assert_eq!(
+            joined(v2, "https://api.pets.com/v2/pets?cursor=abc").as_deref(),
+            Ok("https://api.pets.com/v2/pets?cursor=abc")
+        );
+
+        assert!(matches!(
+            joined(v2, "/pets/../../admin"),
+            Err(UrlJoinErr::Traversal(_))
+        ));
+        assert!(matches!(
+            joined(v2, "pets/%2E%2e/admin"),
+            Err(UrlJoinErr::Traversal(_))
+        ));
+        assert!(matches!(
+            joined(v2, "https://api.pets.com/v1/pets"),
+            Err(UrlJoinErr::OutsideBase { .. })
+        ));
+        assert!(matches!(
+            joined(v2, "https://evil.com/v2/pets"),
+            Err(UrlJoinErr::OutsideBase { .. })
+        ));
+        assert!(matches!(
+            joined(v2, "//evil.com/pets"),
+            Err(UrlJoinErr::OutsideBase { .. })
+        ));
+        assert!(matches!(
+            joined("api.pets.com", "/pets"),
+            Err(UrlJoinErr::InvalidBase { .. })
+        ));
+    }
+
+    struct PetApi {
+        http_client: reqwest::Client,
+    }
+    impl JsonApiClient for PetApi {
+        fn base_url(&self) -> &str {
+            "https://api.pets.com/v2"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_path_fails_before_sending() {
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+        };
+        let err = api
+            .get("/pets/../admin")
+            .recv_json::<serde_json::Value, serde_json::Value>()
+            .await;
+        assert!(
+            matches!(&err, Err(ClientErr::Middleware { source: e, .. }) if e.downcast_ref::<UrlJoinErr>().is_some()),
+            "{err:?}"
+        );
+    }
+}
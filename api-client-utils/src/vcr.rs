@@ -0,0 +1,221 @@
+use crate::error::ExecuteErr;
+use crate::http_cache::CachedResponse;
+use crate::transport::HttpTransport;
+use base64::Engine;
+use file_cache::FileBytes;
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A request and the response it got
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    pub method: String,
+    pub url: String,
+    /// base64
+    pub body: String,
+    pub response: CachedResponse,
+}
+impl Interaction {
+    fn matches(&self, method: &str, url: &str, body: &str) -> bool {
+        self.method == method && self.url == url && self.body == body
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+impl FileBytes for Cassette {
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+enum Mode {
+    /// the cassette didn't exist, interactions go through `inner` and are appended to it
+    Record(Cassette),
+    /// interactions come from the cassette, the flags mark those already replayed
+    Replay(Cassette, Vec<bool>),
+}
+
+/// Record/replay transport for tests: the first run sends requests through `inner`
+/// and saves every interaction to the cassette file, later runs replay them without touching the network.
+/// Requests are matched on method, url and body; identical requests replay their recordings in order.
+/// Delete the cassette to record again. Cassettes are meant to be committed: in CI (`$CI` set),
+/// a missing one is an error rather than a recording against the real API.
+pub struct Vcr {
+    pub path: PathBuf,
+    inner: Arc<dyn HttpTransport>,
+    mode: Mutex<Mode>,
+}
+impl Vcr {
+    /// `tests/cassettes/{name}.json` in the crate being tested
+    pub fn new(name: &str, inner: impl HttpTransport + 'static) -> anyhow::Result<Self> {
+        let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").ok_or(anyhow::anyhow!(
+            "no $CARGO_MANIFEST_DIR, run it with cargo test"
+        ))?;
+        let path = PathBuf::from(manifest_dir).join(format!("tests/cassettes/{name}.json"));
+        Self::at(path, inner)
+    }
+    pub fn at(
+        path: impl Into<PathBuf>,
+        inner: impl HttpTransport + 'static,
+    ) -> anyhow::Result<Self> {
+        let in_ci = std::env::var_os("CI").is_some_and(|ci| !ci.is_empty());
+        Self::open(path.into(), inner, in_ci)
+    }
+    fn open(
+        path: PathBuf,
+        inner: impl HttpTransport + 'static,
+        in_ci: bool,
+    ) -> anyhow::Result<Self> {
+        let mode = match path.exists() {
+            true => {
+                let cassette = Cassette::from_file(&path)?;
+                let replayed = vec![false; cassette.interactions.len()];
+                Mode::Replay(cassette, replayed)
+            }
+            false if in_ci => anyhow::bail!(
+                "no cassette at {}, record it locally and commit it",
+                path.display()
+            ),
+            false => Mode::Record(Cassette::default()),
+        };
+        Ok(Self {
+            path,
+            inner: Arc::new(inner),
+            mode: Mutex::new(mode),
+        })
+    }
+    pub fn is_replaying(&self) -> bool {
+        matches!(*self.lock(), Mode::Replay(..))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Mode> {
+        self.mode.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn replay(&self, method: &str, url: &str, body: &str) -> Option<Result<Response, ExecuteErr>> {
+        let mut mode = self.lock();
+        let Mode::Replay(cassette, replayed) = &mut *mode else {
+            return None;
+        };
+        let matching: Vec<usize> = (0..cassette.interactions.len())
+            .filter(|&i| cassette.interactions[i].matches(method, url, body))
+            .collect();
+        let index = matching.iter().find(|&&i| !replayed[i]).or(matching.last());
+        Some(match index {
+            Some(&i) => {
+                replayed[i] = true;
+                cassette.interactions[i].response.to_response()
+            }
+            None => Err(ExecuteErr::Middleware(anyhow::anyhow!(
+                "no interaction recorded for {method} {url} in {}",
+                self.path.display()
+            ))),
+        })
+    }
+
+    async fn record(
+        &self,
+        request: Request,
+        method: String,
+        url: String,
+        body: String,
+    ) -> Result<Response, ExecuteErr> {
+        let response = self.inner.execute(request).await?;
+        let (status, headers) = (response.status(), response.headers().clone());
+        let response_body = response.bytes().await.map_err(ExecuteErr::Request)?;
+        let recorded = CachedResponse::from_parts(status, &headers, &response_body);
+        let interaction = Interaction {
+            method,
+            url,
+            body,
+            response: recorded.clone(),
+        };
+
+        let mut mode = self.lock();
+        if let Mode::Record(cassette) = &mut *mode {
+            cassette.interactions.push(interaction);
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| ExecuteErr::Middleware(e.into()))?;
+            }
+            cassette
+                .to_file(&self.path)
+                .map_err(ExecuteErr::Middleware)?;
+        }
+        recorded.to_response()
+    }
+}
+impl HttpTransport for Vcr {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, ExecuteErr>> {
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+        let body = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .unwrap_or_default();
+        let body = base64::engine::general_purpose::STANDARD.encode(body);
+        match self.replay(&method, &url, &body) {
+            Some(replayed) => Box::pin(async move { replayed }),
+            None => Box::pin(self.record(request, method, url, body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::SentRequest;
+    use crate::prelude::*;
+    use reqwest::{Method, StatusCode};
+
+    fn send(
+        vcr: &Arc<Vcr>,
+        body: &str,
+    ) -> impl std::future::Future<Output = JsonClientResult<serde_json::Value, serde_json::Value>>
+    {
+        ApiRequestBuilder::new(
+            reqwest::Client::new()
+                .post("http://localhost/search")
+                .body(body.to_string()),
+            vec![],
+        )
+        .with_transport(Some(vcr.clone()))
+        .recv_json()
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() -> anyhow::Result<()> {
+        let temp = file_cache::TempCacheDir::new()?;
+        let path = temp.path().join("search.json");
+        assert!(
+            Vcr::open(path.clone(), MockTransport::new(), true).is_err(),
+            "no recording in CI"
+        );
+        let upstream = MockTransport::new().respond(
+            SentRequest::new(Method::POST, "http://localhost/search".parse()?)
+                .context(StatusCode::OK, r#"{"hits":3}"#.to_string()),
+        );
+
+        let recording = Arc::new(Vcr::open(path.clone(), upstream, false)?);
+        assert!(!recording.is_replaying());
+        assert_eq!(send(&recording, "rex").await?["hits"], 3);
+
+        // nothing upstream anymore, only the cassette can answer
+        let replaying = Arc::new(Vcr::open(path, MockTransport::new(), true)?);
+        assert!(replaying.is_replaying());
+        assert_eq!(send(&replaying, "rex").await?["hits"], 3);
+        assert!(
+            send(&replaying, "felix").await.is_err(),
+            "body doesn't match"
+        );
+        Ok(())
+    }
+}
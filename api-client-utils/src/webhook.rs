@@ -0,0 +1,241 @@
+use crate::serialization_formats::{JsonFormat, SerialFormat};
+use crate::signing::SignatureEncoding;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Server side counterpart of the client: checking that a webhook delivery comes from the API it claims to
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookErr<F: SerialFormat = JsonFormat> {
+    #[error("missing header {0}")]
+    MissingHeader(HeaderName),
+    #[error("malformed header {0}")]
+    MalformedHeader(HeaderName),
+    #[error("signature doesn't match the payload")]
+    InvalidSignature,
+    /// too old or too far in the future, possibly a replayed delivery
+    #[error("timestamp {age_secs}s away from now, over the tolerance")]
+    OutsideTolerance { age_secs: i64 },
+    #[error("payload isn't UTF-8: {0}")]
+    NotUtf8(std::str::Utf8Error),
+    #[error("invalid payload: {0:?}")]
+    Payload(F::Error),
+}
+
+/// Where the signature is sent and what it covers
+#[derive(Debug, Clone)]
+pub enum WebhookScheme {
+    /// `Stripe-Signature: t=1700000000,v1=<hex>` over `{t}.{body}`, any of several `v1` may match
+    Stripe,
+    /// `X-Hub-Signature-256: sha256=<hex>` over the body, without timestamp
+    GitHub,
+    /// signature and unix timestamp (seconds) in headers of their own, over `{timestamp}.{body}`
+    Headers {
+        signature: HeaderName,
+        timestamp: HeaderName,
+        encoding: SignatureEncoding,
+    },
+}
+
+/// Verifies HMAC-SHA256 signatures of webhook deliveries, then deserializes their payload
+///
+/// ```text
+/// let verifier = WebhookVerifier::stripe(&endpoint_secret);
+/// let event: StripeEvent = verifier.parse_json(&headers, &body)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebhookVerifier {
+    secret: Vec<u8>,
+    pub scheme: WebhookScheme,
+    /// max distance between the signed timestamp and now, 5 minutes by default
+    pub tolerance: Duration,
+}
+impl WebhookVerifier {
+    pub fn new(secret: impl Into<Vec<u8>>, scheme: WebhookScheme) -> Self {
+        Self {
+            secret: secret.into(),
+            scheme,
+            tolerance: Duration::from_secs(300),
+        }
+    }
+    pub fn stripe(secret: impl Into<Vec<u8>>) -> Self {
+        Self::new(secret, WebhookScheme::Stripe)
+    }
+    pub fn github(secret: impl Into<Vec<u8>>) -> Self {
+        Self::new(secret, WebhookScheme::GitHub)
+    }
+    pub fn tolerance(self, tolerance: Duration) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// check the signature against the raw body, as received, before any parsing
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), WebhookErr> {
+        self.verify_at(headers, body, SystemTime::now())
+    }
+    pub fn verify_at<F: SerialFormat>(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<(), WebhookErr<F>> {
+        let (timestamp, signatures) = match &self.scheme {
+            WebhookScheme::Stripe => {
+                let name = HeaderName::from_static("stripe-signature");
+                let value = header(headers, &name)?;
+                let mut timestamp = None;
+                let mut signatures = vec![];
+                for (key, value) in value.split(',').filter_map(|part| part.split_once('=')) {
+                    match key.trim() {
+                        "t" => timestamp = Some(value.trim()),
+                        "v1" => signatures.push(hex::decode(value.trim()).ok()),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.filter(|t| t.parse::<i64>().is_ok());
+                let timestamp = timestamp.ok_or(WebhookErr::MalformedHeader(name.clone()))?;
+                let signatures = signatures.into_iter().collect::<Option<Vec<_>>>();
+                let signatures = signatures.ok_or(WebhookErr::MalformedHeader(name))?;
+                (Some(timestamp), signatures)
+            }
+            WebhookScheme::GitHub => {
+                let name = HeaderName::from_static("x-hub-signature-256");
+                let value = header(headers, &name)?;
+                let signature = value.strip_prefix("sha256=").map(hex::decode);
+                let Some(Ok(signature)) = signature else {
+                    return Err(WebhookErr::MalformedHeader(name));
+                };
+                (None, vec![signature])
+            }
+            WebhookScheme::Headers {
+                signature,
+                timestamp,
+                encoding,
+            } => {
+                let value = header(headers, signature)?;
+                let decoded = match encoding {
+                    SignatureEncoding::Hex => hex::decode(value).ok(),
+                    SignatureEncoding::Base64 => {
+                        base64::engine::general_purpose::STANDARD.decode(value).ok()
+                    }
+                };
+                let decoded = decoded.ok_or(WebhookErr::MalformedHeader(signature.clone()))?;
+                let signed_at = header(headers, timestamp)?;
+                if signed_at.parse::<i64>().is_err() {
+                    return Err(WebhookErr::MalformedHeader(timestamp.clone()));
+                }
+                (Some(signed_at), vec![decoded])
+            }
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .map_err(|_| WebhookErr::InvalidSignature)?;
+        if let Some(timestamp) = timestamp {
+            self.check_timestamp(timestamp, now)?;
+            mac.update(timestamp.as_bytes());
+            mac.update(b".");
+        }
+        mac.update(body);
+        // verify_slice compares in constant time
+        let matches = signatures
+            .iter()
+            .any(|signature| mac.clone().verify_slice(signature).is_ok());
+        match matches {
+            true => Ok(()),
+            false => Err(WebhookErr::InvalidSignature),
+        }
+    }
+
+    /// verify, then deserialize the payload with format F
+    pub fn parse<T: DeserializeOwned, F: SerialFormat>(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<T, WebhookErr<F>> {
+        self.verify_at(headers, body, SystemTime::now())?;
+        let text = std::str::from_utf8(body).map_err(WebhookErr::NotUtf8)?;
+        F::from_str(text).map_err(WebhookErr::Payload)
+    }
+    pub fn parse_json<T: DeserializeOwned>(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<T, WebhookErr> {
+        self.parse::<T, JsonFormat>(headers, body)
+    }
+
+    /// `timestamp` was checked to be an integer
+    fn check_timestamp<F: SerialFormat>(
+        &self,
+        timestamp: &str,
+        now: SystemTime,
+    ) -> Result<(), WebhookErr<F>> {
+        let signed_at = timestamp.parse::<i64>().unwrap_or_default();
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        let age_secs = now - signed_at;
+        match age_secs.unsigned_abs() <= self.tolerance.as_secs() {
+            true => Ok(()),
+            false => Err(WebhookErr::OutsideTolerance { age_secs }),
+        }
+    }
+}
+
+fn header<'a, F: SerialFormat>(
+    headers: &'a HeaderMap,
+    name: &HeaderName,
+) -> Result<&'a str, WebhookErr<F>> {
+    let value = headers
+        .get(name)
+        .ok_or_else(|| WebhookErr::MissingHeader(name.clone()))?;
+    value
+        .to_str()
+        .map_err(|_| WebhookErr::MalformedHeader(name.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::unix_millis;
+
+    fn hmac_hex(secret: &str, message: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(message.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Event {
+        id: String,
+    }
+
+    #[test]
+    fn test_verify_webhooks() -> anyhow::Result<()> {
+        let body = r#"{"id":"evt_1"}"#;
+        let now = (unix_millis().parse::<u64>()? / 1000).to_string();
+        let stripe = |timestamp: &str, body: &str| -> anyhow::Result<HeaderMap> {
+            let signature = hmac_hex("whsec", &format!("{timestamp}.{body}"));
+            let value = format!("t={timestamp},v1=deadbeef,v1={signature}");
+            let mut headers = HeaderMap::new();
+            headers.insert("stripe-signature", value.parse()?);
+            Ok(headers)
+        };
+        let verifier = WebhookVerifier::stripe("whsec");
+
+        let event: Event = verifier.parse_json(&stripe(&now, body)?, body.as_bytes())?;
+        assert_eq!(event.id, "evt_1");
+        let tampered = verifier.verify(&stripe(&now, body)?, br#"{"id":"evt_2"}"#);
+        assert!(matches!(tampered, Err(WebhookErr::InvalidSignature)));
+        let replayed = verifier.verify(&stripe("1700000000", body)?, body.as_bytes());
+        assert!(matches!(replayed, Err(WebhookErr::OutsideTolerance { .. })));
+        let unsigned = verifier.verify(&HeaderMap::new(), body.as_bytes());
+        assert!(matches!(unsigned, Err(WebhookErr::MissingHeader(_))));
+
+        let mut headers = HeaderMap::new();
+        let signature = format!("sha256={}", hmac_hex("gh-secret", body));
+        headers.insert("x-hub-signature-256", signature.parse()?);
+        WebhookVerifier::github("gh-secret").verify(&headers, body.as_bytes())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use crate::error::aliases::JsonClientResult;
+use crate::error::{ClientErr, ExecuteErr};
+use crate::middleware::{ApiMiddleware, Next};
+use crate::status_errors::ErrorBody;
+use crate::{ApiRequestBuilder, ToRequestClient};
+use futures::future::BoxFuture;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use reqwest::{Request, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WsErr {
+    #[error("websocket error: {0}")]
+    Socket(#[from] tungstenite::Error),
+    #[error("failed serializing message: {0}")]
+    Serialize(serde_json::Error),
+    #[error("failed deserializing message: {error}, message: {message}")]
+    Deserialize {
+        message: String,
+        error: serde_json::Error,
+    },
+}
+
+/// A websocket carrying JSON messages: a Stream of `In` and a Sink of `Out`.
+/// Pings are answered and skipped, the stream ends when the server closes the connection.
+pub struct WsConnection<In, Out> {
+    socket: Socket,
+    _marker: PhantomData<fn(Out) -> In>,
+}
+impl<In: DeserializeOwned, Out: Serialize> WsConnection<In, Out> {
+    pub fn new(socket: Socket) -> Self {
+        Self {
+            socket,
+            _marker: PhantomData,
+        }
+    }
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+    pub async fn close(mut self) -> Result<(), WsErr> {
+        Ok(self.socket.close(None).await?)
+    }
+}
+impl<In: DeserializeOwned, Out> Stream for WsConnection<In, Out> {
+    type Item = Result<In, WsErr>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match ready!(self.socket.poll_next_unpin(cx)) {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => return Poll::Ready(None),
+            };
+            let parsed = match &message {
+                Message::Text(text) => serde_json::from_str(text),
+                Message::Binary(bytes) => serde_json::from_slice(bytes),
+                Message::Close(_) => return Poll::Ready(None),
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+            };
+            return Poll::Ready(Some(parsed.map_err(|error| WsErr::Deserialize {
+                message: message.to_string(),
+                error,
+            })));
+        }
+    }
+}
+impl<In, Out: Serialize> Sink<&Out> for WsConnection<In, Out> {
+    type Error = WsErr;
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), WsErr>> {
+        self.socket.poll_ready_unpin(cx).map_err(WsErr::from)
+    }
+    fn start_send(mut self: Pin<&mut Self>, item: &Out) -> Result<(), WsErr> {
+        let text = serde_json::to_string(item).map_err(WsErr::Serialize)?;
+        Ok(self.socket.start_send_unpin(Message::Text(text))?)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), WsErr>> {
+        self.socket.poll_flush_unpin(cx).map_err(WsErr::from)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), WsErr>> {
+        self.socket.poll_close_unpin(cx).map_err(WsErr::from)
+    }
+}
+
+/// Ends the middleware chain with the websocket handshake instead of a plain http call,
+/// so the upgrade request carries the same headers, auth and signature as REST calls
+#[derive(Default)]
+struct WsHandshake {
+    socket: Mutex<Option<Socket>>,
+}
+impl ApiMiddleware for WsHandshake {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        _next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+        Box::pin(async move {
+            let mut url = request.url().clone();
+            let scheme = match url.scheme() {
+                "https" | "wss" => "wss",
+                _ => "ws",
+            };
+            let _ = url.set_scheme(scheme);
+            let mut ws_request = url
+                .as_str()
+                .into_client_request()
+                .map_err(|e| ExecuteErr::Middleware(e.into()))?;
+            for (name, value) in request.headers() {
+                if !ws_request.headers().contains_key(name) {
+                    ws_request.headers_mut().insert(name, value.clone());
+                }
+            }
+
+            let response = match tokio_tungstenite::connect_async(ws_request).await {
+                Ok((socket, response)) => {
+                    *self.socket.lock().unwrap_or_else(|e| e.into_inner()) = Some(socket);
+                    response.map(|_| vec![])
+                }
+                // rejected upgrade, handed back as a regular error response
+                Err(tungstenite::Error::Http(response)) => {
+                    response.map(|body| body.unwrap_or_default())
+                }
+                Err(e) => return Err(ExecuteErr::Middleware(e.into())),
+            };
+            Ok(Response::from(response))
+        })
+    }
+}
+
+impl ApiRequestBuilder {
+    /// Upgrade this request to a websocket, after running it through the client's middlewares
+    pub async fn connect_ws<In, Out, ErrResp>(
+        self,
+    ) -> JsonClientResult<WsConnection<In, Out>, ErrResp>
+    where
+        In: DeserializeOwned,
+        Out: Serialize,
+        ErrResp: ErrorBody,
+    {
+        let handshake = Arc::new(WsHandshake::default());
+        let request = ToRequestClient::try_into(self.with_middleware(handshake.clone()))
+            .map_err(ClientErr::BuildRequest)?;
+        let sent = request.sent();
+
+        let response = request.send().await?;
+        let got_status = response.status();
+        if got_status != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(ClientErr::from_error_response(sent, response).await);
+        }
+        let socket = handshake
+            .socket
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let socket = socket.ok_or_else(|| {
+            ClientErr::middleware(anyhow::anyhow!(
+                "a middleware answered the websocket upgrade itself"
+            ))
+        })?;
+        Ok(WsConnection::new(socket))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use serde::Deserialize;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Ping {
+        n: u32,
+    }
+
+    struct LocalApi {
+        base_url: String,
+        http_client: reqwest::Client,
+    }
+    impl JsonApiClient for LocalApi {
+        fn base_url(&self) -> &str {
+            &self.base_url
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+    }
+
+    /// echoes messages back, only accepting upgrades carrying the expected token
+    #[allow(clippy::result_large_err)] // callback signature imposed by tungstenite
+    async fn echo_server() -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let check_auth = |request: &Request, response: Response| match request
+                    .headers()
+                    .get("authorization")
+                {
+                    Some(value) if value == "Bearer secret" => Ok(response),
+                    _ => {
+                        let mut rejection =
+                            ErrorResponse::new(Some(r#"{"error":"unauthorized"}"#.into()));
+                        *rejection.status_mut() = StatusCode::UNAUTHORIZED;
+                        Err(rejection)
+                    }
+                };
+                let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, check_auth).await
+                else {
+                    continue;
+                };
+                while let Some(Ok(message)) = socket.next().await {
+                    if message.is_text() && socket.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(format!("http://{addr}"))
+    }
+
+    #[tokio::test]
+    async fn test_ws_reuses_client_config() -> anyhow::Result<()> {
+        let api = LocalApi {
+            base_url: echo_server().await?,
+            http_client: reqwest::Client::new(),
+        };
+
+        let mut ws = api
+            .get("/ws")
+            .bearer_auth("secret")
+            .connect_ws::<Ping, Ping, serde_json::Value>()
+            .await?;
+        ws.send(&Ping { n: 1 }).await?;
+        assert_eq!(ws.next().await.transpose()?, Some(Ping { n: 1 }));
+        ws.close().await?;
+
+        let rejected = api.ws::<Ping, Ping, serde_json::Value>("/ws").await;
+        let Err(ClientErr::ErrorResponse { context, err_body }) = rejected else {
+            panic!("expected the upgrade to be rejected");
+        };
+        assert_eq!(context.got_status, StatusCode::UNAUTHORIZED);
+        assert_eq!(err_body["error"], "unauthorized");
+        Ok(())
+    }
+}
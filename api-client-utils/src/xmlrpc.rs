@@ -0,0 +1,371 @@
+use crate::error::ClientErr;
+use crate::serialization_formats::{ApiFormat, SerialFormat};
+use crate::{ApiClient, ReceiveResp, RequestClient, ToRequestClient};
+use reqwest::RequestBuilder;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::fmt::Write;
+use std::future::Future;
+use xml::escape::escape_str_pcdata;
+use xml::reader::{EventReader, XmlEvent};
+
+/// XML-RPC bodies, read into the JSON data model: structs become objects, arrays arrays,
+/// `dateTime.iso8601` and `base64` stay strings. Faults are read as the error body.
+#[derive(Debug)]
+pub struct XmlRpcFormat;
+impl SerialFormat for XmlRpcFormat {
+    type Error = serde_xml_rs::Error;
+    fn from_str<T: for<'a> Deserialize<'a>>(input: &str) -> Result<T, Self::Error> {
+        let value = match MethodResponse::parse(input)? {
+            MethodResponse::Params(value) | MethodResponse::Fault(value) => value,
+        };
+        serde_json::from_value(value).map_err(serde_xml_rs::Error::custom)
+    }
+}
+impl ApiFormat for XmlRpcFormat {
+    fn with_accept_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Accept", "text/xml")
+    }
+    fn with_content_type_header(builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Content-Type", "text/xml")
+    }
+}
+
+/// `<fault>` of a methodResponse, returned as ClientErr::ErrorResponse
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct XmlRpcFault {
+    #[serde(rename = "faultCode")]
+    pub code: i64,
+    #[serde(rename = "faultString")]
+    pub message: String,
+}
+impl std::fmt::Display for XmlRpcFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "XML-RPC fault {}: {}", self.code, self.message)
+    }
+}
+
+/// XML-RPC on top of an `ApiClient<XmlRpcFormat>`: `impl XmlRpcClient for MyApi {}`.
+/// Faults come back as ClientErr::ErrorResponse, whatever the HTTP status.
+pub trait XmlRpcClient: ApiClient<XmlRpcFormat> {
+    /// endpoint path, relative to base_url
+    fn rpc_path(&self) -> &str {
+        "/RPC2"
+    }
+
+    /// `params` serializes to a sequence (tuple, array, Vec...), one `<param>` per element
+    fn call<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> impl Future<Output = Result<R, ClientErr<XmlRpcFault, XmlRpcFormat>>> {
+        let request = self.post(self.rpc_path()).body(method_call(method, params));
+        async move {
+            let context =
+                ReceiveResp::<XmlRpcFormat>::expect_success::<XmlRpcFault>(XmlRpcRequest(request))
+                    .await?;
+            let response = match MethodResponse::parse(&context.response_text) {
+                Ok(response) => response,
+                Err(deserialize_error) => {
+                    return Err(ClientErr::DeserializeError {
+                        context: Box::new(context),
+                        deserialize_error,
+                    })
+                }
+            };
+            let context = Box::new(context);
+            match response {
+                MethodResponse::Params(value) => {
+                    serde_json::from_value(value).map_err(|e| ClientErr::DeserializeError {
+                        context,
+                        deserialize_error: serde_xml_rs::Error::custom(e),
+                    })
+                }
+                MethodResponse::Fault(value) => Err(match serde_json::from_value(value) {
+                    Ok(err_body) => ClientErr::ErrorResponse { context, err_body },
+                    Err(_) => ClientErr::UnparsedErrorResponse {
+                        status: context.got_status,
+                        context,
+                    },
+                }),
+            }
+        }
+    }
+}
+
+// wrapper so that ReceiveResp<XmlRpcFormat> doesn't make JSON calls ambiguous
+struct XmlRpcRequest<T>(T);
+impl<T: ToRequestClient> ToRequestClient for XmlRpcRequest<T> {
+    fn try_into(self) -> Result<RequestClient, reqwest::Error> {
+        self.0.try_into()
+    }
+}
+impl<T: ToRequestClient> ReceiveResp<XmlRpcFormat> for XmlRpcRequest<T> {}
+
+/// `params` is a sequence, anything else is sent as a single param
+fn method_call(method: &str, params: impl Serialize) -> String {
+    // params that don't serialize are sent as none, the server rejects the call
+    let params = match serde_json::to_value(params).unwrap_or_default() {
+        Value::Array(params) => params,
+        Value::Null => vec![],
+        param => vec![param],
+    };
+    let mut body = String::from(r#"<?xml version="1.0"?><methodCall>"#);
+    let method = escape_str_pcdata(method);
+    body.push_str(&format!("<methodName>{method}</methodName><params>"));
+    for param in &params {
+        body.push_str("<param>");
+        // writing to a String can't fail
+        let _ = write_value(&mut body, param);
+        body.push_str("</param>");
+    }
+    body.push_str("</params></methodCall>");
+    body
+}
+
+fn write_value(out: &mut String, value: &Value) -> std::fmt::Result {
+    out.push_str("<value>");
+    match value {
+        Value::Null => out.push_str("<nil/>"),
+        Value::Bool(b) => write!(out, "<boolean>{}</boolean>", u8::from(*b))?,
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) if i32::try_from(i).is_ok() => write!(out, "<int>{i}</int>")?,
+            // not in the spec, but understood by the Apache and Python implementations
+            (Some(i), _) => write!(out, "<i8>{i}</i8>")?,
+            (None, Some(f)) => write!(out, "<double>{f}</double>")?,
+            (None, None) => write!(out, "<string>{n}</string>")?,
+        },
+        Value::String(s) => write!(out, "<string>{}</string>", escape_str_pcdata(s))?,
+        Value::Array(items) => {
+            out.push_str("<array><data>");
+            for item in items {
+                write_value(out, item)?;
+            }
+            out.push_str("</data></array>");
+        }
+        Value::Object(members) => {
+            out.push_str("<struct>");
+            for (name, value) in members {
+                write!(out, "<member><name>{}</name>", escape_str_pcdata(name))?;
+                write_value(out, value)?;
+                out.push_str("</member>");
+            }
+            out.push_str("</struct>");
+        }
+    }
+    out.push_str("</value>");
+    Ok(())
+}
+
+enum MethodResponse {
+    Params(Value),
+    Fault(Value),
+}
+impl MethodResponse {
+    fn parse(input: &str) -> Result<Self, serde_xml_rs::Error> {
+        let root = Element::parse(input)?;
+        if root.name != "methodResponse" {
+            return Err(malformed(&format!(
+                "<{}> instead of <methodResponse>",
+                root.name
+            )));
+        }
+        if let Some(fault) = root.child("fault") {
+            let value = fault
+                .child("value")
+                .ok_or_else(|| malformed("empty <fault>"))?;
+            return Ok(MethodResponse::Fault(value.to_json()?));
+        }
+        let value = root
+            .child("params")
+            .and_then(|params| params.child("param"))
+            .and_then(|param| param.child("value"));
+        match value {
+            Some(value) => Ok(MethodResponse::Params(value.to_json()?)),
+            // a method returning nothing
+            None => Ok(MethodResponse::Params(Value::Null)),
+        }
+    }
+}
+
+fn malformed(what: &str) -> serde_xml_rs::Error {
+    serde_xml_rs::Error::custom(format!("malformed XML-RPC: {what}"))
+}
+
+/// just enough DOM to walk XML-RPC values
+struct Element {
+    name: String,
+    children: Vec<Element>,
+    text: String,
+}
+impl Element {
+    fn parse(input: &str) -> Result<Self, serde_xml_rs::Error> {
+        let mut stack: Vec<Element> = vec![];
+        for event in EventReader::from_str(input) {
+            match event? {
+                XmlEvent::StartElement { name, .. } => stack.push(Element {
+                    name: name.local_name,
+                    children: vec![],
+                    text: String::new(),
+                }),
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(current) = stack.last_mut() {
+                        current.text.push_str(&text);
+                    }
+                }
+                XmlEvent::EndElement { .. } => {
+                    let done = stack.pop().ok_or_else(|| malformed("unbalanced tags"))?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(done),
+                        None => return Ok(done),
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(malformed("no root element"))
+    }
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// `self` is a `<value>`
+    fn to_json(&self) -> Result<Value, serde_xml_rs::Error> {
+        let Some(typed) = self.children.first() else {
+            // untyped values are strings
+            return Ok(Value::String(self.text.clone()));
+        };
+        let text = typed.text.trim();
+        let parse_err = |ty: &str| malformed(&format!("<{ty}>{text}</{ty}>"));
+        Ok(match typed.name.as_str() {
+            "int" | "i4" | "i8" => Value::from(text.parse::<i64>().map_err(|_| parse_err("int"))?),
+            "boolean" => Value::Bool(match text {
+                "1" => true,
+                "0" => false,
+                _ => return Err(parse_err("boolean")),
+            }),
+            "double" => text
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| parse_err("double"))?,
+            "string" => Value::String(typed.text.clone()),
+            "dateTime.iso8601" | "base64" => Value::String(text.to_string()),
+            "nil" => Value::Null,
+            "array" => {
+                let data = typed.child("data").map(|d| d.children.as_slice());
+                let items = data.unwrap_or_default().iter().map(Element::to_json);
+                Value::Array(items.collect::<Result<_, _>>()?)
+            }
+            "struct" => {
+                let mut members = Map::new();
+                for member in &typed.children {
+                    let name = member.child("name").map(|n| n.text.clone());
+                    let value = member.child("value");
+                    let (Some(name), Some(value)) = (name, value) else {
+                        return Err(malformed("<member> without name or value"));
+                    };
+                    members.insert(name, value.to_json()?);
+                }
+                Value::Object(members)
+            }
+            other => return Err(malformed(&format!("unknown type <{other}>"))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecuteErr;
+    use crate::middleware::tests::stub_response;
+    use crate::middleware::{ApiMiddleware, Next};
+    use crate::prelude::*;
+    use futures::future::BoxFuture;
+    use reqwest::{Request, Response};
+    use std::sync::Arc;
+
+    /// `pets.get` answers for pet 1, faults otherwise
+    struct PetRpc;
+    impl ApiMiddleware for PetRpc {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, ExecuteErr>> {
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(String::from_utf8_lossy)
+                .unwrap_or_default();
+            let response = match body.contains("<int>1</int>") {
+                true => "<?xml version=\"1.0\"?><methodResponse><params><param><value><struct>
+                    <member><name>name</name><value><string>rex &amp; co</string></value></member>
+                    <member><name>tags</name><value><array><data><value>good</value><value><i4>7</i4></value></data></array></value></member>
+                    <member><name>vaccinated</name><value><boolean>1</boolean></value></member>
+                </struct></value></param></params></methodResponse>",
+                false => "<?xml version=\"1.0\"?><methodResponse><fault><value><struct>
+                    <member><name>faultCode</name><value><int>404</int></value></member>
+                    <member><name>faultString</name><value><string>no such pet</string></value></member>
+                </struct></value></fault></methodResponse>",
+            };
+            Box::pin(async move { stub_response(200, response) })
+        }
+    }
+
+    struct PetApi {
+        http_client: reqwest::Client,
+        middlewares: Vec<Arc<dyn ApiMiddleware>>,
+    }
+    impl ApiClient<XmlRpcFormat> for PetApi {
+        fn base_url(&self) -> &str {
+            "http://localhost"
+        }
+        fn http_client(&self) -> &reqwest::Client {
+            &self.http_client
+        }
+        fn middlewares(&self) -> &[Arc<dyn ApiMiddleware>] {
+            &self.middlewares
+        }
+    }
+    impl XmlRpcClient for PetApi {}
+
+    #[test]
+    fn test_method_call() -> anyhow::Result<()> {
+        let body = method_call("pets.find", ("rex & co", 3, [true], None::<u8>));
+        assert!(body.contains("<methodName>pets.find</methodName>"));
+        assert!(body.contains("<param><value><string>rex &amp; co</string></value></param>"));
+        assert!(body.contains("<param><value><int>3</int></value></param>"));
+        assert!(body.contains("<array><data><value><boolean>1</boolean></value></data></array>"));
+        assert!(body.contains("<nil/>"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xml_rpc_call() -> anyhow::Result<()> {
+        let api = PetApi {
+            http_client: reqwest::Client::new(),
+            middlewares: vec![Arc::new(PetRpc)],
+        };
+
+        let pet: Value = api.call("pets.get", (1,)).await?;
+        assert_eq!(
+            pet,
+            serde_json::json!({ "name": "rex & co", "tags": ["good", 7], "vaccinated": true })
+        );
+        let fault = api
+            .call::<Value>("pets.get", (2,))
+            .await
+            .try_into_err_resp(reqwest::StatusCode::OK)?;
+        assert_eq!(
+            fault,
+            XmlRpcFault {
+                code: 404,
+                message: "no such pet".to_string()
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::meta::EntryMeta;
+use crate::{io, StaticCacheDir};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+/// Where entries are stored, below the `FileBytes` serialization and the ttl/checksum/version
+/// logic of `ttl::load`: a backend only keeps bytes and their metadata under a key.
+/// Keys are `<namespace>/<file_id>`, see `FileBytes::namespace`.
+pub trait CacheBackend: Send + Sync {
+    /// what CacheStats are counted under: the cache dir, database file or bucket url
+    fn location(&self) -> PathBuf;
+
+    /// None if there's no entry, the metadata None for entries written without
+    fn read(
+        &self,
+        key: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<(Vec<u8>, Option<EntryMeta>)>>> + Send;
+    /// replace the entry and its metadata
+    fn write(
+        &self,
+        key: &str,
+        bytes: &[u8],
+        meta: &EntryMeta,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+    /// no error if there's no entry
+    fn remove(&self, key: &str) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// One file per entry under a dir, its metadata in a `.meta` sidecar: what `FromFileOrNew` uses.
+/// Both are renamed into place once written, the sidecar first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsBackend {
+    dir: PathBuf,
+}
+impl FsBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+    pub fn of<CacheDir: StaticCacheDir>() -> anyhow::Result<Self> {
+        Ok(Self::new(CacheDir::cache_dir()?))
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    /// the file of the entry `key`
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+}
+impl CacheBackend for FsBackend {
+    fn location(&self) -> PathBuf {
+        self.dir.clone()
+    }
+
+    async fn read(&self, key: &str) -> anyhow::Result<Option<(Vec<u8>, Option<EntryMeta>)>> {
+        let path = self.path(key);
+        if !io::exists(&path).await? {
+            return Ok(None);
+        }
+        let meta = EntryMeta::load(&path).await?;
+        let bytes = io::read(&path).await?;
+        // read between the writes of another sidecar and entry: both are there on a second read
+        if let Some(Err(_)) = meta.as_ref().map(|meta| meta.verify(&path, &bytes)) {
+            let meta = EntryMeta::load(&path).await?;
+            return Ok(Some((io::read(&path).await?, meta)));
+        }
+        Ok(Some((bytes, meta)))
+    }
+    async fn write(&self, key: &str, bytes: &[u8], meta: &EntryMeta) -> anyhow::Result<()> {
+        let path = self.path(key);
+        io::create_cache_dir(&self.dir).await?;
+        if let Some(dir) = path.parent() {
+            io::create_dir_all(dir).await?;
+        }
+        meta.save(&path).await?;
+        io::write(&path, bytes).await?;
+        Ok(())
+    }
+    async fn remove(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.path(key);
+        io::remove(&path).await?;
+        io::remove(&EntryMeta::path(&path)).await?;
+        Ok(())
+    }
+}
+
+/// A shared `remote` backend with a `local` one in front: reads try local first, then remote,
+/// copying what they find to local. Writes and removals go to both.
+/// e.g. CI jobs sharing a cache through S3, each keeping its own copy on disk.
+#[derive(Debug, Clone)]
+pub struct WriteThrough<Local, Remote> {
+    pub local: Local,
+    pub remote: Remote,
+}
+impl<Local: CacheBackend, Remote: CacheBackend> WriteThrough<Local, Remote> {
+    pub fn new(local: Local, remote: Remote) -> Self {
+        Self { local, remote }
+    }
+}
+impl<Local: CacheBackend, Remote: CacheBackend> CacheBackend for WriteThrough<Local, Remote> {
+    /// the local one's, what's stored on this machine
+    fn location(&self) -> PathBuf {
+        self.local.location()
+    }
+
+    async fn read(&self, key: &str) -> anyhow::Result<Option<(Vec<u8>, Option<EntryMeta>)>> {
+        let local = self.local.read(key).await?;
+        let expired = |meta: &Option<EntryMeta>| meta.as_ref().is_some_and(EntryMeta::is_expired);
+        if let Some((bytes, meta)) = local.filter(|(_, meta)| !expired(meta)) {
+            return Ok(Some((bytes, meta)));
+        }
+        // another machine may have made a fresher one
+        let remote = self.remote.read(key).await?;
+        if let Some((bytes, Some(meta))) = &remote {
+            self.local.write(key, bytes, meta).await?;
+        }
+        Ok(remote)
+    }
+    async fn write(&self, key: &str, bytes: &[u8], meta: &EntryMeta) -> anyhow::Result<()> {
+        self.local.write(key, bytes, meta).await?;
+        self.remote.write(key, bytes, meta).await
+    }
+    async fn remove(&self, key: &str) -> anyhow::Result<()> {
+        self.local.remove(key).await?;
+        self.remote.remove(key).await
+    }
+}
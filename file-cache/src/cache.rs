@@ -0,0 +1,143 @@
+use crate::backend::{CacheBackend, FsBackend};
+use crate::{ttl, CacheLocation, FileBytes, StaticCacheDir};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where a `Cache` keeps its entries and for how long, decided at runtime, e.g. from a `--cache-dir` flag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub dir: PathBuf,
+    /// None: entries are valid until deleted
+    pub ttl: Option<Duration>,
+}
+impl CacheConfig {
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: None,
+        }
+    }
+    /// `CacheConfig::of::<GitRepoCacheDir>()`
+    pub fn of<CacheDir: StaticCacheDir>() -> anyhow::Result<Self> {
+        Ok(Self::in_dir(CacheDir::cache_dir()?))
+    }
+    /// `CacheConfig::at(&args.cache_dir)`, or any other CacheLocation
+    pub fn at(location: &dyn CacheLocation) -> anyhow::Result<Self> {
+        Ok(Self::in_dir(location.dir()?))
+    }
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..self
+        }
+    }
+}
+
+impl CacheLocation for CacheConfig {
+    fn dir(&self) -> anyhow::Result<PathBuf> {
+        Ok(self.dir.clone())
+    }
+}
+
+/// The entries of type T in a backend, as a value rather than the `FromFileOrNew` trait.
+/// With the default FsBackend, same files as the trait: an entry written by one is read by the other.
+#[derive(Debug, Clone)]
+pub struct Cache<T, Backend = FsBackend> {
+    backend: Backend,
+    ttl: Option<Duration>,
+    _entry: PhantomData<fn() -> T>,
+}
+impl<T: FileBytes> Cache<T> {
+    pub fn new(config: CacheConfig) -> Self {
+        Self::with_backend(FsBackend::new(config.dir), config.ttl)
+    }
+    /// where the entry `key` is kept, under T's namespace
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.backend.path(&ttl::entry_key::<T>(key))
+    }
+}
+impl<T: FileBytes, Backend: CacheBackend> Cache<T, Backend> {
+    pub fn with_backend(backend: Backend, ttl: Option<Duration>) -> Self {
+        Self {
+            backend,
+            ttl,
+            _entry: PhantomData,
+        }
+    }
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// None if missing, expired, or of another version that doesn't migrate
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<T>> {
+        ttl::load(&self.backend, &ttl::entry_key::<T>(key)).await
+    }
+    /// replace the entry, its ttl counted from now
+    pub async fn put(&self, key: &str, entry: &T) -> anyhow::Result<()> {
+        ttl::save(&self.backend, &ttl::entry_key::<T>(key), self.ttl, entry).await
+    }
+    /// like FromFileOrNew::from_file_or_save_new
+    pub async fn get_or_insert_with<Fut, E>(&self, key: &str, make_new: Fut) -> anyhow::Result<T>
+    where
+        Fut: Future<Output = Result<T, E>> + Send,
+        anyhow::Error: From<E>,
+    {
+        if let Some(entry) = self.get(key).await? {
+            return Ok(entry);
+        }
+        let new = make_new.await.map_err(anyhow::Error::from)?;
+        self.put(key, &new).await?;
+        Ok(new)
+    }
+    /// delete the entry and its metadata, no error if there's none
+    pub async fn invalidate(&self, key: &str) -> anyhow::Result<()> {
+        self.backend.remove(&ttl::entry_key::<T>(key)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCacheDir;
+    use crate::FromFileOrNew;
+    use std::convert::Infallible;
+
+    #[derive(Debug, PartialEq)]
+    struct Quote(String);
+    impl FileBytes for Quote {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Quote(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+    impl FromFileOrNew<TestCacheDir> for Quote {}
+
+    #[tokio::test]
+    async fn test_cache_handle() -> anyhow::Result<()> {
+        let quote = |text: &str| {
+            let quote = Quote(text.to_string());
+            async move { Ok::<_, Infallible>(quote) }
+        };
+        let cache = Cache::<Quote>::new(CacheConfig::of::<TestCacheDir>()?);
+        assert_eq!(cache.get("quote").await?, None);
+        cache.put("quote", &Quote("a".into())).await?;
+        assert_eq!(cache.get_or_insert_with("quote", quote("b")).await?.0, "a");
+
+        // the trait reads what the handle wrote
+        let from_trait =
+            <Quote as FromFileOrNew<TestCacheDir>>::from_file_or_save_new("quote", quote("c"));
+        assert_eq!(from_trait.await?.0, "a");
+
+        cache.invalidate("quote").await?;
+        assert_eq!(cache.get_or_insert_with("quote", quote("d")).await?.0, "d");
+
+        let expiring = Cache::<Quote>::new(CacheConfig::of::<TestCacheDir>()?.ttl(Duration::ZERO));
+        expiring.put("quote", &Quote("e".into())).await?;
+        assert_eq!(expiring.get("quote").await?, None);
+        Ok(())
+    }
+}
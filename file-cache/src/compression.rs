@@ -0,0 +1,110 @@
+use crate::FileBytes;
+use std::borrow::Cow;
+
+/// the first bytes of any zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// An entry stored zstd-compressed: `Compressed<Responses>` in place of `Responses`.
+/// Entries written uncompressed before still load, told apart by the zstd magic bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Compressed<T>(pub T);
+impl<T> Compressed<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T> std::ops::Deref for Compressed<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T: FileBytes> FileBytes for Compressed<T> {
+    const VERSION: u32 = T::VERSION;
+
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = self.0.as_file_bytes()?;
+        Ok(zstd::encode_all(
+            &bytes[..],
+            zstd::DEFAULT_COMPRESSION_LEVEL,
+        )?)
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        T::from_file_bytes(&decompress(bytes)?).map(Compressed)
+    }
+    fn migrate(old_version: u32, bytes: &[u8]) -> anyhow::Result<Self> {
+        T::migrate(old_version, &decompress(bytes)?).map(Compressed)
+    }
+}
+
+/// the bytes as they are if not zstd-compressed
+fn decompress(bytes: &[u8]) -> anyhow::Result<Cow<'_, [u8]>> {
+    match bytes.starts_with(&ZSTD_MAGIC) {
+        true => Ok(Cow::Owned(zstd::decode_all(bytes)?)),
+        false => Ok(Cow::Borrowed(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache_counter::CacheCounter;
+    use crate::TempCacheDir;
+
+    #[test]
+    fn test_compressed_entries() -> anyhow::Result<()> {
+        let counter = Compressed(CacheCounter(1_000_000));
+        let bytes = counter.as_file_bytes()?;
+        assert!(bytes.starts_with(&ZSTD_MAGIC));
+        assert_eq!(
+            Compressed::<CacheCounter>::from_file_bytes(&bytes)?.0 .0,
+            1_000_000
+        );
+
+        let legacy = CacheCounter(7).as_file_bytes()?;
+        assert_eq!(
+            Compressed::<CacheCounter>::from_file_bytes(&legacy)?.0 .0,
+            7
+        );
+        Ok(())
+    }
+
+    /// a count of 1 when it was a bare name in version 1
+    #[derive(Debug, PartialEq)]
+    struct Visits(String, u32);
+    impl FileBytes for Visits {
+        const VERSION: u32 = 2;
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(format!("{};{}", self.0, self.1).into_bytes())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            let text = String::from_utf8(bytes.to_vec())?;
+            let (name, count) = text.split_once(';').ok_or(anyhow::anyhow!("no count"))?;
+            Ok(Visits(name.to_string(), count.parse()?))
+        }
+        fn migrate(old_version: u32, bytes: &[u8]) -> anyhow::Result<Self> {
+            anyhow::ensure!(old_version == 1, "no migration from {old_version}");
+            Ok(Visits(String::from_utf8(bytes.to_vec())?, 1))
+        }
+    }
+    struct VisitsV1(String);
+    impl FileBytes for VisitsV1 {
+        const VERSION: u32 = 1;
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(VisitsV1(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+
+    #[test]
+    fn test_compressed_migration() -> anyhow::Result<()> {
+        let temp = TempCacheDir::new()?;
+        let path = temp.path().join("visits");
+        Compressed(VisitsV1("alice".into())).to_file(&path)?;
+        let migrated = Compressed::<Visits>::from_file(&path)?;
+        assert_eq!(migrated.0, Visits("alice".into(), 1));
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::FileBytes;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::marker::PhantomData;
+
+/// prefix of encrypted entries, before the nonce
+const MAGIC: &[u8; 4] = b"fce1";
+const NONCE_LEN: usize = 24;
+
+/// Where the 32 bytes key of encrypted entries comes from, e.g. an OS keyring
+pub trait CacheKey {
+    fn key() -> anyhow::Result<[u8; 32]>;
+}
+/// The key in the `FILE_CACHE_KEY` env var, as 64 hex chars: `openssl rand -hex 32`
+pub struct EnvKey;
+impl CacheKey for EnvKey {
+    fn key() -> anyhow::Result<[u8; 32]> {
+        let hex_key =
+            std::env::var("FILE_CACHE_KEY").map_err(|e| anyhow::anyhow!("FILE_CACHE_KEY: {e}"))?;
+        let key = hex::decode(hex_key.trim())?;
+        key.try_into()
+            .map_err(|_| anyhow::anyhow!("FILE_CACHE_KEY must be 32 bytes, 64 hex chars"))
+    }
+}
+
+/// An entry encrypted at rest with XChaCha20-Poly1305, for tokens and other secrets:
+/// `Encrypted<ApiToken>` in place of `ApiToken`. A random nonce is drawn at every write.
+/// Entries that aren't encrypted, or were tampered with, fail to load rather than being trusted.
+pub struct Encrypted<T, Key = EnvKey>(pub T, PhantomData<Key>);
+impl<T, Key> Encrypted<T, Key> {
+    pub fn new(value: T) -> Self {
+        Self(value, PhantomData)
+    }
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T, Key> std::ops::Deref for Encrypted<T, Key> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T: std::fmt::Debug, Key> std::fmt::Debug for Encrypted<T, Key> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Encrypted").field(&self.0).finish()
+    }
+}
+impl<T: FileBytes, Key: CacheKey> FileBytes for Encrypted<T, Key> {
+    const VERSION: u32 = T::VERSION;
+
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let cipher = XChaCha20Poly1305::new(&Key::key()?.into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = self.0.as_file_bytes()?;
+        let ciphertext = cipher
+            .encrypt(&nonce, &plaintext[..])
+            .map_err(|_| anyhow::anyhow!("failed encrypting cache entry"))?;
+        Ok([&MAGIC[..], &nonce, &ciphertext].concat())
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        T::from_file_bytes(&decrypt::<Key>(bytes)?).map(Self::new)
+    }
+    fn migrate(old_version: u32, bytes: &[u8]) -> anyhow::Result<Self> {
+        T::migrate(old_version, &decrypt::<Key>(bytes)?).map(Self::new)
+    }
+}
+
+/// the plaintext of an entry written by Encrypted
+fn decrypt<Key: CacheKey>(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let rest = bytes
+        .strip_prefix(&MAGIC[..])
+        .ok_or_else(|| anyhow::anyhow!("cache entry isn't encrypted"))?;
+    if rest.len() < NONCE_LEN {
+        anyhow::bail!("encrypted cache entry is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(&Key::key()?.into());
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("cache entry doesn't decrypt: wrong key or tampered with"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache_counter::CacheCounter;
+    use crate::TempCacheDir;
+
+    struct TestKey;
+    impl CacheKey for TestKey {
+        fn key() -> anyhow::Result<[u8; 32]> {
+            Ok([7; 32])
+        }
+    }
+    struct OtherKey;
+    impl CacheKey for OtherKey {
+        fn key() -> anyhow::Result<[u8; 32]> {
+            Ok([8; 32])
+        }
+    }
+
+    #[test]
+    fn test_encrypted_entries() -> anyhow::Result<()> {
+        let secret = Encrypted::<_, TestKey>::new(CacheCounter(123_456));
+        let bytes = secret.as_file_bytes()?;
+        assert!(!String::from_utf8_lossy(&bytes).contains("123456"));
+        assert_ne!(bytes, secret.as_file_bytes()?, "a new nonce every write");
+        assert_eq!(
+            Encrypted::<CacheCounter, TestKey>::from_file_bytes(&bytes)?
+                .0
+                 .0,
+            123_456
+        );
+
+        assert!(Encrypted::<CacheCounter, OtherKey>::from_file_bytes(&bytes).is_err());
+        let mut tampered = bytes.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(Encrypted::<CacheCounter, TestKey>::from_file_bytes(&tampered).is_err());
+        assert!(Encrypted::<CacheCounter, TestKey>::from_file_bytes(b"123456").is_err());
+        Ok(())
+    }
+
+    /// a number of seconds since version 1, minutes before
+    #[derive(Debug, PartialEq)]
+    struct Timeout(u64);
+    impl FileBytes for Timeout {
+        const VERSION: u32 = 1;
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.to_string().into_bytes())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Timeout(String::from_utf8(bytes.to_vec())?.parse()?))
+        }
+        fn migrate(old_version: u32, bytes: &[u8]) -> anyhow::Result<Self> {
+            anyhow::ensure!(old_version == 0, "no migration from {old_version}");
+            Ok(Timeout(Self::from_file_bytes(bytes)?.0 * 60))
+        }
+    }
+
+    #[test]
+    fn test_encrypted_migration() -> anyhow::Result<()> {
+        let temp = TempCacheDir::new()?;
+        let path = temp.path().join("timeout");
+        // written without version before
+        Encrypted::<_, TestKey>::new(CacheCounter(5)).to_file(&path)?;
+        assert_eq!(Encrypted::<Timeout, TestKey>::VERSION, 1);
+        let migrated = Encrypted::<Timeout, TestKey>::from_file(&path)?;
+        assert_eq!(migrated.0, Timeout(300));
+        Ok(())
+    }
+}
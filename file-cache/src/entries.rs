@@ -0,0 +1,147 @@
+use crate::meta::EntryMeta;
+use crate::{io, CacheLocation};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// An entry found on disk, for debugging tools and maintenance like `purge::prune`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    /// the dir of the entry under the cache dir, empty for entries at its root
+    pub namespace: String,
+    pub file_id: String,
+    /// bytes on disk, sidecar included
+    pub size: u64,
+    /// from the sidecar, else the file's last modification
+    pub created_at: SystemTime,
+    /// None where the filesystem doesn't track it
+    pub accessed_at: Option<SystemTime>,
+    /// None for entries written without, and sidecars that don't parse
+    pub meta: Option<EntryMeta>,
+}
+impl CacheEntry {
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.meta.as_ref()?.expires_at()
+    }
+    pub fn is_expired(&self) -> bool {
+        self.meta.as_ref().is_some_and(EntryMeta::is_expired)
+    }
+    /// FileBytes::VERSION of the type that wrote it
+    pub fn version(&self) -> u32 {
+        self.meta.as_ref().map_or(0, |meta| meta.version)
+    }
+}
+
+/// Every entry under the cache dir, sorted by path
+pub fn list_entries(location: &dyn CacheLocation) -> anyhow::Result<Vec<CacheEntry>> {
+    let cache_dir = location.dir()?;
+    Ok(walk(&cache_dir, &cache_dir)?.entries)
+}
+
+/// The entries under `dir`, and the sidecars whose entry is gone
+pub(crate) struct Walk {
+    pub entries: Vec<CacheEntry>,
+    pub orphan_sidecars: Vec<PathBuf>,
+}
+pub(crate) fn walk(cache_dir: &Path, dir: &Path) -> anyhow::Result<Walk> {
+    let mut found = Walk {
+        entries: vec![],
+        orphan_sidecars: vec![],
+    };
+    walk_into(cache_dir, dir, &mut found)?;
+    found.entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+fn walk_into(cache_dir: &Path, dir: &Path, found: &mut Walk) -> anyhow::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        // symlinks are listed, not followed
+        if path.is_dir() && !path.is_symlink() {
+            walk_into(cache_dir, &path, found)?;
+            continue;
+        }
+        if io::is_temp(&path) || io::is_cache_dir_tag(&path) {
+            continue;
+        }
+        if path.extension().is_some_and(|ext| ext == "meta") {
+            if !path.with_extension("").exists() {
+                found.orphan_sidecars.push(path);
+            }
+            continue;
+        }
+        found.entries.push(entry_at(cache_dir, path)?);
+    }
+    Ok(())
+}
+
+fn entry_at(cache_dir: &Path, path: PathBuf) -> anyhow::Result<CacheEntry> {
+    let relative = path
+        .strip_prefix(cache_dir)?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let (namespace, file_id) = relative.split_once('/').unwrap_or(("", &relative));
+    let metadata = fs::symlink_metadata(&path)?;
+    let meta_path = EntryMeta::path(&path);
+    let meta_size = fs::metadata(&meta_path).map_or(0, |meta| meta.len());
+    let meta = EntryMeta::read(&path).ok().flatten();
+    Ok(CacheEntry {
+        namespace: namespace.to_string(),
+        file_id: file_id.to_string(),
+        size: metadata.len() + meta_size,
+        created_at: meta
+            .as_ref()
+            .map_or(metadata.modified()?, |meta| meta.created_at),
+        accessed_at: metadata.accessed().ok(),
+        meta,
+        path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, CacheConfig};
+    use crate::{FileBytes, TempCacheDir};
+    use std::time::Duration;
+
+    struct Report(String);
+    impl FileBytes for Report {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Report(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_entries() -> anyhow::Result<()> {
+        let temp = TempCacheDir::new()?;
+        let config = CacheConfig::at(&temp)?.ttl(Duration::from_secs(60));
+        Cache::<Report>::new(config)
+            .put("q1/sales", &Report("42".into()))
+            .await?;
+        fs::write(temp.path().join("legacy"), "no sidecar")?;
+
+        let entries = list_entries(&temp)?;
+        let ids: Vec<_> = (entries.iter())
+            .map(|e| (e.namespace.as_str(), e.file_id.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            [(Report::namespace().as_str(), "q1/sales"), ("", "legacy")]
+        );
+        let report = &entries[0];
+        assert!(report.size > 2 && !report.is_expired());
+        assert_eq!(
+            report.expires_at(),
+            Some(report.created_at + Duration::from_secs(60))
+        );
+        assert_eq!((entries[1].meta.as_ref(), entries[1].size), (None, 10));
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! File operations of the cache, through tokio::fs with the `tokio` feature so they don't
+//! block the runtime, through std::fs otherwise
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "tokio")]
+use tokio::fs;
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn exists(path: &Path) -> std::io::Result<bool> {
+    fs::try_exists(path).await
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn exists(path: &Path) -> std::io::Result<bool> {
+    path.try_exists()
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn read(path: &Path) -> std::io::Result<Vec<u8>> {
+    fs::read(path).await
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn read(path: &Path) -> std::io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+/// Written to a temp file beside `path` then renamed over it, so that readers see either
+/// the old bytes or the new ones, never a truncated file
+#[cfg(feature = "tokio")]
+pub(crate) async fn write(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    let written = match fs::write(&temp, bytes).await {
+        Ok(()) => fs::rename(&temp, path).await,
+        Err(e) => Err(e),
+    };
+    if written.is_err() {
+        let _ = fs::remove_file(&temp).await;
+    }
+    written
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn write(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    write_blocking(path, bytes)
+}
+/// write, blocking
+pub(crate) fn write_blocking(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    let written = std::fs::write(&temp, bytes).and_then(|()| std::fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
+/// `.<file_name>.<pid>-<n>.tmp`, unique to each write
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{file_name}.{}-{n}.tmp", std::process::id()))
+}
+/// a write in progress, not an entry
+pub(crate) fn is_temp(path: &Path) -> bool {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    file_name.starts_with('.') && file_name.ends_with(".tmp")
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn rename(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to).await
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn rename(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::rename(from, to)
+}
+
+/// Marks the dirs the cache creates, see https://bford.info/cachedir/: purge only clears dirs
+/// that have it or look like a cache by their name, and backup tools skip them
+pub(crate) const CACHE_DIR_TAG: &str = "CACHEDIR.TAG";
+const CACHE_DIR_TAG_CONTENT: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55
+# This file is a cache directory tag created by file-cache.
+# For information about cache directory tags, see https://bford.info/cachedir/
+";
+/// the cache dir with its parents, tagged if it's created now
+pub(crate) async fn create_cache_dir(dir: &Path) -> std::io::Result<()> {
+    if exists(dir).await? {
+        return Ok(());
+    }
+    create_dir_all(dir).await?;
+    write(&dir.join(CACHE_DIR_TAG), CACHE_DIR_TAG_CONTENT).await
+}
+/// the tag of a cache dir, not an entry
+pub(crate) fn is_cache_dir_tag(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == CACHE_DIR_TAG)
+}
+
+/// no error if it's already gone
+#[cfg(feature = "tokio")]
+pub(crate) async fn remove(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn remove(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// with its parents
+#[cfg(feature = "tokio")]
+pub(crate) async fn create_dir_all(path: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(path).await
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn create_dir_all(path: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(path)
+}
+
+/// with its content, no error if it's already gone
+#[cfg(feature = "tokio")]
+pub(crate) async fn remove_dir(path: &Path) -> std::io::Result<()> {
+    match fs::remove_dir_all(path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+#[cfg(not(feature = "tokio"))]
+pub(crate) async fn remove_dir(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,96 @@
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
+
+/// A file_id for the inputs an entry is made from, e.g. the params of the query it caches:
+/// `cache_key(&("users", page, per_page))`. Hex of their sha256, the same on every platform
+/// and across runs, unlike `DefaultHasher`.
+pub fn cache_key(inputs: &impl Hash) -> String {
+    let mut hasher = StableHasher(Sha256::new());
+    inputs.hash(&mut hasher);
+    hex::encode(&hasher.0.finalize()[..16])
+}
+
+/// 8 hex digits of the sha256 of `text`, to tell apart names that look the same once shortened
+pub(crate) fn short_hash(text: &str) -> String {
+    hex::encode(&Sha256::digest(text.as_bytes())[..4])
+}
+
+/// Integers written little-endian whatever the platform's endianness, usize as u64
+struct StableHasher(Sha256);
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16);
+    }
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32);
+    }
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128);
+    }
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+    /// unused, cache_key reads the whole digest
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCacheDir;
+    use crate::{FileBytes, FromFileOrNew};
+    use std::convert::Infallible;
+
+    struct Page(Vec<u8>);
+    impl FileBytes for Page {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.clone())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Page(bytes.to_vec()))
+        }
+    }
+    impl FromFileOrNew<TestCacheDir> for Page {}
+
+    #[tokio::test]
+    async fn test_cache_key() -> anyhow::Result<()> {
+        let key = cache_key(&("users", 2u32, Some("name")));
+        assert_eq!(key, cache_key(&("users", 2u32, Some("name"))));
+        assert_ne!(key, cache_key(&("users", 3u32, Some("name"))));
+        assert_eq!(key.len(), 32);
+
+        let fetch = |byte| async move { Ok::<_, Infallible>(Page(vec![byte])) };
+        let page = |n: u32, byte| async move {
+            let inputs = ("users", n);
+            <Page as FromFileOrNew<TestCacheDir>>::from_inputs_or_new(&inputs, fetch(byte)).await
+        };
+        assert_eq!(page(1, 1).await?.0, [1]);
+        assert_eq!(page(2, 2).await?.0, [2]);
+        assert_eq!(page(1, 3).await?.0, [1]);
+        Ok(())
+    }
+}
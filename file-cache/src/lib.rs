@@ -1,37 +1,138 @@
 use std::convert::Infallible;
-use std::fs;
+use std::ffi::OsString;
 use std::future::Future;
-use std::io::Read;
+use std::hash::Hash;
+use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+pub mod backend;
+pub mod cache;
+#[cfg(feature = "zstd")]
+pub mod compression;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod entries;
+mod io;
+pub mod keys;
+pub mod memo;
+pub mod meta;
+pub mod purge;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub mod text_formats;
+pub mod ttl;
 
 lazy_static::lazy_static! {
   pub static ref GIT_WORK_DIR: Result<PathBuf, String> = GitRepoCacheDir::work_dir();
 }
 pub mod prelude {
-    pub use crate::{FileBytes, FromFileOrNew};
+    pub use crate::backend::{CacheBackend, FsBackend, WriteThrough};
+    pub use crate::cache::{Cache, CacheConfig};
+    #[cfg(feature = "zstd")]
+    pub use crate::compression::Compressed;
+    #[cfg(feature = "encryption")]
+    pub use crate::encryption::{CacheKey, Encrypted};
+    pub use crate::entries::{list_entries, CacheEntry};
+    pub use crate::keys::cache_key;
+    pub use crate::memo::{memoize, memoize_in};
+    pub use crate::meta::{CorruptEntry, EntryMeta};
+    #[cfg(feature = "redis")]
+    pub use crate::redis::RedisBackend;
+    #[cfg(feature = "s3")]
+    pub use crate::s3::S3Backend;
+    #[cfg(feature = "sqlite")]
+    pub use crate::sqlite::SqliteBackend;
+    pub use crate::stats::CacheStats;
+    #[cfg(feature = "toml")]
+    pub use crate::text_formats::Toml;
+    #[cfg(feature = "yaml")]
+    pub use crate::text_formats::Yaml;
+    pub use crate::{
+        from_file_or_save_new_in, CacheLocation, EnvCacheDir, FileBytes, FromFileOrNew,
+    };
 }
 
 pub trait FileBytes: Sized {
+    /// Bump it when the bytes change shape, e.g. a field added to a serialized struct.
+    /// Entries of another version go through `migrate` instead of `from_file_bytes`.
+    const VERSION: u32 = 0;
+
     fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>>;
     fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self>;
 
+    /// Subdirectory of the cache dir holding the entries of this type, so that the same
+    /// file_id used by two types can't collide. By default the type name and a hash of its full
+    /// path, `Compressed-Token-1a2b3c4d`, so that `a::Token` and `b::Token` don't share one.
+    /// Override it to keep entries across a move of the type, or a compiler whose
+    /// `type_name` differs.
+    fn namespace() -> String {
+        let type_name = std::any::type_name::<Self>();
+        format!(
+            "{}-{}",
+            short_type_name(type_name),
+            keys::short_hash(type_name)
+        )
+    }
+
+    /// Read an entry written when VERSION was `old_version`. When it fails, as by default,
+    /// from_file_or_save_new drops the entry and makes a new one, from_file returns the error.
+    fn migrate(old_version: u32, bytes: &[u8]) -> anyhow::Result<Self> {
+        let _ = bytes;
+        anyhow::bail!(
+            "no migration of cache entries from version {old_version} to {}",
+            Self::VERSION
+        )
+    }
+
+    /// Err(CorruptEntry) if the bytes don't match the checksum of the entry's sidecar
     fn from_file(path: &Path) -> anyhow::Result<Self> {
-        let mut file = fs::File::open(path)?;
-        let mut read_data = Vec::new();
-        file.read_to_end(&mut read_data)?;
-        Self::from_file_bytes(&read_data)
+        let (bytes, meta) = meta::read_verified_blocking(path)?;
+        match meta.map_or(0, |meta| meta.version) {
+            version if version == Self::VERSION => Self::from_file_bytes(&bytes),
+            old_version => Self::migrate(old_version, &bytes),
+        }
     }
     fn to_file(&self, path: &Path) -> anyhow::Result<()> {
-        fs::write(path, self.as_file_bytes()?)?;
-        Ok(())
+        meta::rewrite_blocking(path, &self.as_file_bytes()?, Self::VERSION)
     }
 }
 
+/// `a::Compressed<b::Token>` -> `Compressed-Token`: paths dropped, and the characters that
+/// don't belong in a dir name
+fn short_type_name(type_name: &str) -> String {
+    let mut short = String::new();
+    let mut ident = String::new();
+    for c in type_name.chars() {
+        match c {
+            c if c.is_alphanumeric() || c == '_' => ident.push(c),
+            ':' => ident.clear(),
+            _ => {
+                short.push_str(&ident);
+                ident.clear();
+                if !short.is_empty() && !short.ends_with('-') {
+                    short.push('-');
+                }
+            }
+        }
+    }
+    short.push_str(&ident);
+    short.trim_end_matches('-').to_string()
+}
+
 pub trait FromFileOrNew<CacheDir>: FileBytes
 where
     CacheDir: StaticCacheDir,
 {
+    /// Entries saved with a ttl (see `with_ttl`) are made new again once expired
     fn from_file_or_save_new<Fut, E>(
         file_id: &str,
         make_new: Fut,
@@ -40,17 +141,34 @@ where
         Fut: std::future::Future<Output = Result<Self, E>> + Send,
         anyhow::Error: From<E>,
     {
+        ttl::load_or_save::<Self, CacheDir, Fut, E>(file_id, None, make_new)
+    }
+
+    /// from_file_or_save_new, keyed by the inputs the entry is made from, see `keys::cache_key`
+    fn from_inputs_or_new<Fut, E>(
+        inputs: &impl Hash,
+        make_new: Fut,
+    ) -> impl Future<Output = anyhow::Result<Self>>
+    where
+        Fut: std::future::Future<Output = Result<Self, E>> + Send,
+        anyhow::Error: From<E>,
+    {
+        let file_id = keys::cache_key(inputs);
+        async move { Self::from_file_or_save_new(&file_id, make_new).await }
+    }
+
+    /// `Token::with_ttl(Duration::from_secs(3600)).from_file_or_save_new("token", fetch_token())`
+    fn with_ttl(ttl: Duration) -> ttl::WithTtl<Self, CacheDir> {
+        ttl::WithTtl::new(ttl)
+    }
+
+    /// Delete every entry of this type, i.e. its namespace
+    fn clear_cache() -> impl Future<Output = anyhow::Result<()>> {
         async {
-            let file_path = CacheDir::file_path(file_id)?;
-
-            // if file, load from file. else generate new and save to file
-            if Path::new(&file_path).exists() {
-                Self::from_file(&file_path)
-            } else {
-                let new = make_new.await.map_err(anyhow::Error::from)?;
-                fs::write(file_path, new.as_file_bytes()?).expect("Unable to write file");
-                Ok(new)
-            }
+            anyhow::ensure!(!Self::namespace().is_empty(), "no namespace to clear");
+            let dir = CacheDir::cache_dir()?.join(Self::namespace());
+            io::remove_dir(&dir).await?;
+            Ok(())
         }
     }
 
@@ -63,6 +181,23 @@ where
 // auto-implement FromFileOrNew for all FileBytes types
 impl<T: FileBytes> FromFileOrNew<GitRepoCacheDir> for T {}
 
+/// from_file_or_save_new in a cache dir chosen at runtime, e.g. from a `--cache-dir` flag:
+/// `from_file_or_save_new_in(&args.cache_dir, "token", fetch_token())`
+pub async fn from_file_or_save_new_in<T, Fut, E>(
+    location: &dyn CacheLocation,
+    file_id: &str,
+    make_new: Fut,
+) -> anyhow::Result<T>
+where
+    T: FileBytes,
+    Fut: Future<Output = Result<T, E>> + Send,
+    anyhow::Error: From<E>,
+{
+    let backend = backend::FsBackend::new(location.dir()?);
+    ttl::adopt_flat_entry::<T>(&backend, file_id).await?;
+    ttl::load_or_save_in(&backend, file_id, None, make_new).await
+}
+
 pub trait CachedOrDefault: FromFileOrNew<GitRepoCacheDir> + Default {
     fn cached_or_default(file_id: &str) -> impl Future<Output = anyhow::Result<Self>> {
         Self::from_file_or_save_new::<_, Infallible>(file_id, async { Ok(Self::default()) })
@@ -76,7 +211,33 @@ pub trait StaticCacheDir {
         let cache_dir = Self::cache_dir()?;
         Ok(cache_dir.join(path_relative))
     }
+    /// where the entry `file_id` of T is kept, under T's namespace
+    fn entry_path<T: FileBytes>(file_id: &str) -> anyhow::Result<PathBuf> {
+        let cache_dir = Self::cache_dir()?;
+        Ok(cache_dir.join(T::namespace()).join(file_id))
+    }
+}
+/// A cache dir as a value, for when it's only known at runtime. StaticCacheDir types are
+/// CacheLocations too: `&GitRepoCacheDir {}`, as well as paths and CacheConfigs.
+pub trait CacheLocation {
+    fn dir(&self) -> anyhow::Result<PathBuf>;
+}
+impl<CacheDir: StaticCacheDir> CacheLocation for CacheDir {
+    fn dir(&self) -> anyhow::Result<PathBuf> {
+        CacheDir::cache_dir()
+    }
+}
+impl CacheLocation for Path {
+    fn dir(&self) -> anyhow::Result<PathBuf> {
+        Ok(self.to_path_buf())
+    }
 }
+impl CacheLocation for PathBuf {
+    fn dir(&self) -> anyhow::Result<PathBuf> {
+        Ok(self.clone())
+    }
+}
+
 pub struct GitRepoCacheDir {}
 impl GitRepoCacheDir {
     pub fn work_dir() -> Result<PathBuf, String> {
@@ -90,10 +251,112 @@ impl GitRepoCacheDir {
     }
 }
 impl StaticCacheDir for GitRepoCacheDir {
+    /// `.cache` at the root of the git repo, unless redirected by `FILE_CACHE_DIR`
     fn cache_dir() -> anyhow::Result<PathBuf> {
+        EnvCacheDir::new("FILE_CACHE_DIR").dir()
+    }
+}
+
+/// The dir set in an env var, else the git repo's `.cache`: `EnvCacheDir::new("MYAPP_CACHE_DIR")`
+/// for each app to have its own variable
+#[derive(Debug, Clone)]
+pub struct EnvCacheDir {
+    var: String,
+    lookup: fn(&str) -> Option<OsString>,
+}
+impl EnvCacheDir {
+    pub fn new(var: &str) -> Self {
+        Self {
+            var: var.to_string(),
+            lookup: |var| std::env::var_os(var),
+        }
+    }
+    /// read the variable with `lookup` instead of from the process env, e.g. in tests
+    pub fn with_lookup(self, lookup: fn(&str) -> Option<OsString>) -> Self {
+        Self { lookup, ..self }
+    }
+    /// None if the variable is unset or empty
+    pub fn from_env(&self) -> Option<PathBuf> {
+        let dir = (self.lookup)(&self.var).filter(|dir| !dir.is_empty())?;
+        Some(PathBuf::from(dir))
+    }
+}
+impl CacheLocation for EnvCacheDir {
+    fn dir(&self) -> anyhow::Result<PathBuf> {
+        if let Some(dir) = self.from_env() {
+            return Ok(dir);
+        }
         let work_dir = GIT_WORK_DIR.as_ref().map_err(anyhow::Error::msg)?;
-        let cache_dir = work_dir.join(".cache");
-        Ok(cache_dir)
+        Ok(work_dir.join(".cache"))
+    }
+}
+
+/// Names an app's own subdirectory of a shared cache dir like XdgCacheDir, so that
+/// the apps using this crate don't read each other's entries
+pub trait AppName {
+    const APP_NAME: &'static str;
+}
+/// `$XDG_CACHE_HOME/<app>`, else `~/.cache/<app>`: for binaries run outside of a git repo
+pub struct XdgCacheDir<App>(PhantomData<App>);
+impl<App: AppName> XdgCacheDir<App> {
+    /// as a CacheLocation value
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<App: AppName> Default for XdgCacheDir<App> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<App: AppName> StaticCacheDir for XdgCacheDir<App> {
+    fn cache_dir() -> anyhow::Result<PathBuf> {
+        Ok(xdg_cache_home(|var| std::env::var_os(var))?.join(App::APP_NAME))
+    }
+}
+/// `$XDG_CACHE_HOME`, else `~/.cache`, with the variables read by `lookup`
+pub fn xdg_cache_home(lookup: impl Fn(&str) -> Option<OsString>) -> anyhow::Result<PathBuf> {
+    let non_empty = |var| lookup(var).filter(|value| !value.is_empty());
+    match non_empty("XDG_CACHE_HOME") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => {
+            Ok(PathBuf::from(non_empty("HOME").ok_or(anyhow::anyhow!("no $HOME"))?).join(".cache"))
+        }
+    }
+}
+
+/// A fresh dir under the system temp dir, deleted on drop: for tests not to write in the
+/// repo's `.cache`, nor see what other tests wrote. Use it as a CacheLocation.
+#[derive(Debug)]
+pub struct TempCacheDir {
+    dir: PathBuf,
+}
+impl TempCacheDir {
+    pub fn new() -> anyhow::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let n = NEXT.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("file-cache-{}-{n}", std::process::id()));
+            // an earlier process with the same pid may have left its dir behind: skip it
+            match std::fs::create_dir(&dir) {
+                Ok(()) => return Ok(Self { dir }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+impl CacheLocation for TempCacheDir {
+    fn dir(&self) -> anyhow::Result<PathBuf> {
+        Ok(self.dir.clone())
+    }
+}
+impl Drop for TempCacheDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
     }
 }
 
@@ -120,6 +383,9 @@ pub mod implementations {
         fn from_file_bytes(_: &[u8]) -> anyhow::Result<Self> {
             Ok(())
         }
+        fn namespace() -> String {
+            "markers".to_string()
+        }
     }
 }
 
@@ -138,9 +404,14 @@ pub mod cache_counter {
     }
     impl CacheCounter {
         pub async fn next(file_id: &str) -> anyhow::Result<Self> {
-            let mut counter = CacheCounter::cached_or_default(file_id).await?;
+            Self::next_in(&GitRepoCacheDir {}, file_id).await
+        }
+        pub async fn next_in(location: &dyn CacheLocation, file_id: &str) -> anyhow::Result<Self> {
+            let zero = async { Ok::<_, Infallible>(CacheCounter::default()) };
+            let mut counter: Self = from_file_or_save_new_in(location, file_id, zero).await?;
             counter.0 += 1;
-            counter.to_file(&GitRepoCacheDir::file_path(file_id)?)?;
+            let file_path = location.dir()?.join(ttl::entry_key::<Self>(file_id));
+            meta::rewrite(&file_path, &counter.as_file_bytes()?, Self::VERSION).await?;
             Ok(counter)
         }
     }
@@ -154,12 +425,175 @@ pub mod cache_counter {
 #[cfg(test)]
 pub mod tests {
     use super::cache_counter::CacheCounter;
+    use super::*;
     use test_utils::TestResult;
 
+    /// a cache dir under the system temp dir shared by the tests, for those that need a StaticCacheDir
+    pub struct TestCacheDir;
+    impl StaticCacheDir for TestCacheDir {
+        fn cache_dir() -> anyhow::Result<PathBuf> {
+            static CLEARED: std::sync::Once = std::sync::Once::new();
+            let cache_dir = std::env::temp_dir().join(format!("file-cache-{}", std::process::id()));
+            // left by an earlier run that had the same pid, its entries would be cache hits
+            CLEARED.call_once(|| {
+                let _ = std::fs::remove_dir_all(&cache_dir);
+            });
+            std::fs::create_dir_all(&cache_dir)?;
+            Ok(cache_dir)
+        }
+    }
+
+    struct Token(String);
+    impl FileBytes for Token {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Token(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+    impl FromFileOrNew<TestCacheDir> for Token {}
+    impl FromFileOrNew<TestCacheDir> for cache_counter::CacheCounter {}
+
+    mod other {
+        pub struct Token;
+        impl super::FileBytes for Token {
+            fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+                Ok(vec![])
+            }
+            fn from_file_bytes(_: &[u8]) -> anyhow::Result<Self> {
+                Ok(Token)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_namespaces() -> TestResult {
+        assert!(Token::namespace().starts_with("Token-"));
+        assert_ne!(Token::namespace(), other::Token::namespace());
+        assert_eq!(
+            short_type_name("a::Compressed<b::Token>"),
+            "Compressed-Token"
+        );
+        assert_eq!(short_type_name("(u8, alloc::string::String)"), "u8-String");
+        let token =
+            <Token as FromFileOrNew<TestCacheDir>>::from_file_or_save_new("shared_id", async {
+                Ok::<_, Infallible>(Token("secret".into()))
+            });
+        let count = <CacheCounter as FromFileOrNew<TestCacheDir>>::from_file_or_save_new(
+            "shared_id",
+            async { Ok::<_, Infallible>(CacheCounter(7)) },
+        );
+        assert_eq!(token.await?.0, "secret");
+        assert_eq!(count.await?.0, 7);
+
+        <Token as FromFileOrNew<TestCacheDir>>::clear_cache().await?;
+        assert!(!TestCacheDir::entry_path::<Token>("shared_id")?.exists());
+        assert!(TestCacheDir::entry_path::<CacheCounter>("shared_id")?.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flat_entry_adopted() -> TestResult {
+        let temp = TempCacheDir::new()?;
+        std::fs::write(temp.path().join("legacy"), "from before namespaces")?;
+        let token = async { Ok::<_, Infallible>(Token("new".into())) };
+        let adopted: Token = from_file_or_save_new_in(&temp, "legacy", token).await?;
+        assert_eq!(adopted.0, "from before namespaces");
+        assert!(!temp.path().join("legacy").exists());
+        assert!(temp.path().join(ttl::entry_key::<Token>("legacy")).exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_runtime_location() -> TestResult {
+        let flag_dir = TestCacheDir::file_path("from_flag")?;
+        let token = |text: &str| {
+            let token = Token(text.to_string());
+            async move { Ok::<_, Infallible>(token) }
+        };
+        let saved: Token = from_file_or_save_new_in(&flag_dir, "token", token("a")).await?;
+        let loaded: Token = from_file_or_save_new_in(&flag_dir, "token", token("b")).await?;
+        assert_eq!((saved.0.as_str(), loaded.0.as_str()), ("a", "a"));
+        assert!(flag_dir.join(ttl::entry_key::<Token>("token")).exists());
+
+        // the static dir as a value
+        let count = async { Ok::<_, Infallible>(CacheCounter(3)) };
+        let counter: CacheCounter =
+            from_file_or_save_new_in(&TestCacheDir, "runtime", count).await?;
+        assert!(TestCacheDir::entry_path::<CacheCounter>("runtime")?.exists());
+        assert_eq!(counter.0, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_cache_dir() -> TestResult {
+        fn env(var: &str) -> Option<OsString> {
+            match var {
+                "MYAPP_CACHE_DIR" => Some("/tmp/myapp".into()),
+                "EMPTY_CACHE_DIR" => Some("".into()),
+                _ => None,
+            }
+        }
+        let location = EnvCacheDir::new("MYAPP_CACHE_DIR").with_lookup(env);
+        assert_eq!(location.dir()?, PathBuf::from("/tmp/myapp"));
+        for unset in ["EMPTY_CACHE_DIR", "OTHER_CACHE_DIR"] {
+            let location = EnvCacheDir::new(unset).with_lookup(env);
+            assert_eq!(location.from_env(), None);
+            if let Ok(work_dir) = GIT_WORK_DIR.as_ref() {
+                assert_eq!(location.dir()?, work_dir.join(".cache"));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_xdg_app_name() -> TestResult {
+        struct Billing;
+        impl AppName for Billing {
+            const APP_NAME: &'static str = "billing";
+        }
+        struct Accounting;
+        impl AppName for Accounting {
+            const APP_NAME: &'static str = "accounting";
+        }
+        let xdg_cache_home = xdg_cache_home(|var| std::env::var_os(var))?;
+        assert_eq!(
+            XdgCacheDir::<Billing>::cache_dir()?,
+            xdg_cache_home.join("billing")
+        );
+        assert_eq!(
+            XdgCacheDir::<Accounting>::new().dir()?,
+            xdg_cache_home.join("accounting")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xdg_cache_home() -> TestResult {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |var: &str| {
+                let (_, value) = vars.iter().find(|(name, _)| *name == var)?;
+                Some(OsString::from(value))
+            }
+        };
+        let xdg = env(&[("XDG_CACHE_HOME", "/tmp/xdg"), ("HOME", "/home/me")]);
+        assert_eq!(xdg_cache_home(xdg)?, PathBuf::from("/tmp/xdg"));
+        let home = env(&[("XDG_CACHE_HOME", ""), ("HOME", "/home/me")]);
+        assert_eq!(xdg_cache_home(home)?, PathBuf::from("/home/me/.cache"));
+        assert!(xdg_cache_home(env(&[])).is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_counter() -> TestResult {
-        let counter = CacheCounter::next("test_counter").await?;
-        dbg!(&counter);
+        let temp = TempCacheDir::new()?;
+        assert_eq!(CacheCounter::next_in(&temp, "test_counter").await?.0, 1);
+        assert_eq!(CacheCounter::next_in(&temp, "test_counter").await?.0, 2);
+
+        let path = temp.path().to_path_buf();
+        drop(temp);
+        assert!(!path.exists());
         Ok(())
     }
 }
@@ -0,0 +1,75 @@
+use crate::{keys, ttl, FileBytes, GitRepoCacheDir, StaticCacheDir};
+use std::future::Future;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Cache the result of an expensive call in the repo's `.cache`, keyed by its inputs:
+/// `memoize(&("user", id), Some(HOUR), || fetch_user(id)).await?`.
+/// `make_new` runs only when there is no entry for `key`, or it expired after `ttl`.
+pub async fn memoize<T, F, Fut, E>(
+    key: &impl Hash,
+    ttl: Option<Duration>,
+    make_new: F,
+) -> anyhow::Result<T>
+where
+    T: FileBytes,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, E>> + Send,
+    anyhow::Error: From<E>,
+{
+    memoize_in::<GitRepoCacheDir, T, F, Fut, E>(key, ttl, make_new).await
+}
+
+/// memoize, in another cache dir
+pub async fn memoize_in<CacheDir, T, F, Fut, E>(
+    key: &impl Hash,
+    ttl: Option<Duration>,
+    make_new: F,
+) -> anyhow::Result<T>
+where
+    CacheDir: StaticCacheDir,
+    T: FileBytes,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, E>> + Send,
+    anyhow::Error: From<E>,
+{
+    let file_id = keys::cache_key(key);
+    ttl::load_or_save::<T, CacheDir, Fut, E>(&file_id, ttl, make_new()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCacheDir;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Square(u64);
+    impl FileBytes for Square {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.to_le_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Square(u64::from_le_bytes(bytes.try_into()?)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memoize() -> anyhow::Result<()> {
+        let calls = AtomicUsize::new(0);
+        let square = |n: u64| {
+            let calls = &calls;
+            async move {
+                memoize_in::<TestCacheDir, _, _, _, _>(&("square", n), None, || async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    anyhow::Ok(Square(n * n))
+                })
+                .await
+            }
+        };
+        assert_eq!(square(3).await?.0, 9);
+        assert_eq!(square(3).await?.0, 9);
+        assert_eq!(square(4).await?.0, 16);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
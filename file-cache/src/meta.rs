@@ -0,0 +1,226 @@
+use crate::io;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What's known of an entry besides its bytes, kept in a `<file_id>.meta` sidecar so the entry
+/// itself stays in its own format. One `key=value` per line, unknown keys are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMeta {
+    pub created_at: SystemTime,
+    /// None: valid until deleted
+    pub ttl: Option<Duration>,
+    /// hex sha256 of the entry's bytes, None for sidecars written before checksums
+    pub checksum: Option<String>,
+    /// FileBytes::VERSION of the type that wrote the entry, 0 without sidecar
+    pub version: u32,
+}
+impl EntryMeta {
+    /// metadata of an entry written now with these bytes
+    pub fn new(ttl: Option<Duration>, bytes: &[u8]) -> Self {
+        Self {
+            created_at: SystemTime::now(),
+            ttl,
+            checksum: Some(checksum(bytes)),
+            version: 0,
+        }
+    }
+    pub fn version(self, version: u32) -> Self {
+        Self { version, ..self }
+    }
+    /// the sidecar of the entry at `entry_path`
+    pub fn path(entry_path: &Path) -> PathBuf {
+        let mut file_name = entry_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".meta");
+        entry_path.with_file_name(file_name)
+    }
+
+    /// None for entries written without metadata
+    pub fn read(entry_path: &Path) -> anyhow::Result<Option<Self>> {
+        let meta_path = Self::path(entry_path);
+        if !meta_path.exists() {
+            return Ok(None);
+        }
+        Self::parse(&fs::read_to_string(&meta_path)?).map(Some)
+    }
+    pub fn write(&self, entry_path: &Path) -> anyhow::Result<()> {
+        io::write_blocking(&Self::path(entry_path), self.to_text()?.as_bytes())?;
+        Ok(())
+    }
+
+    /// `read` without blocking, with the tokio feature
+    pub(crate) async fn load(entry_path: &Path) -> anyhow::Result<Option<Self>> {
+        let meta_path = Self::path(entry_path);
+        if !io::exists(&meta_path).await? {
+            return Ok(None);
+        }
+        let text = String::from_utf8(io::read(&meta_path).await?)?;
+        Self::parse(&text).map(Some)
+    }
+    /// `write` without blocking, with the tokio feature
+    pub(crate) async fn save(&self, entry_path: &Path) -> anyhow::Result<()> {
+        let text = self.to_text()?;
+        io::write(&Self::path(entry_path), text.as_bytes()).await?;
+        Ok(())
+    }
+
+    pub(crate) fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut meta = Self {
+            created_at: UNIX_EPOCH,
+            ttl: None,
+            checksum: None,
+            version: 0,
+        };
+        for (key, value) in text.lines().filter_map(|line| line.split_once('=')) {
+            match key.trim() {
+                "created_at" => meta.created_at = UNIX_EPOCH + secs(value)?,
+                "ttl" => meta.ttl = Some(secs(value)?),
+                "sha256" => meta.checksum = Some(value.trim().to_string()),
+                "version" => meta.version = value.trim().parse()?,
+                _ => {}
+            }
+        }
+        Ok(meta)
+    }
+    pub(crate) fn to_text(&self) -> anyhow::Result<String> {
+        let created_at = self.created_at.duration_since(UNIX_EPOCH)?.as_secs();
+        let mut text = format!("created_at={created_at}\nversion={}\n", self.version);
+        if let Some(ttl) = self.ttl {
+            text.push_str(&format!("ttl={}\n", ttl.as_secs()));
+        }
+        if let Some(checksum) = &self.checksum {
+            text.push_str(&format!("sha256={checksum}\n"));
+        }
+        Ok(text)
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        Some(self.created_at + self.ttl?)
+    }
+    pub fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= SystemTime::now())
+    }
+
+    /// Err if the checksum doesn't match `bytes`, read from `entry_path`
+    pub fn verify(&self, entry_path: &Path, bytes: &[u8]) -> Result<(), CorruptEntry> {
+        let Some(expected) = &self.checksum else {
+            return Ok(());
+        };
+        let actual = checksum(bytes);
+        match *expected == actual {
+            true => Ok(()),
+            false => Err(CorruptEntry {
+                path: entry_path.to_path_buf(),
+                expected: expected.clone(),
+                actual,
+            }),
+        }
+    }
+}
+
+fn secs(value: &str) -> anyhow::Result<Duration> {
+    Ok(Duration::from_secs(value.trim().parse()?))
+}
+
+fn checksum(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// An entry whose bytes don't match the checksum of its sidecar, e.g. after a disk error.
+/// Returned by `FileBytes::from_file`; `from_file_or_save_new` deletes it and makes a new one,
+/// as for a miss.
+/// Tell it apart with `err.downcast_ref::<CorruptEntry>()`.
+#[derive(thiserror::Error, Debug)]
+#[error("corrupt cache entry {path:?}: sha256 {actual}, expected {expected}")]
+pub struct CorruptEntry {
+    pub path: PathBuf,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// the bytes of the entry at `path` checked against the checksum of its sidecar if any, and that sidecar
+pub(crate) fn read_verified_blocking(path: &Path) -> anyhow::Result<(Vec<u8>, Option<EntryMeta>)> {
+    let read = || -> anyhow::Result<_> { Ok((EntryMeta::read(path)?, fs::read(path)?)) };
+    let (mut meta, mut bytes) = read()?;
+    // read between the writes of another sidecar and entry: both are there on a second read
+    if let Some(Err(_)) = meta.as_ref().map(|meta| meta.verify(path, &bytes)) {
+        (meta, bytes) = read()?;
+    }
+    if let Some(Err(corrupt)) = meta.as_ref().map(|meta| meta.verify(path, &bytes)) {
+        return Err(corrupt.into());
+    }
+    Ok((bytes, meta))
+}
+
+/// Overwrite an entry outside of `from_file_or_save_new`: its sidecar gets the new checksum
+/// and version and keeps its ttl, counted from now. Created when needed to record the version.
+pub(crate) async fn rewrite(path: &Path, bytes: &[u8], version: u32) -> anyhow::Result<()> {
+    let meta = EntryMeta::load(path).await?;
+    if meta.is_some() || version != 0 {
+        let ttl = meta.and_then(|meta| meta.ttl);
+        EntryMeta::new(ttl, bytes)
+            .version(version)
+            .save(path)
+            .await?;
+    }
+    io::write(path, bytes).await?;
+    Ok(())
+}
+/// rewrite, blocking
+pub(crate) fn rewrite_blocking(path: &Path, bytes: &[u8], version: u32) -> anyhow::Result<()> {
+    let meta = EntryMeta::read(path)?;
+    if meta.is_some() || version != 0 {
+        let ttl = meta.and_then(|meta| meta.ttl);
+        EntryMeta::new(ttl, bytes).version(version).write(path)?;
+    }
+    io::write_blocking(path, bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCacheDir;
+    use crate::{FileBytes, FromFileOrNew, StaticCacheDir};
+    use std::convert::Infallible;
+
+    struct Note(String);
+    impl FileBytes for Note {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Note(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+    impl FromFileOrNew<TestCacheDir> for Note {}
+
+    #[tokio::test]
+    async fn test_corrupt_entry() -> anyhow::Result<()> {
+        let note = |text: &str| {
+            let note = Note(text.to_string());
+            async move { Ok::<_, Infallible>(note) }
+        };
+        let load =
+            |text| <Note as FromFileOrNew<TestCacheDir>>::from_file_or_save_new("note", note(text));
+        load("written").await?;
+        let path = TestCacheDir::entry_path::<Note>("note")?;
+        fs::write(&path, "flipped")?;
+
+        let err = Note::from_file(&path).err();
+        let corrupt = err.as_ref().and_then(|e| e.downcast_ref::<CorruptEntry>());
+        assert!(corrupt.is_some(), "{err:?}");
+        // a miss for from_file_or_save_new, which deletes it even if making a new one fails
+        let offline = <Note as FromFileOrNew<TestCacheDir>>::from_file_or_save_new("note", async {
+            Err::<Note, _>(anyhow::anyhow!("offline"))
+        });
+        assert!(offline.await.is_err());
+        assert!(!path.exists());
+        assert!(!EntryMeta::path(&path).exists());
+        assert_eq!(load("rewritten").await?.0, "rewritten");
+        assert_eq!(Note::from_file(&path)?.0, "rewritten");
+        Ok(())
+    }
+}
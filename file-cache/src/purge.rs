@@ -0,0 +1,344 @@
+//! Dropping entries programmatically rather than `rm -rf .cache`, and pruning the expired
+//! ones. Blocking, for maintenance
+//! tasks; every path is checked to stay inside the cache dir before anything is deleted.
+use crate::entries::{self, CacheEntry};
+use crate::meta::EntryMeta;
+use crate::{io, ttl, xdg_cache_home, CacheLocation, FileBytes, GIT_WORK_DIR};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A path that would have led outside of the cache dir, or a cache dir that can't be one
+#[derive(thiserror::Error, Debug)]
+#[error("refusing to delete {path:?}: not inside the cache dir {cache_dir:?}")]
+pub struct OutsideCacheDir {
+    pub path: PathBuf,
+    pub cache_dir: PathBuf,
+}
+
+/// Delete every entry of the cache dir, the dir itself is kept. Returns the number of files deleted.
+/// Like the others, refuses a dir holding the home, working or git dir, a shared cache or temp root
+/// like `~/.cache` or `/var/cache`, or one that doesn't look like a cache: no `cache` in its path,
+/// and not created by this crate.
+pub fn clear_all(location: &dyn CacheLocation) -> anyhow::Result<usize> {
+    let cache_dir = checked_cache_dir(location)?;
+    invalidate_under(&cache_dir, &cache_dir, "")
+}
+
+/// Delete the entry `file_id` of T and its sidecar. Ok(false) if there was none.
+pub fn invalidate<T: FileBytes>(
+    location: &dyn CacheLocation,
+    file_id: &str,
+) -> anyhow::Result<bool> {
+    let cache_dir = checked_cache_dir(location)?;
+    let path = inside(&cache_dir, &ttl::entry_key::<T>(file_id))?;
+    if !path.is_file() {
+        return Ok(false);
+    }
+    fs::remove_file(&path)?;
+    let meta_path = EntryMeta::path(&path);
+    if meta_path.exists() {
+        fs::remove_file(meta_path)?;
+    }
+    Ok(true)
+}
+
+/// Delete the entries whose path under the cache dir starts with `prefix`, sidecars included:
+/// `format!("{}/user-", Token::namespace())` for some entries of a type. Returns the number of
+/// files deleted.
+pub fn invalidate_prefix(location: &dyn CacheLocation, prefix: &str) -> anyhow::Result<usize> {
+    let cache_dir = checked_cache_dir(location)?;
+    inside(&cache_dir, prefix)?;
+    invalidate_under(&cache_dir, &cache_dir, prefix)
+}
+
+/// What a prune deleted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// entries deleted, with their sidecars
+    pub removed: usize,
+    /// size of the files deleted, sidecars included
+    pub bytes_freed: u64,
+}
+impl std::fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} entries pruned, {} bytes freed",
+            self.removed, self.bytes_freed
+        )
+    }
+}
+
+/// Delete the expired entries of the whole cache dir, and sidecars left without their entry.
+/// For a cron job or at startup, entries are otherwise only replaced once read again.
+pub fn prune(location: &dyn CacheLocation) -> anyhow::Result<PruneReport> {
+    let cache_dir = checked_cache_dir(location)?;
+    prune_under(&cache_dir, &cache_dir, CacheEntry::is_expired)
+}
+
+/// Delete the entries of T (its namespace) that are expired, or of another version that `migrate` can't read
+pub fn prune_stale<T: FileBytes>(location: &dyn CacheLocation) -> anyhow::Result<PruneReport> {
+    let cache_dir = checked_cache_dir(location)?;
+    let dir = inside(&cache_dir, &T::namespace())?;
+    prune_under(&cache_dir, &dir, |entry| {
+        let version = entry.version();
+        let unreadable =
+            || fs::read(&entry.path).map_or(true, |bytes| T::migrate(version, &bytes).is_err());
+        entry.is_expired() || (version != T::VERSION && unreadable())
+    })
+}
+
+fn prune_under(
+    cache_dir: &Path,
+    dir: &Path,
+    is_prunable: impl Fn(&CacheEntry) -> bool,
+) -> anyhow::Result<PruneReport> {
+    let mut report = PruneReport::default();
+    let found = entries::walk(cache_dir, dir)?;
+    for sidecar in found.orphan_sidecars {
+        report.bytes_freed += fs::metadata(&sidecar)?.len();
+        fs::remove_file(&sidecar)?;
+    }
+    for entry in found.entries.iter().filter(|entry| is_prunable(entry)) {
+        fs::remove_file(&entry.path)?;
+        let meta_path = EntryMeta::path(&entry.path);
+        if meta_path.exists() {
+            fs::remove_file(meta_path)?;
+        }
+        report.removed += 1;
+        report.bytes_freed += entry.size;
+    }
+    Ok(report)
+}
+
+/// the cache dir, unless it's one no cache should be in: `/`, the home dir, `~/.cache`
+fn checked_cache_dir(location: &dyn CacheLocation) -> anyhow::Result<PathBuf> {
+    checked_cache_dir_in_env(location, |var| std::env::var_os(var))
+}
+/// checked_cache_dir, with the env vars read by `env`
+fn checked_cache_dir_in_env(
+    location: &dyn CacheLocation,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> anyhow::Result<PathBuf> {
+    let cache_dir = match fs::canonicalize(location.dir()?) {
+        Ok(cache_dir) => cache_dir,
+        // nothing to delete
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return location.dir(),
+        Err(e) => return Err(e.into()),
+    };
+    // neither the home, working or git dir, the caches of every app or the temp dirs, nor one of
+    // their parents
+    let home = env("HOME").map(PathBuf::from);
+    let cwd = std::env::current_dir().ok();
+    let work_dir = GIT_WORK_DIR.as_ref().ok().cloned();
+    let shared_roots = [
+        xdg_cache_home(&env).ok(),
+        home.as_ref().map(|home| home.join("Library/Caches")),
+        Some(PathBuf::from("/Library/Caches")),
+        Some(PathBuf::from("/var/cache")),
+        Some(PathBuf::from("/var/tmp")),
+        Some(std::env::temp_dir()),
+    ];
+    let too_wide = [home, cwd, work_dir]
+        .into_iter()
+        .chain(shared_roots)
+        .flatten()
+        .filter_map(|dir| fs::canonicalize(dir).ok())
+        .any(|dir| dir.starts_with(&cache_dir));
+    // e.g. `.cache`, `~/.cache/app`, `my-app-cache`, or a dir the cache created
+    let looks_like_cache = cache_dir.components().any(|c| {
+        c.as_os_str()
+            .to_string_lossy()
+            .to_lowercase()
+            .contains("cache")
+    }) || cache_dir.join(io::CACHE_DIR_TAG).is_file();
+    match !too_wide && looks_like_cache {
+        true => Ok(cache_dir),
+        false => Err(OutsideCacheDir {
+            path: cache_dir.clone(),
+            cache_dir,
+        }
+        .into()),
+    }
+}
+
+/// `relative` joined to the cache dir, Err for absolute paths or `..` that would escape it
+fn inside(cache_dir: &Path, relative: &str) -> Result<PathBuf, OutsideCacheDir> {
+    let only_names = Path::new(relative)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    match only_names {
+        true => Ok(cache_dir.join(relative)),
+        false => Err(OutsideCacheDir {
+            path: cache_dir.join(relative),
+            cache_dir: cache_dir.to_path_buf(),
+        }),
+    }
+}
+
+/// files under `dir` whose path relative to the cache dir starts with `prefix`
+fn invalidate_under(cache_dir: &Path, dir: &Path, prefix: &str) -> anyhow::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut deleted = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if io::is_cache_dir_tag(&path) {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(cache_dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        // symlinks are deleted, not followed
+        if path.is_dir() && !path.is_symlink() {
+            let could_match =
+                prefix.starts_with(&format!("{relative}/")) || relative.starts_with(prefix);
+            if could_match {
+                deleted += invalidate_under(cache_dir, &path, prefix)?;
+            }
+            if relative.starts_with(prefix) {
+                fs::remove_dir(&path)?;
+            }
+        } else if relative.starts_with(prefix) {
+            fs::remove_file(&path)?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, CacheConfig};
+    use crate::{from_file_or_save_new_in, TempCacheDir};
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    struct Avatar(Vec<u8>);
+    impl FileBytes for Avatar {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.clone())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Avatar(bytes.to_vec()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prune() -> anyhow::Result<()> {
+        let temp = TempCacheDir::new()?;
+        let config = CacheConfig::at(&temp)?;
+        Cache::<Avatar>::new(config.clone())
+            .put("fresh", &Avatar(vec![1; 10]))
+            .await?;
+        let expiring = Cache::<Avatar>::new(config.clone().ttl(Duration::ZERO));
+        expiring.put("expired", &Avatar(vec![1; 10])).await?;
+        fs::write(
+            temp.path().join(Avatar::namespace()).join("orphan.meta"),
+            "version=0",
+        )?;
+
+        let report = prune(&temp)?;
+        assert_eq!(report.removed, 1);
+        assert!(report.bytes_freed > 10);
+        assert!(temp.path().join(Avatar::namespace()).join("fresh").exists());
+        assert!(!temp
+            .path()
+            .join(Avatar::namespace())
+            .join("orphan.meta")
+            .exists());
+
+        // Avatar's shape changed since, without a migration
+        struct NewAvatar;
+        impl FileBytes for NewAvatar {
+            const VERSION: u32 = 1;
+            fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+                Ok(vec![])
+            }
+            fn from_file_bytes(_: &[u8]) -> anyhow::Result<Self> {
+                Ok(NewAvatar)
+            }
+            fn namespace() -> String {
+                Avatar::namespace()
+            }
+        }
+        assert_eq!(prune_stale::<NewAvatar>(&temp)?.removed, 1);
+        assert!(!temp.path().join(Avatar::namespace()).join("fresh").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge() -> anyhow::Result<()> {
+        let temp = TempCacheDir::new()?;
+        for file_id in ["user-1", "user-2", "team-1"] {
+            let avatar = async { Ok::<_, Infallible>(Avatar(vec![1])) };
+            let _: Avatar = from_file_or_save_new_in(&temp, file_id, avatar).await?;
+        }
+
+        assert!(invalidate::<Avatar>(&temp, "user-1")?);
+        assert!(!invalidate::<Avatar>(&temp, "user-1")?);
+        assert_eq!(
+            invalidate_prefix(&temp, &format!("{}/user-", Avatar::namespace()))?,
+            2
+        );
+        assert!(temp
+            .path()
+            .join(Avatar::namespace())
+            .join("team-1")
+            .exists());
+
+        let escape = invalidate::<Avatar>(&temp, "../../etc/passwd").err();
+        assert!(escape.is_some_and(|e| e.is::<OutsideCacheDir>()));
+        assert!(invalidate_prefix(&temp, "/").is_err());
+        assert!(clear_all(&PathBuf::from("/")).is_err());
+
+        assert_eq!(clear_all(&temp)?, 2);
+        assert!(temp.path().exists() && fs::read_dir(temp.path())?.next().is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refused_dirs() -> anyhow::Result<()> {
+        assert!(clear_all(&PathBuf::from(".")).is_err());
+        assert!(clear_all(&std::env::current_dir()?.join("src/..")).is_err());
+
+        // e.g. FILE_CACHE_DIR=/tmp, not a dir the cache created
+        let shared = std::env::temp_dir().join(format!("purge-shared-{}", std::process::id()));
+        fs::create_dir_all(&shared)?;
+        fs::write(shared.join("notes"), "not an entry")?;
+        let refused = clear_all(&shared).err();
+        assert!(refused.is_some_and(|e| e.is::<OutsideCacheDir>()));
+        assert!(shared.join("notes").exists());
+
+        // unless the cache created it, and tagged it
+        let created = shared.join("data");
+        let avatar = async { Ok::<_, Infallible>(Avatar(vec![1])) };
+        let _: Avatar = from_file_or_save_new_in(&created, "user-1", avatar).await?;
+        assert_eq!(clear_all(&created)?, 2);
+        assert!(created.join(io::CACHE_DIR_TAG).exists());
+
+        // the cache dir of every app, with or without a cache of ours in it
+        let home = shared.join("home");
+        let app = home.join(".cache/app");
+        let avatar = async { Ok::<_, Infallible>(Avatar(vec![1])) };
+        let _: Avatar = from_file_or_save_new_in(&app, "user-1", avatar).await?;
+        fs::copy(
+            app.join(io::CACHE_DIR_TAG),
+            home.join(".cache").join(io::CACHE_DIR_TAG),
+        )?;
+        let home_var = home.clone().into_os_string();
+        let env = |var: &str| (var == "HOME").then(|| home_var.clone());
+        let xdg_root = checked_cache_dir_in_env(&home.join(".cache"), env).err();
+        assert!(xdg_root.is_some_and(|e| e.is::<OutsideCacheDir>()));
+        assert!(checked_cache_dir_in_env(&app, env).is_ok());
+        if Path::new("/var/cache").exists() {
+            assert!(checked_cache_dir_in_env(&PathBuf::from("/var/cache"), env).is_err());
+        }
+        fs::remove_dir_all(shared)?;
+        Ok(())
+    }
+}
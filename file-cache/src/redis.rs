@@ -0,0 +1,116 @@
+use crate::backend::CacheBackend;
+use crate::meta::EntryMeta;
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use std::path::PathBuf;
+
+/// Entries as redis hashes (`bytes` and `meta` fields), shared between the instances of a
+/// service. Entries with a ttl expire in redis itself, no stale entries are left behind.
+#[derive(Clone)]
+pub struct RedisBackend {
+    conn: MultiplexedConnection,
+    url: String,
+    prefix: String,
+}
+impl RedisBackend {
+    /// `redis://host:6379/0`, keys prefixed with `file-cache:`
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            conn: client.get_multiplexed_async_connection().await?,
+            url: url.to_string(),
+            prefix: "file-cache:".to_string(),
+        })
+    }
+    /// e.g. the service name, for several to share a redis
+    pub fn prefix(self, prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            ..self
+        }
+    }
+
+    fn redis_key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+}
+impl CacheBackend for RedisBackend {
+    fn location(&self) -> PathBuf {
+        PathBuf::from(&self.url).join(&self.prefix)
+    }
+
+    async fn read(&self, key: &str) -> anyhow::Result<Option<(Vec<u8>, Option<EntryMeta>)>> {
+        let (bytes, meta): (Option<Vec<u8>>, Option<String>) = (self.conn.clone())
+            .hget(self.redis_key(key), &["bytes", "meta"])
+            .await?;
+        let Some(bytes) = bytes else {
+            return Ok(None);
+        };
+        let meta = meta.as_deref().map(EntryMeta::parse).transpose()?;
+        Ok(Some((bytes, meta)))
+    }
+    async fn write(&self, key: &str, bytes: &[u8], meta: &EntryMeta) -> anyhow::Result<()> {
+        let key = self.redis_key(key);
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&key).hset_multiple(
+            &key,
+            &[
+                ("bytes", bytes.to_vec()),
+                ("meta", meta.to_text()?.into_bytes()),
+            ],
+        );
+        if let Some(ttl) = meta.ttl {
+            let millis = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+            pipe.pexpire(&key, millis);
+        }
+        pipe.query_async::<()>(&mut self.conn.clone()).await?;
+        Ok(())
+    }
+    async fn remove(&self, key: &str) -> anyhow::Result<()> {
+        (self.conn.clone())
+            .del::<_, ()>(self.redis_key(key))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::FileBytes;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    struct Session(String);
+    impl FileBytes for Session {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Session(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+
+    /// against the redis at REDIS_URL, skipped without
+    #[tokio::test]
+    async fn test_redis_backend() -> anyhow::Result<()> {
+        let Ok(url) = std::env::var("REDIS_URL") else {
+            return Ok(());
+        };
+        let prefix = format!("file-cache-test-{}:", std::process::id());
+        let backend = RedisBackend::connect(&url).await?.prefix(&prefix);
+        let sessions = Cache::<Session, _>::with_backend(backend.clone(), None);
+        sessions.put("alice", &Session("token".into())).await?;
+        assert_eq!(sessions.get("alice").await?, Some(Session("token".into())));
+        sessions.invalidate("alice").await?;
+        assert_eq!(sessions.get("alice").await?, None);
+
+        let short = Cache::<Session, _>::with_backend(backend, Some(Duration::from_millis(50)));
+        short.put("bob", &Session("token".into())).await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let key = crate::ttl::entry_key::<Session>("bob");
+        assert_eq!(short.backend().read(&key).await?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use crate::backend::CacheBackend;
+use crate::meta::EntryMeta;
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Entries as objects of an S3-compatible bucket (AWS, MinIO, R2...), their metadata in a
+/// `<key>.meta` object beside them like FsBackend's sidecars.
+/// Usually behind `WriteThrough` so each machine keeps a copy on disk.
+#[derive(Debug, Clone)]
+pub struct S3Backend {
+    store: Arc<dyn ObjectStore>,
+    location: PathBuf,
+    prefix: String,
+}
+impl S3Backend {
+    /// Credentials and endpoint from the `AWS_*` env vars: AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY,
+    /// AWS_REGION, and AWS_ENDPOINT for stores other than AWS
+    pub fn from_env(bucket: &str) -> anyhow::Result<Self> {
+        let store = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()?;
+        Ok(Self::new(store, format!("s3://{bucket}")))
+    }
+    /// any object store, `location` naming it in CacheStats
+    pub fn new(store: impl ObjectStore, location: impl Into<PathBuf>) -> Self {
+        Self {
+            store: Arc::new(store),
+            location: location.into(),
+            prefix: String::new(),
+        }
+    }
+    /// entries under `prefix/` rather than at the root of the bucket
+    pub fn prefix(self, prefix: &str) -> Self {
+        Self {
+            location: self.location.join(prefix),
+            prefix: prefix.trim_matches('/').to_string(),
+            ..self
+        }
+    }
+
+    fn object_path(&self, key: &str) -> ObjectPath {
+        match self.prefix.is_empty() {
+            true => ObjectPath::from(key),
+            false => ObjectPath::from(format!("{}/{key}", self.prefix)),
+        }
+    }
+    /// None if there's no such object
+    async fn get(&self, path: &ObjectPath) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.store.get(path).await {
+            Ok(object) => Ok(Some(object.bytes().await?.to_vec())),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    async fn delete(&self, path: &ObjectPath) -> anyhow::Result<()> {
+        match self.store.delete(path).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+impl CacheBackend for S3Backend {
+    fn location(&self) -> PathBuf {
+        self.location.clone()
+    }
+
+    async fn read(&self, key: &str) -> anyhow::Result<Option<(Vec<u8>, Option<EntryMeta>)>> {
+        let Some(bytes) = self.get(&self.object_path(key)).await? else {
+            return Ok(None);
+        };
+        let meta = self.get(&self.object_path(&format!("{key}.meta"))).await?;
+        let meta = meta
+            .map(|meta| EntryMeta::parse(&String::from_utf8(meta)?))
+            .transpose()?;
+        Ok(Some((bytes, meta)))
+    }
+    async fn write(&self, key: &str, bytes: &[u8], meta: &EntryMeta) -> anyhow::Result<()> {
+        let meta_path = self.object_path(&format!("{key}.meta"));
+        (self.store)
+            .put(&self.object_path(key), PutPayload::from(bytes.to_vec()))
+            .await?;
+        (self.store)
+            .put(&meta_path, PutPayload::from(meta.to_text()?))
+            .await?;
+        Ok(())
+    }
+    async fn remove(&self, key: &str) -> anyhow::Result<()> {
+        self.delete(&self.object_path(key)).await?;
+        self.delete(&self.object_path(&format!("{key}.meta"))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{FsBackend, WriteThrough};
+    use crate::cache::Cache;
+    use crate::tests::TestCacheDir;
+    use crate::{FileBytes, StaticCacheDir};
+    use object_store::memory::InMemory;
+
+    #[derive(Debug, PartialEq)]
+    struct Build(String);
+    impl FileBytes for Build {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Build(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_s3_write_through() -> anyhow::Result<()> {
+        let bucket = S3Backend::new(InMemory::new(), "memory://ci").prefix("cache");
+        let machine = |dir: &str| -> anyhow::Result<_> {
+            let local = FsBackend::new(TestCacheDir::file_path(dir)?);
+            Ok(Cache::<Build, _>::with_backend(
+                WriteThrough::new(local, bucket.clone()),
+                None,
+            ))
+        };
+        let (first, second) = (machine("s3_first")?, machine("s3_second")?);
+
+        let key = crate::ttl::entry_key::<Build>("artifact");
+        first.put("artifact", &Build("v1".into())).await?;
+        assert!(bucket.read(&key).await?.is_some());
+        // the second machine gets it from the bucket, and keeps a copy
+        assert_eq!(second.get("artifact").await?, Some(Build("v1".into())));
+        assert!(second.backend().local.path(&key).exists());
+
+        second.invalidate("artifact").await?;
+        assert_eq!(bucket.read(&key).await?, None);
+        Ok(())
+    }
+}
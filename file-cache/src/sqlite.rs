@@ -0,0 +1,116 @@
+use crate::backend::CacheBackend;
+use crate::meta::EntryMeta;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Every entry a row of one sqlite database, for caches of many small entries where a file
+/// each thrashes the filesystem. Queries block the calling thread, they're quick ones.
+pub struct SqliteBackend {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+impl SqliteBackend {
+    /// the database at `path` and its parent dirs are created if missing
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE IF NOT EXISTS entries (
+                key TEXT PRIMARY KEY,
+                bytes BLOB NOT NULL,
+                meta TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self {
+            path,
+            conn: Mutex::new(conn),
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+impl CacheBackend for SqliteBackend {
+    fn location(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    async fn read(&self, key: &str) -> anyhow::Result<Option<(Vec<u8>, Option<EntryMeta>)>> {
+        let row = self
+            .lock()
+            .query_row(
+                "SELECT bytes, meta FROM entries WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        let Some((bytes, meta)) = row else {
+            return Ok(None);
+        };
+        Ok(Some((bytes, Some(EntryMeta::parse(&meta)?))))
+    }
+    async fn write(&self, key: &str, bytes: &[u8], meta: &EntryMeta) -> anyhow::Result<()> {
+        self.lock().execute(
+            "INSERT OR REPLACE INTO entries (key, bytes, meta) VALUES (?1, ?2, ?3)",
+            params![key, bytes, meta.to_text()?],
+        )?;
+        Ok(())
+    }
+    async fn remove(&self, key: &str) -> anyhow::Result<()> {
+        self.lock()
+            .execute("DELETE FROM entries WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::tests::TestCacheDir;
+    use crate::{FileBytes, StaticCacheDir};
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    struct Row(u32);
+    impl FileBytes for Row {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.to_le_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Row(u32::from_le_bytes(bytes.try_into()?)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_backend() -> anyhow::Result<()> {
+        let backend = SqliteBackend::open(TestCacheDir::file_path("sqlite/cache.db")?)?;
+        let cache = Cache::<Row, _>::with_backend(backend, None);
+        for n in 0..100 {
+            cache.put(&n.to_string(), &Row(n)).await?;
+        }
+        assert_eq!(cache.get("42").await?, Some(Row(42)));
+        let row = cache.get_or_insert_with("42", async { Ok::<_, Infallible>(Row(0)) });
+        assert_eq!(row.await?, Row(42));
+        cache.invalidate("42").await?;
+        assert_eq!(cache.get("42").await?, None);
+
+        let expiring = Cache::<Row, _>::with_backend(
+            SqliteBackend::open(cache.backend().path())?,
+            Some(Duration::ZERO),
+        );
+        expiring.put("1", &Row(1)).await?;
+        assert_eq!(expiring.get("1").await?, None);
+        Ok(())
+    }
+}
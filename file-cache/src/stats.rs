@@ -0,0 +1,176 @@
+use crate::StaticCacheDir;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+  static ref COUNTERS: Mutex<HashMap<PathBuf, CacheStats>> = Mutex::default();
+}
+
+/// What a cache dir did since the process started, to tell whether it's earning its keep
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// entries loaded from file
+    pub hits: u64,
+    /// entries made new because missing, expired or of a version that doesn't migrate
+    pub misses: u64,
+    pub writes: u64,
+    /// expired or stale entries replaced
+    pub evictions: u64,
+    /// size of the files under the cache dir now, sidecars included
+    pub bytes_stored: u64,
+}
+impl CacheStats {
+    pub fn of<CacheDir: StaticCacheDir>() -> anyhow::Result<Self> {
+        Self::for_dir(&CacheDir::cache_dir()?)
+    }
+    pub fn for_dir(cache_dir: &Path) -> anyhow::Result<Self> {
+        let counters = lock().get(cache_dir).cloned().unwrap_or_default();
+        Ok(Self {
+            bytes_stored: dir_size(cache_dir)?,
+            ..counters
+        })
+    }
+    /// None before the first read
+    pub fn hit_rate(&self) -> Option<f64> {
+        let reads = self.hits + self.misses;
+        (reads > 0).then(|| self.hits as f64 / reads as f64)
+    }
+}
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} hits, {} misses", self.hits, self.misses)?;
+        if let Some(hit_rate) = self.hit_rate() {
+            write!(f, " ({:.0}% hit rate)", hit_rate * 100.0)?;
+        }
+        write!(
+            f,
+            ", {} writes, {} evictions, {} bytes stored",
+            self.writes, self.evictions, self.bytes_stored
+        )
+    }
+}
+
+pub(crate) enum CacheEvent {
+    Hit,
+    /// `evicted`: there was an entry, expired or stale
+    Miss {
+        evicted: bool,
+    },
+    Write,
+}
+pub(crate) fn record(cache_dir: &Path, event: CacheEvent) {
+    let mut counters = lock();
+    let stats = counters.entry(cache_dir.to_path_buf()).or_default();
+    match event {
+        CacheEvent::Hit => stats.hits += 1,
+        CacheEvent::Miss { evicted } => {
+            stats.misses += 1;
+            stats.evictions += u64::from(evicted);
+        }
+        CacheEvent::Write => stats.writes += 1,
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, HashMap<PathBuf, CacheStats>> {
+    COUNTERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += match metadata.is_dir() {
+            true => dir_size(&entry.path())?,
+            false => metadata.len(),
+        };
+    }
+    Ok(size)
+}
+
+/// Print the stats of `CacheDir` to stderr every `interval`, until the task is aborted
+#[cfg(feature = "tokio")]
+pub fn log_every<CacheDir: StaticCacheDir>(
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            let stats = CacheDir::cache_dir()
+                .and_then(|dir| CacheStats::for_dir(&dir).map(|stats| (dir, stats)));
+            match stats {
+                Ok((dir, stats)) => eprintln!("file-cache {}: {stats}", dir.display()),
+                Err(e) => eprintln!("file-cache: no stats, {e}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileBytes, FromFileOrNew};
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    /// a dir of its own, for other tests not to count
+    struct StatsCacheDir;
+    impl StaticCacheDir for StatsCacheDir {
+        fn cache_dir() -> anyhow::Result<PathBuf> {
+            static CLEARED: std::sync::Once = std::sync::Once::new();
+            let cache_dir =
+                std::env::temp_dir().join(format!("file-cache-stats-{}", std::process::id()));
+            // left by an earlier run that had the same pid, it would count its entries
+            CLEARED.call_once(|| {
+                let _ = std::fs::remove_dir_all(&cache_dir);
+            });
+            std::fs::create_dir_all(&cache_dir)?;
+            Ok(cache_dir)
+        }
+    }
+
+    struct Answer(u8);
+    impl FileBytes for Answer {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(vec![self.0])
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Answer(bytes[0]))
+        }
+    }
+    impl FromFileOrNew<StatsCacheDir> for Answer {}
+
+    #[tokio::test]
+    async fn test_cache_stats() -> anyhow::Result<()> {
+        let compute = || async { Ok::<_, Infallible>(Answer(42)) };
+        for _ in 0..3 {
+            <Answer as FromFileOrNew<StatsCacheDir>>::from_file_or_save_new("answer", compute())
+                .await?;
+        }
+        <Answer as FromFileOrNew<StatsCacheDir>>::with_ttl(Duration::ZERO)
+            .from_file_or_save_new("expiring", compute())
+            .await?;
+        <Answer as FromFileOrNew<StatsCacheDir>>::from_file_or_save_new("expiring", compute())
+            .await?;
+
+        let stats = CacheStats::of::<StatsCacheDir>()?;
+        let expected = CacheStats {
+            hits: 2,
+            misses: 3,
+            writes: 3,
+            evictions: 1,
+            bytes_stored: stats.bytes_stored,
+        };
+        assert_eq!(stats, expected);
+        assert!(stats
+            .to_string()
+            .starts_with("2 hits, 3 misses (40% hit rate)"));
+        Ok(())
+    }
+}
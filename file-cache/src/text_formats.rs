@@ -0,0 +1,65 @@
+//! Entries in a format people can read and edit, for cached config and state
+use crate::FileBytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// An entry stored as TOML: `Toml<Settings>` in place of `Settings`.
+/// A wrapper rather than a marker trait, so TOML, YAML, Compressed... each have their FileBytes impl.
+#[cfg(feature = "toml")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Toml<T>(pub T);
+#[cfg(feature = "toml")]
+impl<T: Serialize + DeserializeOwned> FileBytes for Toml<T> {
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(toml::to_string_pretty(&self.0)?.into_bytes())
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(Toml(toml::from_str(std::str::from_utf8(bytes)?)?))
+    }
+}
+
+/// An entry stored as YAML: `Yaml<State>` in place of `State`
+#[cfg(feature = "yaml")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Yaml<T>(pub T);
+#[cfg(feature = "yaml")]
+impl<T: Serialize + DeserializeOwned> FileBytes for Yaml<T> {
+    fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(serde_yaml::to_string(&self.0)?.into_bytes())
+    }
+    fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(Yaml(serde_yaml::from_slice(bytes)?))
+    }
+}
+
+#[cfg(all(test, feature = "toml", feature = "yaml"))]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Settings {
+        api_url: String,
+        retries: u32,
+    }
+
+    #[test]
+    fn test_text_formats() -> anyhow::Result<()> {
+        let settings = Settings {
+            api_url: "https://api.pets.com".to_string(),
+            retries: 3,
+        };
+        let toml = Toml(settings.clone()).as_file_bytes()?;
+        assert_eq!(
+            String::from_utf8(toml.clone())?,
+            "api_url = \"https://api.pets.com\"\nretries = 3\n"
+        );
+        assert_eq!(Toml::<Settings>::from_file_bytes(&toml)?.0, settings);
+
+        let edited = b"api_url: https://api.pets.com\nretries: 5\n";
+        assert_eq!(Yaml::<Settings>::from_file_bytes(edited)?.0.retries, 5);
+        let yaml = Yaml(settings.clone()).as_file_bytes()?;
+        assert_eq!(Yaml::<Settings>::from_file_bytes(&yaml)?.0, settings);
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+use crate::backend::{CacheBackend, FsBackend};
+use crate::meta::EntryMeta;
+use crate::stats::{self, CacheEvent};
+use crate::{io, keys, FileBytes, StaticCacheDir};
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// See FromFileOrNew::with_ttl
+pub struct WithTtl<T, CacheDir> {
+    ttl: Duration,
+    _entry: PhantomData<fn() -> (T, CacheDir)>,
+}
+impl<T: FileBytes, CacheDir: StaticCacheDir> WithTtl<T, CacheDir> {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            _entry: PhantomData,
+        }
+    }
+
+    /// like FromFileOrNew::from_file_or_save_new, the new entry expiring after the ttl
+    pub async fn from_file_or_save_new<Fut, E>(
+        &self,
+        file_id: &str,
+        make_new: Fut,
+    ) -> anyhow::Result<T>
+    where
+        Fut: Future<Output = Result<T, E>> + Send,
+        anyhow::Error: From<E>,
+    {
+        load_or_save::<T, CacheDir, Fut, E>(file_id, Some(self.ttl), make_new).await
+    }
+    /// like FromFileOrNew::from_inputs_or_new, the new entry expiring after the ttl
+    pub async fn from_inputs_or_new<Fut, E>(
+        &self,
+        inputs: &impl Hash,
+        make_new: Fut,
+    ) -> anyhow::Result<T>
+    where
+        Fut: Future<Output = Result<T, E>> + Send,
+        anyhow::Error: From<E>,
+    {
+        let file_id = keys::cache_key(inputs);
+        self.from_file_or_save_new(&file_id, make_new).await
+    }
+
+    /// Like from_file_or_save_new, except that an expired entry is returned right away while
+    /// `make_new` refreshes it in the background, for callers that prefer stale data to waiting.
+    /// Only missing entries (or of a version that doesn't migrate) wait for `make_new`.
+    /// Comes with the handle of the refresh if this call started one: a failed refresh leaves the
+    /// stale entry for the next call to retry, await the handle to see why it failed.
+    #[cfg(feature = "tokio")]
+    pub async fn stale_while_revalidate<Fut, E>(
+        &self,
+        file_id: &str,
+        make_new: Fut,
+    ) -> anyhow::Result<(T, Option<tokio::task::JoinHandle<anyhow::Result<()>>>)>
+    where
+        T: Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        anyhow::Error: From<E>,
+    {
+        let backend = FsBackend::of::<CacheDir>()?;
+        let key = entry_key::<T>(file_id);
+        let Some((entry, expired)) = load_stale::<T>(&backend, &key).await? else {
+            let new = load_or_save_in(&backend, file_id, Some(self.ttl), make_new).await?;
+            return Ok((new, None));
+        };
+        stats::record(&backend.location(), CacheEvent::Hit);
+        if !expired {
+            return Ok((entry, None));
+        }
+        // one refresh at a time per entry, the other callers get the stale one meanwhile
+        let Some(refreshing) = Refreshing::start(backend.path(&key)) else {
+            return Ok((entry, None));
+        };
+        let ttl = self.ttl;
+        let refresh = tokio::spawn(async move {
+            let _refreshing = refreshing;
+            let new = make_new.await.map_err(anyhow::Error::from)?;
+            save(&backend, &key, Some(ttl), &new).await
+        });
+        Ok((entry, Some(refresh)))
+    }
+}
+
+/// entries being refreshed by stale_while_revalidate
+#[cfg(feature = "tokio")]
+type PathSet = std::collections::HashSet<std::path::PathBuf>;
+#[cfg(feature = "tokio")]
+lazy_static::lazy_static! {
+  static ref REFRESHING: std::sync::Mutex<PathSet> = Default::default();
+}
+#[cfg(feature = "tokio")]
+fn lock_refreshing() -> std::sync::MutexGuard<'static, PathSet> {
+    REFRESHING.lock().unwrap_or_else(|e| e.into_inner())
+}
+/// An entry in REFRESHING until dropped, also when its refresh panics
+#[cfg(feature = "tokio")]
+struct Refreshing(std::path::PathBuf);
+#[cfg(feature = "tokio")]
+impl Refreshing {
+    /// None if already being refreshed
+    fn start(path: std::path::PathBuf) -> Option<Self> {
+        lock_refreshing()
+            .insert(path.clone())
+            .then_some(Refreshing(path))
+    }
+}
+#[cfg(feature = "tokio")]
+impl Drop for Refreshing {
+    fn drop(&mut self) {
+        lock_refreshing().remove(&self.0);
+    }
+}
+
+/// the entry even if expired, and whether it is, None if missing, corrupt or of another version
+#[cfg(feature = "tokio")]
+async fn load_stale<T: FileBytes>(
+    backend: &impl CacheBackend,
+    key: &str,
+) -> anyhow::Result<Option<(T, bool)>> {
+    let Some((bytes, meta)) = backend.read(key).await? else {
+        return Ok(None);
+    };
+    let path = backend.location().join(key);
+    if meta
+        .as_ref()
+        .is_some_and(|meta| meta.verify(&path, &bytes).is_err())
+    {
+        return Ok(None);
+    }
+    let expired = meta.as_ref().is_some_and(EntryMeta::is_expired);
+    match meta.map_or(0, |meta| meta.version) == T::VERSION {
+        true => Ok(Some((T::from_file_bytes(&bytes)?, expired))),
+        false => Ok(None),
+    }
+}
+
+/// load the entry unless missing or expired, else make a new one and save it, with its metadata
+pub(crate) async fn load_or_save<T, CacheDir, Fut, E>(
+    file_id: &str,
+    ttl: Option<Duration>,
+    make_new: Fut,
+) -> anyhow::Result<T>
+where
+    T: FileBytes,
+    CacheDir: StaticCacheDir,
+    Fut: Future<Output = Result<T, E>> + Send,
+    anyhow::Error: From<E>,
+{
+    let backend = FsBackend::of::<CacheDir>()?;
+    adopt_flat_entry::<T>(&backend, file_id).await?;
+    load_or_save_in(&backend, file_id, ttl, make_new).await
+}
+
+/// Entries used to be kept right in the cache dir, before namespaces: one found at `file_id`
+/// is moved under T's namespace, with its sidecar, instead of being made anew
+pub(crate) async fn adopt_flat_entry<T: FileBytes>(
+    backend: &FsBackend,
+    file_id: &str,
+) -> anyhow::Result<()> {
+    let key = entry_key::<T>(file_id);
+    let (flat, namespaced) = (backend.path(file_id), backend.path(&key));
+    if key == file_id || !flat.is_file() || io::exists(&namespaced).await? {
+        return Ok(());
+    }
+    if let Some(parent) = namespaced.parent() {
+        io::create_dir_all(parent).await?;
+    }
+    for (from, to) in [
+        (EntryMeta::path(&flat), EntryMeta::path(&namespaced)),
+        (flat, namespaced),
+    ] {
+        match io::rename(&from, &to).await {
+            // no sidecar, or adopted by another caller meanwhile
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+/// load_or_save, in any backend
+pub(crate) async fn load_or_save_in<T, Fut, E>(
+    backend: &impl CacheBackend,
+    file_id: &str,
+    ttl: Option<Duration>,
+    make_new: Fut,
+) -> anyhow::Result<T>
+where
+    T: FileBytes,
+    Fut: Future<Output = Result<T, E>> + Send,
+    anyhow::Error: From<E>,
+{
+    let key = entry_key::<T>(file_id);
+
+    // if file, load from file. else generate new and save to file
+    if let Some(entry) = load(backend, &key).await? {
+        return Ok(entry);
+    }
+    let new = make_new.await.map_err(anyhow::Error::from)?;
+    save(backend, &key, ttl, &new).await?;
+    Ok(new)
+}
+
+/// key of the entry `file_id` of T in a backend, under T's namespace
+pub(crate) fn entry_key<T: FileBytes>(file_id: &str) -> String {
+    match T::namespace() {
+        namespace if namespace.is_empty() => file_id.to_string(),
+        namespace => format!("{namespace}/{file_id}"),
+    }
+}
+
+/// None if missing, expired, of another version that doesn't migrate, or not matching its
+/// checksum. Corrupt entries are deleted, the others left in place for the caller to overwrite.
+pub(crate) async fn load<T: FileBytes>(
+    backend: &impl CacheBackend,
+    key: &str,
+) -> anyhow::Result<Option<T>> {
+    let location = backend.location();
+    let entry = backend.read(key).await?;
+    let expired = entry
+        .as_ref()
+        .and_then(|(_, meta)| meta.as_ref())
+        .is_some_and(EntryMeta::is_expired);
+
+    let mut evicted = expired;
+    if let Some((bytes, meta)) = entry.filter(|_| !expired) {
+        let corrupt = meta
+            .as_ref()
+            .map(|meta| meta.verify(&location.join(key), &bytes));
+        if let Some(Err(_)) = corrupt {
+            backend.remove(key).await?;
+            stats::record(&location, CacheEvent::Miss { evicted: true });
+            return Ok(None);
+        }
+        let version = meta.as_ref().map_or(0, |meta| meta.version);
+        if version == T::VERSION {
+            stats::record(&location, CacheEvent::Hit);
+            return T::from_file_bytes(&bytes).map(Some);
+        }
+        // an older shape: migrated and saved back, or dropped for a new one
+        if let Ok(migrated) = T::migrate(version, &bytes) {
+            stats::record(&location, CacheEvent::Hit);
+            let ttl = meta.and_then(|meta| meta.ttl);
+            save(backend, key, ttl, &migrated).await?;
+            return Ok(Some(migrated));
+        }
+        evicted = true;
+    }
+    stats::record(&location, CacheEvent::Miss { evicted });
+    Ok(None)
+}
+
+/// write the entry and its metadata, replacing any previous one
+pub(crate) async fn save<T: FileBytes>(
+    backend: &impl CacheBackend,
+    key: &str,
+    ttl: Option<Duration>,
+    entry: &T,
+) -> anyhow::Result<()> {
+    let bytes = entry.as_file_bytes()?;
+    let meta = EntryMeta::new(ttl, &bytes).version(T::VERSION);
+    backend.write(key, &bytes, &meta).await?;
+    stats::record(&backend.location(), CacheEvent::Write);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCacheDir;
+    use crate::FromFileOrNew;
+    use crate::StaticCacheDir;
+    use std::convert::Infallible;
+
+    #[derive(Debug, PartialEq)]
+    struct Token(String);
+    impl FileBytes for Token {
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            Ok(Token(String::from_utf8(bytes.to_vec())?))
+        }
+    }
+    impl FromFileOrNew<TestCacheDir> for Token {}
+
+    #[tokio::test]
+    async fn test_ttl_expiry() -> anyhow::Result<()> {
+        let fetch = |token: &str| {
+            let token = Token(token.to_string());
+            async move { Ok::<_, Infallible>(token) }
+        };
+        let hour = <Token as FromFileOrNew<TestCacheDir>>::with_ttl(Duration::from_secs(3600));
+        let expired = <Token as FromFileOrNew<TestCacheDir>>::with_ttl(Duration::ZERO);
+
+        let first = hour.from_file_or_save_new("ttl_token", fetch("a")).await?;
+        let cached = hour.from_file_or_save_new("ttl_token", fetch("b")).await?;
+        assert_eq!((first.0.as_str(), cached.0.as_str()), ("a", "a"));
+        let meta = EntryMeta::read(&TestCacheDir::entry_path::<Token>("ttl_token")?)?;
+        assert_eq!(meta.and_then(|m| m.ttl), Some(Duration::from_secs(3600)));
+
+        expired
+            .from_file_or_save_new("ttl_expired", fetch("c"))
+            .await?;
+        let refreshed = expired
+            .from_file_or_save_new("ttl_expired", fetch("d"))
+            .await?;
+        assert_eq!(refreshed.0, "d");
+        // expired entries expire for plain reads too, the new one saved without ttl
+        let plain = <Token as FromFileOrNew<TestCacheDir>>::from_file_or_save_new(
+            "ttl_expired",
+            fetch("e"),
+        )
+        .await?;
+        assert_eq!(plain.0, "e");
+        let meta = EntryMeta::read(&TestCacheDir::entry_path::<Token>("ttl_expired")?)?;
+        assert_eq!(meta.map(|m| m.ttl), Some(None));
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_stale_while_revalidate() -> anyhow::Result<()> {
+        let fetch = |token: &str| {
+            let token = Token(token.to_string());
+            async move { Ok::<_, Infallible>(token) }
+        };
+        let expired = <Token as FromFileOrNew<TestCacheDir>>::with_ttl(Duration::ZERO);
+        expired.from_file_or_save_new("swr", fetch("old")).await?;
+
+        let (stale, refresh) = expired.stale_while_revalidate("swr", fetch("new")).await?;
+        assert_eq!(stale.0, "old");
+        refresh.expect("refresh started").await??;
+        let path = TestCacheDir::entry_path::<Token>("swr")?;
+        assert_eq!(std::fs::read(&path)?, b"new");
+
+        // a panicking refresh doesn't keep the entry from being refreshed again
+        let panics = async { panic!("refresh panicked") as Result<Token, Infallible> };
+        let (_, refresh) = expired.stale_while_revalidate("swr", panics).await?;
+        assert!(refresh.expect("refresh started").await.is_err());
+        let (stale, refresh) = expired
+            .stale_while_revalidate("swr", fetch("newer"))
+            .await?;
+        assert_eq!(stale.0, "new");
+        refresh.expect("refresh started").await??;
+        assert_eq!(std::fs::read(&path)?, b"newer");
+        Ok(())
+    }
+
+    /// was a bare name in version 1
+    #[derive(Debug, PartialEq)]
+    struct Profile {
+        name: String,
+        age: u8,
+    }
+    impl FileBytes for Profile {
+        const VERSION: u32 = 2;
+        fn as_file_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            Ok(format!("{};{}", self.name, self.age).into_bytes())
+        }
+        fn from_file_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+            let text = String::from_utf8(bytes.to_vec())?;
+            let (name, age) = text.split_once(';').ok_or(anyhow::anyhow!("no age"))?;
+            Ok(Profile {
+                name: name.to_string(),
+                age: age.parse()?,
+            })
+        }
+        fn migrate(old_version: u32, bytes: &[u8]) -> anyhow::Result<Self> {
+            anyhow::ensure!(old_version == 1, "no migration from {old_version}");
+            Ok(Profile {
+                name: String::from_utf8(bytes.to_vec())?,
+                age: 0,
+            })
+        }
+    }
+    impl FromFileOrNew<TestCacheDir> for Profile {}
+
+    #[tokio::test]
+    async fn test_version_migration() -> anyhow::Result<()> {
+        let write_old = |file_id: &str, version: u32| -> anyhow::Result<_> {
+            let path = TestCacheDir::entry_path::<Profile>(file_id)?;
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, "ada")?;
+            EntryMeta::new(None, b"ada").version(version).write(&path)?;
+            Ok(path)
+        };
+        let fetch = || async {
+            Ok::<_, Infallible>(Profile {
+                name: "new".into(),
+                age: 36,
+            })
+        };
+        let load = |file_id| {
+            <Profile as FromFileOrNew<TestCacheDir>>::from_file_or_save_new(file_id, fetch())
+        };
+
+        let path = write_old("profile_v1", 1)?;
+        assert_eq!(Profile::from_file(&path)?.name, "ada");
+        let migrated = load("profile_v1").await?;
+        assert_eq!((migrated.name.as_str(), migrated.age), ("ada", 0));
+        assert_eq!(EntryMeta::read(&path)?.map(|m| m.version), Some(2));
+        assert_eq!(std::fs::read(&path)?, b"ada;0");
+
+        // entries from before versions don't migrate: made new instead
+        let path = write_old("profile_v0", 0)?;
+        assert!(Profile::from_file(&path).is_err());
+        assert_eq!(load("profile_v0").await?.name, "new");
+        assert_eq!(EntryMeta::read(&path)?.map(|m| m.version), Some(2));
+        Ok(())
+    }
+}